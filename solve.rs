@@ -1,23 +1,36 @@
 extern crate collections;
+extern crate getopts;
+extern crate serialize;
+extern crate sync;
+extern crate time;
+#[cfg(petgraph)] extern crate petgraph;
 use std::io::File;
-use collections::HashMap;
-use std::ascii::StrAsciiExt;
+use collections::{HashMap, HashSet, TreeMap, RingBuf, Deque};
+use getopts::{optopt, optflag, optmulti, getopts, OptGroup, Matches};
+use serialize::json;
+use serialize::json::ToJson;
+use serialize::Decodable;
+use std::sync::atomics::{AtomicBool, SeqCst};
+use sync::Arc;
 
-#[deriving(Eq, Clone)]
+#[deriving(Eq, TotalEq, Hash, Clone, Show)]
 enum TileData<'a> {
   Fixed(char),
+  Blocked,
   NoWords,
   OneWord(char, &'a str),
-  TwoWords(char, &'a str, &'a str)
+  TwoWords(char, &'a str, &'a str),
+  // a waypoint that some other word passes through on its way
+  Through(char, &'a str)
 }
 
-#[deriving(Eq, Clone)]
+#[deriving(Eq, TotalEq, Hash, Clone, Show, Encodable)]
 struct Point {
   x: int,
   y: int
 }
 
-#[deriving(Eq, Clone)]
+#[deriving(Eq, TotalEq, Hash, Clone)]
 struct Grid<T> {
   width: int,
   height: int,
@@ -27,13 +40,283 @@ struct Grid<T> {
 type CrosswordGrid<'a> = Grid<TileData<'a>>;
 type StringGrid = Grid<char>;
 
+static FOUR_WAYS: [(int, int), ..4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+static EIGHT_WAYS: [(int, int), ..8] = [(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)];
+static KNIGHT_MOVES: [(int, int), ..8] = [(-2, -1), (-2, 1), (2, -1), (2, 1), (-1, -2), (1, -2), (-1, 2), (1, 2)];
+
 impl Point {
   #[inline(always)] fn offset(self, x: int, y: int) -> Point {
-    Point { x: self.x - x, y: self.y - y }
+    Point { x: self.x + x, y: self.y + y }
   }
   #[inline(always)] fn dist(self, other: Point) -> int {
     return std::num::abs(other.x - self.x) + std::num::abs(other.y - self.y);
   }
+  #[inline(always)] fn chebyshev(self, other: Point) -> int {
+    std::cmp::max(std::num::abs(other.x - self.x), std::num::abs(other.y - self.y))
+  }
+}
+
+// A lower bound on the moves between two points that holds for any offset
+// set: no single move closes more distance than the longest offset does, in
+// either metric.  When every offset changes x+y by an odd amount the parity
+// of x+y also flips on every move.
+#[deriving(Clone)]
+struct StepBound {
+  manhattan: int,
+  chebyshev: int,
+  parity: bool
+}
+
+impl StepBound {
+  fn for_offsets(offsets: &[(int, int)]) -> Option<StepBound> {
+    if offsets.is_empty() {
+      return None;
+    }
+    let mut bound = StepBound { manhattan: 0, chebyshev: 0, parity: true };
+    for &(x, y) in offsets.iter() {
+      let (x, y) = (std::num::abs(x), std::num::abs(y));
+      bound.manhattan = std::cmp::max(bound.manhattan, x + y);
+      bound.chebyshev = std::cmp::max(bound.chebyshev, std::cmp::max(x, y));
+      bound.parity = bound.parity && (x + y) % 2 == 1;
+    }
+    Some(bound)
+  }
+  #[inline(always)] fn min_steps(&self, a: Point, b: Point) -> int {
+    let by_manhattan = (a.dist(b) + self.manhattan - 1) / self.manhattan;
+    let by_chebyshev = (a.chebyshev(b) + self.chebyshev - 1) / self.chebyshev;
+    std::cmp::max(by_manhattan, by_chebyshev)
+  }
+  #[inline(always)] fn parity_ok(&self, a: Point, b: Point, steps: int) -> bool {
+    !self.parity || (steps - a.dist(b)) % 2 == 0
+  }
+}
+
+#[deriving(Eq, Clone)]
+enum SolutionOrder {
+  AsFound,
+  BestScore,
+  Canonical
+}
+
+// What BestScore looks at, in order, when two solutions have the same score:
+// the bigger share of cells filled, more crossings, a smaller box around the
+// words, and then canonical text, so the order is the same on every run.
+#[deriving(Eq, Clone)]
+enum TieBreak {
+  MostFilled,
+  MostCrossings,
+  SmallestBox,
+  CanonicalText
+}
+
+static DEFAULT_TIE_BREAK: [TieBreak, ..4] = [MostFilled, MostCrossings, SmallestBox, CanonicalText];
+
+// "crossings,box": those first, then the rest of the default chain
+fn parse_tie_break(spec: &str) -> Result<Vec<TieBreak>, ~str> {
+  let mut chain = Vec::new();
+  for name in spec.split(',').map(|s| s.trim()) {
+    let key = match name {
+      "fill" => MostFilled,
+      "crossings" => MostCrossings,
+      "box" => SmallestBox,
+      "canonical" => CanonicalText,
+      _ => return Err(format!("\"{}\" isn't one of fill, crossings, box or canonical", name))
+    };
+    if !chain.contains(&key) {
+      chain.push(key);
+    }
+  }
+  for key in DEFAULT_TIE_BREAK.iter() {
+    if !chain.contains(key) {
+      chain.push(key.clone());
+    }
+  }
+  Ok(chain)
+}
+
+// When two solutions count as the same one.  ByShape only looks at what's
+// drawn: the letters, and how many words use each cell, whichever words
+// those are.  ByWordPaths needs every word on the same cells.
+#[deriving(Eq, Clone)]
+enum DedupMode {
+  ByShape,
+  ByWordPaths,
+  NoDedup
+}
+
+// How cells sit next to each other.  On a Square grid a word moves by the
+// offsets it's given; on a Hex grid it moves to the six cells hex_neighbors
+// gives, which the offsets only cover loosely.
+#[deriving(Eq, Clone)]
+enum Topology {
+  Square,
+  Hex
+}
+
+static DEFAULT_MAX_WORD_LEN: uint = 64;
+
+// Settings for a solve: how the search moves and prunes, and how its results
+// come back.
+#[deriving(Clone)]
+struct SolveOptions {
+  offsets: Vec<(int, int)>,
+  topology: Topology,
+  bound: Option<StepBound>,
+  ordering: SolutionOrder,
+  // keep at most this many grids after each word
+  beam: Option<uint>,
+  // give up after visiting this many cells in total
+  max_nodes: Option<uint>,
+  // set from another task to stop the search early
+  cancel: Option<Arc<AtomicBool>>,
+  // seconds for the whole solve, and for any one word within it
+  timeout: Option<f64>,
+  word_timeout: Option<f64>,
+  // only search within this many cells of the box around each word's ends
+  roi_border: Option<uint>,
+  // keep every word inside this box, corners included, top left first
+  within: Option<(Point, Point)>,
+  // how many words each cell can carry, from an overlay
+  capacity: Option<Grid<uint>>,
+  // put the solutions whose words turn least first, ahead of `ordering`
+  prefer_straight: bool,
+  // search only one side of a symmetric grid and reflect the results
+  symmetry: bool,
+  // keep words out of the cells next to other words' waypoints
+  isolate_anchors: bool,
+  // read lowercase letters in the grid as cells some word has to pass through
+  prefilled: bool,
+  // check every placement the search makes, even in a release build
+  paranoid: bool,
+  // words that go exactly along these cells instead of being searched
+  fixed_paths: Vec<(~str, Vec<Point>)>,
+  // words a solution can leave out
+  optional: Vec<~str>,
+  // let a word pass through another word's waypoint with the letter it needs
+  through_waypoints: bool,
+  // give up if the search goes deeper than this: words placed so far plus
+  // letters into the current one
+  max_depth: Option<uint>,
+  // the spellings of each word given as "colour/color", in the order to try
+  // them, and whether every result has to settle on the same one
+  variants: Vec<Vec<~str>>,
+  consistent_variants: bool,
+  // words given as "chain: ember -> river -> rest", drawn together
+  chains: Vec<Vec<~str>>,
+  dedup: DedupMode,
+  // how BestScore orders solutions with the same score
+  tie_break: Vec<TieBreak>,
+  // print how much room the next words have after each one is placed
+  verbose: bool,
+  // try each word's next cell closest to its last waypoint first
+  toward_dest: bool,
+  // the most letters a word in the words file can have
+  max_word_len: uint
+}
+
+impl SolveOptions {
+  fn new() -> SolveOptions {
+    SolveOptions::with_offsets(Vec::from_slice(FOUR_WAYS.as_slice()))
+  }
+  fn with_offsets(offsets: Vec<(int, int)>) -> SolveOptions {
+    let bound = StepBound::for_offsets(offsets.as_slice());
+    SolveOptions { offsets: offsets, topology: Square, bound: bound, ordering: AsFound, beam: None, max_nodes: None, cancel: None, timeout: None, word_timeout: None, roi_border: None, within: None, capacity: None, prefer_straight: false, symmetry: false, isolate_anchors: false, prefilled: false, paranoid: false, fixed_paths: Vec::new(), optional: Vec::new(), through_waypoints: false, max_depth: None, variants: Vec::new(), consistent_variants: false, chains: Vec::new(), dedup: NoDedup, tie_break: Vec::from_slice(DEFAULT_TIE_BREAK.as_slice()), verbose: false, toward_dest: false, max_word_len: DEFAULT_MAX_WORD_LEN }
+  }
+
+  // The limits that can't mean anything at zero or below.  A beam of 0 keeps
+  // no grids and a node or depth cap of 0 stops before the first cell, so
+  // rather than hand back an empty result that looks like an answer these
+  // are refused; a border of 0 is fine and keeps words inside the box.
+  fn validate(&self) -> Result<(), ~str> {
+    if self.max_word_len < 2 {
+      return Err(~"--max-word-len must be at least 2, or no word could get in");
+    }
+    let counts = [("beam", self.beam), ("max-nodes", self.max_nodes), ("max-depth", self.max_depth)];
+    for &(name, n) in counts.iter() {
+      if n == Some(0) {
+        return Err(format!("--{} must be at least 1", name));
+      }
+    }
+    if self.topology == Hex && self.symmetry {
+      return Err(~"--symmetry only works on a square grid");
+    }
+    let times = [("timeout", self.timeout), ("word-timeout", self.word_timeout)];
+    for &(name, t) in times.iter() {
+      match t {
+        Some(t) if !(t > 0.0) || t.is_infinite() => return Err(format!("--{} must be a number of seconds more than 0", name)),
+        _ => { }
+      }
+    }
+    Ok(())
+  }
+}
+
+// the value of a flag that takes a whole number, if it was given
+fn count_flag(matches: &Matches, name: &str) -> Result<Option<uint>, ~str> {
+  match matches.opt_str(name) {
+    None => Ok(None),
+    Some(n) => match from_str::<uint>(n) {
+      Some(n) => Ok(Some(n)),
+      None => Err(format!("--{} takes a whole number, not \"{}\"", name, n))
+    }
+  }
+}
+
+fn seconds_flag(matches: &Matches, name: &str) -> Result<Option<f64>, ~str> {
+  match matches.opt_str(name) {
+    None => Ok(None),
+    Some(n) => match from_str::<f64>(n) {
+      Some(t) => Ok(Some(t)),
+      None => Err(format!("--{} takes a number of seconds, not \"{}\"", name, n))
+    }
+  }
+}
+
+// Where a word can go from p in one move, on or off the grid.
+fn steps_from(opts: &SolveOptions, p: Point) -> Vec<Point> {
+  match opts.topology {
+    Square => opts.offsets.iter().map(|&(x, y)| p.offset(x, y)).collect(),
+    Hex => hex_neighbors(p).iter().map(|&q| q).collect()
+  }
+}
+
+// "x,y;x,y", two opposite corners of a box, in either order
+fn parse_box(spec: &str) -> Result<(Point, Point), ~str> {
+  let corners: Vec<Option<Point>> = spec.split(';').map(|corner| {
+    let parts: Vec<Option<int>> = corner.split(',').map(|n| from_str::<int>(n.trim())).collect();
+    match parts.as_slice() {
+      [Some(x), Some(y)] => Some(Point { x: x, y: y }),
+      _ => None
+    }
+  }).collect();
+  match corners.as_slice() {
+    [Some(a), Some(b)] => Ok((Point { x: std::cmp::min(a.x, b.x), y: std::cmp::min(a.y, b.y) },
+                              Point { x: std::cmp::max(a.x, b.x), y: std::cmp::max(a.y, b.y) })),
+    _ => Err(format!("\"{}\" should look like x,y;x,y", spec))
+  }
+}
+
+// "4", "8", "knight", or a list of moves like "1,2;2,1;-1,2"
+fn parse_offsets(spec: &str) -> Result<Vec<(int, int)>, ~str> {
+  match spec {
+    "4" => return Ok(Vec::from_slice(FOUR_WAYS.as_slice())),
+    "8" => return Ok(Vec::from_slice(EIGHT_WAYS.as_slice())),
+    "knight" => return Ok(Vec::from_slice(KNIGHT_MOVES.as_slice())),
+    _ => { }
+  }
+  let mut offsets = Vec::new();
+  for pair in spec.split(';') {
+    let parts: Vec<&str> = pair.split(',').map(|x| x.trim()).collect();
+    if parts.len() != 2 {
+      return Err(format!("bad move \"{}\": expected dx,dy", pair));
+    }
+    match (from_str::<int>(*parts.get(0)), from_str::<int>(*parts.get(1))) {
+      (Some(0), Some(0)) => return Err(~"0,0 isn't a move"),
+      (Some(x), Some(y)) => offsets.push((x, y)),
+      _ => return Err(format!("bad move \"{}\": expected dx,dy", pair))
+    }
+  }
+  Ok(offsets)
 }
 
 impl<T> Grid<T> {
@@ -48,21 +331,30 @@ impl<T> Grid<T> {
     else                { None    }
   }
   #[inline(always)] fn set(& mut self, p: Point, data: T) {
-    *self.tiles.get_mut((self.height * p.y + p.x) as uint) = data;
+    *self.tiles.get_mut((self.width * p.y + p.x) as uint) = data;
   }
   #[inline(always)] fn get_ref<'a>(&'a self, p: Point) -> Option<&'a T> {
-    if self.is_valid(p) { Some(self.tiles.get((self.height * p.y + p.x) as uint)) }
+    if self.is_valid(p) { Some(self.tiles.get((self.width * p.y + p.x) as uint)) }
     else { None }
   }
   #[allow(dead_code)]
   #[inline] fn get_mut_ref<'a>(&'a mut self, p: Point) -> Option<&'a mut T> {
-    if self.is_valid(p) { Some(self.tiles.get_mut((self.height * p.y + p.x) as uint)) }
+    if self.is_valid(p) { Some(self.tiles.get_mut((self.width * p.y + p.x) as uint)) }
     else { None }
   }
   fn map<U>(& self, map: |&T|->U)->Grid<U> {
     let mapped: Vec<U> = self.tiles.iter().map(map).collect();
     Grid { width: self.width, height: self.height, tiles: mapped }
   }
+  // like map, for when the new value depends on where the tile is
+  #[allow(dead_code)]
+  fn map_points<U>(& self, map: |Point, &T|->U)->Grid<U> {
+    let width = self.width;
+    let mapped: Vec<U> = self.tiles.iter().enumerate().map(|(i, tile)| {
+      map(Point { x: i as int % width, y: i as int / width }, tile)
+    }).collect();
+    Grid { width: self.width, height: self.height, tiles: mapped }
+  }
 }
 impl<T: Clone> Grid<T> {
   #[inline(always)] fn replace(& self, p: Point, data: T) -> Grid<T> {
@@ -71,6 +363,129 @@ impl<T: Clone> Grid<T> {
     new.set(p, data);
     new
   }
+  // None if either row is off the grid
+  #[allow(dead_code)]
+  fn swap_rows(& self, y1: uint, y2: uint) -> Option<Grid<T>> {
+    if y1 >= self.height as uint || y2 >= self.height as uint { return None; }
+    let mut new = self.clone();
+    let width = self.width as uint;
+    for x in range(0, width) {
+      new.tiles.as_mut_slice().swap(y1 * width + x, y2 * width + x);
+    }
+    Some(new)
+  }
+  // None if either column is off the grid
+  #[allow(dead_code)]
+  fn swap_cols(& self, x1: uint, x2: uint) -> Option<Grid<T>> {
+    if x1 >= self.width as uint || x2 >= self.width as uint { return None; }
+    let mut new = self.clone();
+    let width = self.width as uint;
+    for y in range(0, self.height as uint) {
+      new.tiles.as_mut_slice().swap(y * width + x1, y * width + x2);
+    }
+    Some(new)
+  }
+  // top left, top right, bottom left, bottom right
+  #[allow(dead_code)]
+  fn corner_points(& self) -> [Point, ..4] {
+    let (right, bottom) = (self.width - 1, self.height - 1);
+    [Point { x: 0, y: 0 }, Point { x: right, y: 0 }, Point { x: 0, y: bottom }, Point { x: right, y: bottom }]
+  }
+  #[allow(dead_code)]
+  fn corners<'a>(&'a self) -> [Option<&'a T>, ..4] {
+    let c = self.corner_points();
+    [self.get_ref(c[0]), self.get_ref(c[1]), self.get_ref(c[2]), self.get_ref(c[3])]
+  }
+  // does nothing to an empty grid
+  #[allow(dead_code)]
+  fn fill_corners(& mut self, value: T) {
+    for &p in self.corner_points().iter() {
+      if self.is_valid(p) {
+        self.set(p, value.clone());
+      }
+    }
+  }
+  // rows become columns: x,y becomes y,x
+  fn transpose(& self) -> Grid<T> {
+    let mut tiles = Vec::with_capacity(self.tiles.len());
+    for x in range(0, self.width) {
+      for y in range(0, self.height) {
+        tiles.push(self.tiles.get((self.width * y + x) as uint).clone());
+      }
+    }
+    Grid { width: self.height, height: self.width, tiles: tiles }
+  }
+  // a quarter turn clockwise: x,y becomes height-1-y,x
+  fn rotate(& self) -> Grid<T> {
+    self.transpose().flip_h()
+  }
+  // mirrored left to right: x becomes width-1-x
+  fn flip_h(& self) -> Grid<T> {
+    let width = self.width as uint;
+    let mut tiles = Vec::with_capacity(self.tiles.len());
+    for row in self.tiles.as_slice().chunks(width) {
+      tiles.extend(row.iter().rev().map(|t| t.clone()));
+    }
+    Grid { width: self.width, height: self.height, tiles: tiles }
+  }
+  // mirrored top to bottom: y becomes height-1-y
+  fn flip_v(& self) -> Grid<T> {
+    let width = self.width as uint;
+    let mut tiles = Vec::with_capacity(self.tiles.len());
+    for row in self.tiles.as_slice().chunks(width).rev() {
+      tiles.push_all(row);
+    }
+    Grid { width: self.width, height: self.height, tiles: tiles }
+  }
+}
+
+// A grid of hexagons in offset coordinates, stored row by row like any other
+// Grid, with each odd row pushed half a cell to the right of the rows above
+// and below it.  Each cell has six neighbors: the two beside it, and two in
+// each of the rows above and below, which ones depending on the row.
+struct HexGrid<T> {
+  grid: Grid<T>
+}
+
+// the six cells around p on a HexGrid, whether or not they're on it
+fn hex_neighbors(p: Point) -> [Point, ..6] {
+  let dx = if p.y & 1 == 1 { 0 } else { -1 };
+  [p.offset(-1, 0), p.offset(1, 0), p.offset(dx, -1), p.offset(dx + 1, -1), p.offset(dx, 1), p.offset(dx + 1, 1)]
+}
+
+impl<T> HexGrid<T> {
+  fn new(grid: Grid<T>) -> HexGrid<T> {
+    HexGrid { grid: grid }
+  }
+  #[allow(dead_code)]
+  #[inline(always)] fn set(& mut self, p: Point, data: T) {
+    self.grid.set(p, data)
+  }
+  #[allow(dead_code)]
+  #[inline(always)] fn get_ref<'a>(&'a self, p: Point) -> Option<&'a T> {
+    self.grid.get_ref(p)
+  }
+  #[allow(dead_code)]
+  fn map<U>(& self, map: |&T|->U)->HexGrid<U> {
+    HexGrid::new(self.grid.map(map))
+  }
+  #[allow(dead_code)]
+  fn neighbors(& self, p: Point) -> Vec<Point> {
+    hex_neighbors(p).iter().map(|&q| q).filter(|&q| self.grid.is_valid(q)).collect()
+  }
+}
+
+// a space between cells, and another in front of the odd rows to stagger them
+impl std::fmt::Show for HexGrid<char> {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    let iter = self.grid.tiles.as_slice().chunks(self.grid.width as uint).enumerate();
+    let pieces: Vec<~str> = iter.map(|(y, row)| {
+      let cells: Vec<~str> = row.iter().map(|c| std::str::from_char(*c)).collect();
+      format!("{}{}", if y % 2 == 1 { " " } else { "" }, cells.connect(" "))
+    }).collect();
+    let string = pieces.connect("\n");
+    write!(fmt.buf, "{}", string)
+  }
 }
 
 impl std::fmt::Show for StringGrid {
@@ -83,27 +498,56 @@ impl std::fmt::Show for StringGrid {
 }
 
 impl<'a> Grid<TileData<'a>> {
-  fn neighbors<'b>(& self, p: Point, invec: &'b mut [Point, ..4]) -> &'b [Point] {
-    let offsets = [
-      p.offset(-1, 0),
-      p.offset( 1, 0),
-      p.offset( 0,-1),
-      p.offset( 0, 1)
-    ];
-    let mut i = 0;
-    for &offset in offsets.iter() {
-      if self.is_valid(offset) {
-        invec[i] = offset;
-        i += 1;
+  // how many of the cells above, below and beside p have something in them
+  #[allow(dead_code)]
+  fn neighbor_count(& self, p: Point) -> uint {
+    self.neighbors(p, FOUR_WAYS.as_slice()).iter().filter(|&&n| *self.get_ref(n).unwrap() != NoWords).count()
+  }
+
+  // neighbor_count for every cell at once
+  fn neighbor_counts(& self) -> Grid<u8> {
+    self.map_points(|p, _| self.neighbor_count(p) as u8)
+  }
+
+  fn neighbors(& self, p: Point, offsets: &[(int, int)]) -> Vec<Point> {
+    offsets.iter().map(|&(x, y)| p.offset(x, y)).filter(|&q| self.is_valid(q)).collect()
+  }
+
+  // where a word on this grid can go from p in one move
+  fn steps(& self, opts: &SolveOptions, p: Point) -> Vec<Point> {
+    steps_from(opts, p).move_iter().filter(|&q| self.is_valid(q)).collect()
+  }
+
+  // the cells a move leads to from p, or back to p from, since a word's route
+  // can be walked from either end; on a hex grid these are the same
+  fn either_way(& self, opts: &SolveOptions, p: Point) -> Vec<Point> {
+    let mut next = steps_from(opts, p);
+    if opts.topology == Square {
+      for &(x, y) in opts.offsets.iter() {
+        let q = p.offset(-x, -y);
+        if !next.contains(&q) {
+          next.push(q);
+        }
       }
     }
-    invec.slice_to(i)
+    next.move_iter().filter(|&q| self.is_valid(q)).collect()
   }
 
-  #[allow(dead_code)]
   fn to_strgrid(&self) -> StringGrid {
     self.map(default_char)
   }
+
+  // A grid of chars read the way a grid file is: blanks, walls, and a
+  // waypoint for anything else.  There's no From trait to hang this on, so
+  // it's CrosswordGrid::from_strgrid(&chars).
+  #[allow(dead_code)]
+  fn from_strgrid(grid: &StringGrid) -> Grid<TileData<'a>> {
+    grid.map(|&c| match c {
+      ' ' => NoWords,
+      '#' => Blocked,
+      _ => Fixed(ALPHABET.normalize(c))
+    })
+  }
 }
 
 trait FlattenCrossword {
@@ -127,168 +571,7469 @@ impl<'a> FlattenCrossword for Vec<CrosswordGrid<'a>> {
     let mapped: Vec<CrosswordGrid<'a>> = self.iter().map(|x| x.map(|&tile| match tile {
       OneWord(_, word) if word == s => tile,
       TwoWords(_, a, b) if a == s || b == s => tile,
-      Fixed(_) => tile,
+      Through(_, w) if w == s => tile,
+      Fixed(_) | Blocked => tile,
+      Through(c, _) => Fixed(c),
       _ => NoWords
     })).collect();
     mapped.flatten()
   }
 }
 
-trait Case {
-  fn to_upper(&self) -> char;
-  fn to_lower(&self) -> char;
-}
-impl Case for char {
-  #[inline(always)] fn to_upper(&self) -> char {
-    self.to_ascii().to_upper().to_char()
+#[inline] fn has_word<'a>(tile: &TileData<'a>, word: &str) -> bool {
+  match *tile {
+    OneWord(_, w) => w == word,
+    TwoWords(_, a, b) => a == word || b == word,
+    Through(_, w) => w == word,
+    _ => false
   }
+}
 
-  #[inline(always)] fn to_lower(&self) -> char {
-    self.to_ascii().to_lower().to_char()
+// "15x15 [F:25 O:80 T:15 N:105]": the grid's size and how many of its cells
+// are waypoints, carry one word, carry two, and carry none.  Walls make up
+// the rest.
+fn tile_summary<'a>(grid: &CrosswordGrid<'a>) -> ~str {
+  let mut counts = [0u, ..4];
+  for tile in grid.tiles.iter() {
+    match *tile {
+      Fixed(_) | Through(..) => counts[0] += 1,
+      OneWord(..) => counts[1] += 1,
+      TwoWords(..) => counts[2] += 1,
+      NoWords => counts[3] += 1,
+      Blocked => { }
+    }
   }
+  format!("{}x{} [F:{} O:{} T:{} N:{}]", grid.width, grid.height, counts[0], counts[1], counts[2], counts[3])
 }
 
-#[inline] fn default_char<'a>(tile: &TileData<'a>) -> char {
+// whether the word is written into any cell of the grid
+fn contains_word_cell<'a>(grid: &CrosswordGrid<'a>, word: &str) -> bool {
+  grid.tiles.iter().any(|tile| has_word(tile, word))
+}
+
+#[inline] fn tile_char<'a>(tile: &TileData<'a>) -> Option<char> {
   match *tile {
-    Fixed(c) => c.to_upper(),
-    OneWord(c, _) => c.to_lower(),
-    TwoWords(c, _, _) => c.to_lower(),
-    NoWords => ' '
+    Fixed(c) | OneWord(c, _) | TwoWords(c, _, _) | Through(c, _) => Some(c),
+    Blocked | NoWords => None
   }
 }
 
-fn readlines(file: &str) -> Vec<~str> {
-  let path = Path::new(file);
-  let input = File::open(&path).read_to_end().unwrap();
-  let text = std::str::from_utf8(input.as_slice()).unwrap();
-  text.lines_any().map(|line| line.into_owned()).collect()
+// The cells a word was written into, in the order it passes through them.
+// Endpoints are fixed tiles and never written, so they aren't included.
+fn word_path<'a>(grid: &CrosswordGrid<'a>, word: &str, opts: &SolveOptions) -> Vec<Point> {
+  let mut cells: Vec<Point> = Vec::new();
+  for y in range(0, grid.height) {
+    for x in range(0, grid.width) {
+      let p = Point { x: x, y: y };
+      if has_word(grid.get_ref(p).unwrap(), word) {
+        cells.push(p);
+      }
+    }
+  }
+  let letters: Vec<char> = word.chars().collect();
+  let mut path: Vec<Point> = Vec::new();
+  extend_path(grid, letters.as_slice(), cells.as_slice(), opts, &mut path);
+  path
 }
 
-fn readgrid(file: &str) -> ~CrosswordGrid {
-  let lines = readlines(file);
-  let longest = lines.iter().map(|a| a.char_len()).max().unwrap();
-  let mut full = StrBuf::new();
-  for line in lines.iter() {
-    full.push_str(*line);
+// the word's cells aren't necessarily an induced path (it can wind back next
+// to itself), so walk them by letter and backtrack on dead ends
+fn extend_path<'a>(grid: &CrosswordGrid<'a>, letters: &[char], cells: &[Point], opts: &SolveOptions, path: &mut Vec<Point>) -> bool {
+  if path.len() == cells.len() {
+    return true;
   }
-  let downcase = full.into_owned().to_ascii_lower();
-  let mut tileit = downcase
-  .chars()
-  .map(|c| match c {
-    ' ' => NoWords,
-    _   => Fixed(c)
-  });
-  let tiles: Vec<TileData> = tileit.collect();
-  ~Grid { width: longest as int, height: lines.len() as int, tiles: tiles }
+  let want = letters[path.len() + 1];
+  let candidates: Vec<Point> = match path.last() {
+    // the walk starts next to the word's first waypoint, which matters when
+    // its letters read the same both ways (or are all wildcards)
+    None => cells.iter().filter(|&&p| grid.either_way(opts, p).iter().any(|&n| match *grid.get_ref(n).unwrap() {
+      Fixed(c) | Through(c, _) => ALPHABET.same(c, letters[0]),
+      _ => false
+    })).map(|&p| p).collect(),
+    Some(&last) => grid.steps(opts, last)
+  };
+  for &p in candidates.iter() {
+    if !cells.contains(&p) || path.contains(&p) { continue; }
+    if !tile_char(grid.get_ref(p).unwrap()).map_or(false, |c| ALPHABET.same(c, want)) { continue; }
+    path.push(p);
+    if extend_path(grid, letters, cells, opts, path) {
+      return true;
+    }
+    path.pop();
+  }
+  false
 }
 
-fn readwords(file: &str) -> Vec<~str> {
-  let lines = readlines(file);
-  lines.iter().map(|x| x.to_ascii_lower()).collect::<Vec<~str>>()
+// How many times `word` changes direction between its two waypoints.
+fn turns<'a>(grid: &CrosswordGrid<'a>, word: &str, start: Point, end: Point, opts: &SolveOptions) -> uint {
+  let mut path = vec!(start);
+  path.push_all(word_path(grid, word, opts).as_slice());
+  path.push(end);
+  path.as_slice().windows(3).filter(|w| {
+    (w[1].x - w[0].x, w[1].y - w[0].y) != (w[2].x - w[1].x, w[2].y - w[1].y)
+  }).count()
 }
 
-fn hashgrid(grid: CrosswordGrid) -> HashMap<char, Point> {
-  let mut map = HashMap::<char, Point>::new();
-  for x in range(0, grid.width) {
-    for y in range(0, grid.height) {
-      let p = Point { x: x, y: y };
-      let data = *grid.get_ref(p).unwrap();
-      match data {
-        Fixed(letter) => {
-          match map.find(&letter) {
-            Some(x) => {
-              fail!("Already have letter: \"{}\" at point: {},{}", letter, x.x, x.y);
-            },
-            None => { }
-          }
-          map.insert(letter, p);
-        },
-        _ => { }
+fn total_turns<'a>(grid: &CrosswordGrid<'a>, wordpts: &[(&'a str, &(Point, Point))], opts: &SolveOptions) -> uint {
+  wordpts.iter().fold(0, |accum, &(word, &(start, end))| accum + turns(grid, word, start, end, opts))
+}
+
+// Every cell within `radius` steps (up, down, left or right) of the path,
+// the path itself included.  A path that fills out its neighborhood has room
+// to shift if something else needs its cells; a thin one doesn't.
+fn path_neighborhood<'a>(grid: &CrosswordGrid<'a>, path: &[Point], radius: uint) -> HashSet<Point> {
+  let mut seen: HashSet<Point> = path.iter().map(|&p| p).collect();
+  let mut frontier: Vec<Point> = Vec::from_slice(path);
+  for _ in range(0, radius) {
+    let mut next = Vec::new();
+    for &p in frontier.iter() {
+      for &n in grid.neighbors(p, FOUR_WAYS.as_slice()).iter() {
+        if seen.insert(n) {
+          next.push(n);
+        }
       }
     }
+    frontier = next;
   }
-  map
+  seen
 }
 
-fn allpaths<'a>(grid: & CrosswordGrid<'a>, word: &'a str, start: Point, dest: Point, s: &'a str, accum: &mut Vec<CrosswordGrid<'a>>) {
-  let len = s.len() as int - 1;
-  if start == dest && len == 0 {
-    accum.push(grid.clone());
-    return;
+// One word's route through a solution, waypoints included.
+struct WordPath<'a> {
+  word: &'a str,
+  cells: Vec<Point>,
+  // cells within one step of the route
+  neighborhood: uint
+}
+
+impl<'a> WordPath<'a> {
+  fn new(grid: &CrosswordGrid<'a>, word: &'a str, start: Point, end: Point, opts: &SolveOptions) -> WordPath<'a> {
+    let mut cells = vec!(start);
+    cells.push_all(word_path(grid, word, opts).as_slice());
+    cells.push(end);
+    let neighborhood = path_neighborhood(grid, cells.as_slice(), 1).len();
+    WordPath { word: word, cells: cells, neighborhood: neighborhood }
   }
-  if start.dist(dest) > len {
-    return;
+}
+
+// Shannon entropy, in bits, of the paths `word` takes across a solution set.
+// Zero means every solution agrees on its route.
+fn path_entropy<'a>(solutions: &[CrosswordGrid<'a>], word: &str, opts: &SolveOptions) -> f64 {
+  let mut counts = HashMap::<Vec<Point>, uint>::new();
+  for grid in solutions.iter() {
+    let path = word_path(grid, word, opts);
+    counts.insert_or_update_with(path, 1, |_, count| *count += 1);
   }
-  match grid.get_ref(start) {
-    None => { },
-    // skip if wrong character, or the character is part of this word
-    Some(&OneWord(c, w)) if c == s.char_at(0) && w != word => {
-      let newtile: TileData<'a> = TwoWords(c, w, word);
-      let newgrid = grid.replace(start, newtile);
-      allpaths2(&newgrid, word, start, dest, s, accum)
-    }
-    Some(&NoWords) => {
-      let newtile = OneWord(s.char_at(0), word);
-      let newgrid = grid.replace(start, newtile);
-      allpaths2(&newgrid, word, start, dest, s, accum)
-    }
-    Some(&TwoWords(..)) => { },
-    _ => { }
+  let total = solutions.len() as f64;
+  counts.values().fold(0.0, |accum, &count| {
+    let p = count as f64 / total;
+    accum - p * p.log2()
+  })
+}
+
+// Crossings record their words in the order they were placed, so the same
+// solution found under two word orders only compares equal after sorting.
+fn canonical<'a>(grid: &CrosswordGrid<'a>) -> CrosswordGrid<'a> {
+  grid.map(|&tile| match tile {
+    TwoWords(c, a, b) if b < a => TwoWords(c, b, a),
+    _ => tile
+  })
+}
+
+// The grid as it was before `word` went in: its cells go back to blank, or
+// to the one other word crossing there.
+#[allow(dead_code)]
+fn blank_out_word<'a>(grid: &CrosswordGrid<'a>, word: &str) -> CrosswordGrid<'a> {
+  if !contains_word_cell(grid, word) {
+    return grid.clone();
   }
+  grid.map(|&tile| match tile {
+    OneWord(_, w) if w == word => NoWords,
+    TwoWords(c, a, b) if a == word => OneWord(c, b),
+    TwoWords(c, a, b) if b == word => OneWord(c, a),
+    Through(c, w) if w == word => Fixed(c),
+    _ => tile
+  })
 }
 
-fn allpaths2<'a>(grid: & CrosswordGrid<'a>, word: &'a str, start: Point, dest: Point, s: &'a str, accum: &mut Vec<CrosswordGrid<'a>>) {
-  let mystring: & str = s.slice_from(1);
+// Every pair of cells side by side or one above the other where neither is
+// blank, each pair once, left or upper cell first.
+fn as_edge_list<'a>(grid: &CrosswordGrid<'a>) -> Vec<(Point, Point)> {
+  let mut edges = Vec::new();
+  for y in range(0, grid.height) {
+    for x in range(0, grid.width) {
+      let p = Point { x: x, y: y };
+      if *grid.get_ref(p).unwrap() == NoWords {
+        continue;
+      }
+      for &q in [Point { x: x + 1, y: y }, Point { x: x, y: y + 1 }].iter() {
+        match grid.get_ref(q) {
+          Some(tile) if *tile != NoWords => edges.push((p, q)),
+          _ => { }
+        }
+      }
+    }
+  }
+  edges
+}
 
-  let mut tmpvec = [Point { x: 0, y: 0}, ..4];
-  let neighbors = grid.neighbors(start, &mut tmpvec);
-  for &p in neighbors.iter() {
-      allpaths(grid, word, p, dest, mystring, accum);
+// Whether the graph of cells a word could use, joined by the moves in
+// `opts`, can be drawn flat with no two moves crossing.  Up-down-left-right
+// grids always can, so they're true without looking; anything else, hex
+// grids included, gets the real test.  A graph is planar when each of its
+// blocks (the pieces left when it's split at its articulation points) is,
+// and each block is checked by drawing it one path at a time, as Demoucron,
+// Malgrange and Pertuiset do: start from a cycle, and keep adding a path
+// from some piece not yet drawn across a face that has room for all of that
+// piece's ends.  A piece with no such face means there's no drawing.  Euler's
+// bound, at most 3v-6 edges on v cells, rules out the dense ones first.
+#[allow(dead_code)]
+fn is_planar<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions) -> bool {
+  if opts.topology == Square && opts.offsets.iter().all(|m| FOUR_WAYS.contains(m)) {
+    return true;
+  }
+  let mut index: Grid<Option<uint>> = grid.map(|_| None);
+  let mut cells = 0u;
+  for y in range(0, grid.height) {
+    for x in range(0, grid.width) {
+      let p = Point { x: x, y: y };
+      if routable(grid.get_ref(p).unwrap()) {
+        index.set(p, Some(cells));
+        cells += 1;
+      }
+    }
+  }
+  // the moves needn't come in opposite pairs, but the graph has no direction
+  let mut adj: Vec<Vec<uint>> = Vec::from_fn(cells, |_| Vec::new());
+  let mut edges = 0u;
+  for y in range(0, grid.height) {
+    for x in range(0, grid.width) {
+      let p = Point { x: x, y: y };
+      let a = match *index.get_ref(p).unwrap() { Some(a) => a, None => continue };
+      for &q in steps_from(opts, p).iter() {
+        match index.get_ref(q) {
+          Some(&Some(b)) if b != a && !adj.get(a).contains(&b) => {
+            adj.get_mut(a).push(b);
+            adj.get_mut(b).push(a);
+            edges += 1;
+          }
+          _ => { }
+        }
+      }
+    }
+  }
+  if cells >= 3 && edges > 3 * cells - 6 {
+    return false;
   }
+  blocks(&adj).iter().all(|block| block_is_planar(block.as_slice()))
 }
 
-fn word_to_path(gridmap: &HashMap<char, Point>, word: &str) -> (Point, Point) {
-  let first = word.char_at(0);
-  let last = word.char_at_reverse(word.len()); // no, really!
-  let start = gridmap.get(&first);
-  let end = gridmap.get(&last);
-  (*start, *end)
+// The edges of each block of a graph, found by the same depth-first search
+// articulation_points makes: an edge's block closes when the search backs out
+// past a cell nothing below it reaches around.
+#[allow(dead_code)]
+fn blocks(adj: &Vec<Vec<uint>>) -> Vec<Vec<(uint, uint)>> {
+  let n = adj.len();
+  let (mut found, mut low) = (Vec::from_elem(n, 0u), Vec::from_elem(n, 0u));
+  let mut time = 0u;
+  let mut edges: Vec<(uint, uint)> = Vec::new();
+  let mut out = Vec::new();
+  for root in range(0, n) {
+    if *found.get(root) != 0 {
+      continue;
+    }
+    time += 1;
+    *found.get_mut(root) = time;
+    *low.get_mut(root) = time;
+    // each cell on the way down, where it came from, and the next neighbor
+    let mut stack: Vec<(uint, Option<uint>, uint)> = vec!((root, None, 0));
+    while !stack.is_empty() {
+      let top = stack.len() - 1;
+      let (v, parent, next) = *stack.get(top);
+      match adj.get(v).as_slice().get(next) {
+        Some(&w) => {
+          match *stack.get_mut(top) { (_, _, ref mut next) => *next += 1 }
+          if *found.get(w) == 0 {
+            edges.push((v, w));
+            time += 1;
+            *found.get_mut(w) = time;
+            *low.get_mut(w) = time;
+            stack.push((w, Some(v), 0));
+          } else if parent != Some(w) && *found.get(w) < *found.get(v) {
+            edges.push((v, w));
+            *low.get_mut(v) = std::cmp::min(*low.get(v), *found.get(w));
+          }
+        }
+        None => {
+          stack.pop();
+          match parent {
+            Some(u) => {
+              *low.get_mut(u) = std::cmp::min(*low.get(u), *low.get(v));
+              if *low.get(v) >= *found.get(u) {
+                let mut block = Vec::new();
+                loop {
+                  let e = edges.pop().unwrap();
+                  block.push(e);
+                  if e == (u, v) {
+                    break;
+                  }
+                }
+                out.push(block);
+              }
+            }
+            None => { }
+          }
+        }
+      }
+    }
+  }
+  out
 }
 
-fn add_word<'a>(accum: Vec<CrosswordGrid<'a>>, wordpt: &[(&'a str, &(Point, Point))])-> Vec<CrosswordGrid<'a>> {
-  let next = wordpt.head();
-  if next.is_none() {
-    accum
-  } else {
-    let (word, &(start, end)) = *next.unwrap();
-    println!("searching \"{}\" on {} grids", word, accum.len());
-    let mut out: Vec<CrosswordGrid> = Vec::new();
-    for i in accum.iter() {
-      allpaths2(i, word, start, end, word, &mut out);
-    }
-    if out.len() > 0 {
-      add_word(out, wordpt.slice_from(1))
-    } else {
-      println!("could not produce any paths to fit \"{}\"!", word);
-      accum
+// The shortest path from `from` to whichever cell `stop` first accepts,
+// through cells `through` allows, leaving out the direct step in `skip`.
+#[allow(dead_code)]
+fn path_between(adj: &Vec<Vec<uint>>, from: uint, skip: Option<(uint, uint)>, through: |uint| -> bool, stop: |uint| -> bool) -> Option<Vec<uint>> {
+  let mut prev: HashMap<uint, uint> = HashMap::new();
+  let mut queue = RingBuf::new();
+  queue.push_back(from);
+  while !queue.is_empty() {
+    let u = queue.pop_front().unwrap();
+    for &w in adj.get(u).iter() {
+      // off `from` itself, only into cells the path may go through
+      if w == from || prev.contains_key(&w) || skip == Some((u, w)) || (u == from && !through(w)) {
+        continue;
+      }
+      prev.insert(w, u);
+      if stop(w) {
+        let mut path = vec!(w);
+        let mut at = u;
+        while at != from {
+          path.push(at);
+          at = *prev.get(&at);
+        }
+        path.push(from);
+        path.reverse();
+        return Some(path);
+      }
+      if through(w) {
+        queue.push_back(w);
+      }
     }
   }
+  None
 }
 
-fn main() {
-  let args = std::os::args();
-  let blankgrid = *readgrid(args[1]);
-  let mut words = readwords(args[2]);
-  words.sort_by(|a,b| a.len().cmp(&b.len()));
-  let gridmap: HashMap<char, Point> = hashgrid(blankgrid.clone());
-  let paths: Vec<(Point, Point)> = words.iter().map(|word| word_to_path(&gridmap, *word)).collect();
-  println!("loaded {} words!", words.len());
-  let worditer = words.iter().map(|x| x.as_slice());
-  let wordpts: Vec<(&str, &(Point, Point))> = worditer.zip(paths.iter()).collect();
-  let results = add_word(vec!(blankgrid.clone()), wordpts.as_slice());
-  println!("{}", results.flatten());
-  for w in words.iter() {
-    println!("Showing only \"{}\":", *w);
-    println!("{}", results.flattenWord(*w));
+// Whether one block, given as its edges, can be drawn flat.
+#[allow(dead_code)]
+fn block_is_planar(block: &[(uint, uint)]) -> bool {
+  // a bridge on its own
+  if block.len() < 3 {
+    return true;
+  }
+  let mut local: HashMap<uint, uint> = HashMap::new();
+  for &(a, b) in block.iter() {
+    for &v in [a, b].iter() {
+      let next = local.len();
+      local.find_or_insert(v, next);
+    }
+  }
+  let n = local.len();
+  if block.len() > 3 * n - 6 {
+    return false;
+  }
+  let mut adj: Vec<Vec<uint>> = Vec::from_fn(n, |_| Vec::new());
+  for &(a, b) in block.iter() {
+    let (a, b) = (*local.get(&a), *local.get(&b));
+    adj.get_mut(a).push(b);
+    adj.get_mut(b).push(a);
+  }
+  fn key(a: uint, b: uint) -> (uint, uint) {
+    if a < b { (a, b) } else { (b, a) }
+  }
+  // a block with two or more edges has a cycle through any of them
+  let (a, b) = (0u, *adj.get(0).get(0));
+  let cycle = path_between(&adj, a, Some((a, b)), |_| true, |v| v == b).unwrap();
+  let mut drawn: HashSet<(uint, uint)> = HashSet::new();
+  let mut placed = Vec::from_elem(n, false);
+  for i in range(0, cycle.len()) {
+    let (v, w) = (*cycle.get(i), *cycle.get((i + 1) % cycle.len()));
+    drawn.insert(key(v, w));
+    *placed.get_mut(v) = true;
+  }
+  // each face as the cells around it, in order; the cycle has two
+  let mut faces = vec!(cycle.clone(), cycle);
+  while drawn.len() < block.len() {
+    // the pieces not drawn yet: an edge between two drawn cells, or a run of
+    // undrawn cells together with their edges to the drawing.  Each comes
+    // with the drawn cells it touches, and its run's number if it has one.
+    let mut pieces: Vec<(Vec<uint>, Option<uint>)> = Vec::new();
+    for v in range(0, n) {
+      for &w in adj.get(v).iter() {
+        if v < w && *placed.get(v) && *placed.get(w) && !drawn.contains(&(v, w)) {
+          pieces.push((vec!(v, w), None));
+        }
+      }
+    }
+    let mut run: Vec<Option<uint>> = Vec::from_elem(n, None);
+    for v in range(0, n) {
+      if *placed.get(v) || run.get(v).is_some() {
+        continue;
+      }
+      let number = pieces.len();
+      let mut touches = Vec::new();
+      let mut todo = vec!(v);
+      *run.get_mut(v) = Some(number);
+      while !todo.is_empty() {
+        let u = todo.pop().unwrap();
+        for &w in adj.get(u).iter() {
+          if *placed.get(w) {
+            if !touches.contains(&w) {
+              touches.push(w);
+            }
+          } else if run.get(w).is_none() {
+            *run.get_mut(w) = Some(number);
+            todo.push(w);
+          }
+        }
+      }
+      pieces.push((touches, Some(number)));
+    }
+    // a piece that fits only one face goes there now, before some other
+    // piece takes the room it needs
+    let mut chosen = None;
+    for (i, &(ref touches, _)) in pieces.iter().enumerate() {
+      let fits: Vec<uint> = range(0, faces.len()).filter(|&f| touches.iter().all(|v| faces.get(f).contains(v))).collect();
+      if fits.is_empty() {
+        return false;
+      }
+      if chosen.is_none() || fits.len() == 1 {
+        chosen = Some((i, *fits.get(0)));
+      }
+      if fits.len() == 1 {
+        break;
+      }
+    }
+    let (i, f) = chosen.unwrap();
+    let path = match *pieces.get(i) {
+      (ref touches, None) => touches.clone(),
+      (ref touches, Some(number)) => {
+        let from = *touches.iter().min().unwrap();
+        path_between(&adj, from, None, |w| *run.get(w) == Some(number), |w| *placed.get(w) && w != from).unwrap()
+      }
+    };
+    for i in range(0, path.len() - 1) {
+      drawn.insert(key(*path.get(i), *path.get(i + 1)));
+      *placed.get_mut(*path.get(i)) = true;
+    }
+    // the path splits its face in two, each side going back along it
+    let (first, last) = (*path.get(0), *path.get(path.len() - 1));
+    let face = faces.get(f).clone();
+    let around = |from: uint, to: uint| {
+      let mut side = Vec::new();
+      let mut at = face.iter().position(|&v| v == from).unwrap();
+      side.push(from);
+      while *face.get(at) != to {
+        at = (at + 1) % face.len();
+        side.push(*face.get(at));
+      }
+      side
+    };
+    let inner = path.slice(1, path.len() - 1);
+    let mut one = around(first, last);
+    for &v in inner.iter().rev() {
+      one.push(v);
+    }
+    let mut two = around(last, first);
+    two.push_all(inner);
+    *faces.get_mut(f) = one;
+    faces.push(two);
+  }
+  true
+}
+
+// as_edge_list as tab-separated source and target columns, naming each cell
+// x,y, for graph tools to import.
+fn edge_list_tsv<'a>(grid: &CrosswordGrid<'a>) -> ~str {
+  let mut out = StrBuf::new();
+  out.push_str("source\ttarget\n");
+  for &(a, b) in as_edge_list(grid).iter() {
+    out.push_str(format!("{},{}\t{},{}\n", a.x, a.y, b.x, b.y));
+  }
+  out.into_owned()
+}
+
+// The same graph as as_edge_list, for petgraph's algorithms: a node for each
+// cell that isn't blank, carrying its point and how it's drawn, and an edge
+// between each pair side by side or one above the other.  Only built with
+// --cfg petgraph, and petgraph on the library path.
+#[cfg(petgraph)]
+#[allow(dead_code)]
+fn to_petgraph_grid<'a>(grid: &CrosswordGrid<'a>) -> petgraph::stable_graph::StableGraph<(Point, char), ()> {
+  let mut graph = petgraph::stable_graph::StableGraph::new();
+  let mut nodes = HashMap::new();
+  for (i, tile) in grid.tiles.iter().enumerate() {
+    if *tile != NoWords {
+      let p = Point { x: i as int % grid.width, y: i as int / grid.width };
+      nodes.insert(p, graph.add_node((p, default_char(tile))));
+    }
+  }
+  for &(a, b) in as_edge_list(grid).iter() {
+    graph.add_edge(*nodes.get(&a), *nodes.get(&b), ());
+  }
+  graph
+}
+
+// The word dilate fills new cells with.  It can't be PREFILLED, which words
+// can be routed through, and no real word is a space.
+static DILATED: &'static str = " ";
+
+// Grows what's drawn by a cell: every blank cell next to one that isn't
+// blank (or a wall) becomes a placeholder, a lettered cell of DILATED's.
+#[allow(dead_code)]
+fn dilate<'a>(grid: &CrosswordGrid<'a>) -> CrosswordGrid<'a> {
+  let mut out = grid.clone();
+  for (i, tile) in grid.tiles.iter().enumerate() {
+    let p = Point { x: i as int % grid.width, y: i as int / grid.width };
+    if *tile == NoWords && grid.neighbors(p, FOUR_WAYS.as_slice()).iter().any(|&n| match *grid.get_ref(n).unwrap() { NoWords | Blocked => false, _ => true }) {
+      out.set(p, OneWord(' ', DILATED));
+    }
+  }
+  out
+}
+
+// The other way: every word cell next to a blank goes back to blank.
+// Waypoints and walls stay where they are.
+#[allow(dead_code)]
+fn erode<'a>(grid: &CrosswordGrid<'a>) -> CrosswordGrid<'a> {
+  let mut out = grid.clone();
+  for (i, tile) in grid.tiles.iter().enumerate() {
+    let p = Point { x: i as int % grid.width, y: i as int / grid.width };
+    let word_cell = match *tile { OneWord(..) | TwoWords(..) => true, _ => false };
+    if word_cell && grid.neighbors(p, FOUR_WAYS.as_slice()).iter().any(|&n| *grid.get_ref(n).unwrap() == NoWords) {
+      out.set(p, NoWords);
+    }
+  }
+  out
+}
+
+// Just what the search drew in: the cells that were blank in `blank` and
+// carry a word in `solved`, with everything else (waypoints, walls, cells
+// drawn in beforehand) left blank.
+fn added_cells<'a, 'b>(blank: &CrosswordGrid<'b>, solved: &CrosswordGrid<'a>) -> StringGrid {
+  solved.map_points(|p, after| match (blank.get_ref(p), after) {
+    (Some(&NoWords), &OneWord(..)) | (Some(&NoWords), &TwoWords(..)) => default_char(after),
+    _ => ' '
+  })
+}
+
+// Just a chain's words, as one path: their letters and the waypoints they
+// go between, with everything else left blank.
+fn chain_cells<'a>(grid: &CrosswordGrid<'a>, chain: &[~str]) -> StringGrid {
+  let ends: Vec<char> = chain.iter().flat_map(|w| vec!(w.char_at(0), w.char_at_reverse(w.len())).move_iter()).collect();
+  grid.map(|tile| match *tile {
+    Fixed(c) if ends.iter().any(|&e| ALPHABET.same(c, e)) => default_char(tile),
+    _ if chain.iter().any(|w| has_word(tile, *w)) => default_char(tile),
+    _ => ' '
+  })
+}
+
+// Each word's cells marked by where they fall along it, counting its first
+// waypoint as letter 0: 'o' on the even letters and 'x' on the odd ones, or
+// '*' where two words cross on letters of different parity.  Waypoints,
+// walls and blanks are drawn as usual.
+fn parity_cells<'a>(grid: &CrosswordGrid<'a>, words: &[~str], opts: &SolveOptions) -> StringGrid {
+  let mut out = grid.map(|tile| match *tile {
+    OneWord(..) | TwoWords(..) => ' ',
+    _ => default_char(tile)
+  });
+  for w in words.iter() {
+    for (i, &p) in word_path(grid, *w, opts).iter().enumerate() {
+      let mark = if i % 2 == 0 { 'x' } else { 'o' };
+      let was = *out.get_ref(p).unwrap();
+      out.set(p, if was == ' ' || was == mark { mark } else { '*' });
+    }
+  }
+  out
+}
+
+// One cell of merge_grids: the two cells' words together, if they can share
+// the cell.
+fn merge_tiles<'a>(a: TileData<'a>, b: TileData<'a>) -> Option<TileData<'a>> {
+  if a == b {
+    return Some(a);
+  }
+  match (a, b) {
+    (NoWords, t) | (t, NoWords) => Some(t),
+    (OneWord(c, w), t @ OneWord(d, _)) | (t @ OneWord(d, _), OneWord(c, w)) if w == PREFILLED && ALPHABET.same(c, d) => Some(t),
+    (OneWord(c, w), OneWord(d, v)) if w != v && ALPHABET.same(c, d) => Some(TwoWords(ALPHABET.crossed(c, d), w, v)),
+    (OneWord(c, w), t @ TwoWords(d, x, y)) | (t @ TwoWords(d, x, y), OneWord(c, w)) if (w == x || w == y || w == PREFILLED) && ALPHABET.same(c, d) => Some(t),
+    (Fixed(c), t @ Through(d, _)) | (t @ Through(d, _), Fixed(c)) if c == d => Some(t),
+    _ => None
+  }
+}
+
+// The clue for the word on each cell, for anything that wants to show one
+// when a cell's picked.  A crossing cell gets the clue of the word that was
+// there first; waypoints and blanks get none.
+#[allow(dead_code)]
+fn to_clue_grid<'a, 'c>(grid: &CrosswordGrid<'a>, clues: &HashMap<&'a str, &'c str>) -> Grid<Option<&'c str>> {
+  grid.map(|tile| match *tile {
+    OneWord(_, w) | TwoWords(_, w, _) | Through(_, w) => clues.find(&w).map(|c| *c),
+    _ => None
+  })
+}
+
+// Every word with a cell on the grid.
+fn words_on<'a>(grid: &CrosswordGrid<'a>) -> HashSet<&'a str> {
+  let mut words = HashSet::new();
+  for tile in grid.tiles.iter() {
+    match *tile {
+      OneWord(_, w) | Through(_, w) if w != PREFILLED => { words.insert(w); }
+      TwoWords(_, a, b) => { words.insert(a); words.insert(b); }
+      _ => { }
+    }
+  }
+  words
+}
+
+// Two partial solutions of the same puzzle laid over each other, if they
+// agree: the same letter wherever both have one, no more than two words on a
+// cell, and any word that's on both taking the same cells on both.
+fn merge_grids<'a>(a: &CrosswordGrid<'a>, b: &CrosswordGrid<'a>) -> Option<CrosswordGrid<'a>> {
+  if a.width != b.width || a.height != b.height {
+    return None;
+  }
+  let on_a = words_on(a);
+  for w in words_on(b).iter().filter(|w| on_a.contains(*w)) {
+    if a.tiles.iter().zip(b.tiles.iter()).any(|(s, t)| has_word(s, *w) != has_word(t, *w)) {
+      return None;
+    }
+  }
+  let mut tiles = Vec::with_capacity(a.tiles.len());
+  for (&s, &t) in a.tiles.iter().zip(b.tiles.iter()) {
+    match merge_tiles(s, t) {
+      Some(tile) => tiles.push(tile),
+      None => return None
+    }
+  }
+  Some(Grid { width: a.width, height: a.height, tiles: tiles })
+}
+
+// The grid's negative: waypoints become blanks and blanks become waypoints
+// with no letter, so no word can start on them.  Everything else stays.
+#[allow(dead_code)]
+fn invert<'a>(grid: &CrosswordGrid<'a>) -> CrosswordGrid<'a> {
+  grid.map(|&tile| match tile {
+    Fixed(_) => NoWords,
+    NoWords => Fixed(' '),
+    _ => tile
+  })
+}
+
+// Words with at least one cell on the edge of the grid.
+#[allow(dead_code)]
+fn boundary_words<'a>(grid: &CrosswordGrid<'a>) -> HashSet<&'a str> {
+  let mut words = HashSet::new();
+  for (i, tile) in grid.tiles.iter().enumerate() {
+    let (x, y) = (i as int % grid.width, i as int / grid.width);
+    if x != 0 && x != grid.width - 1 && y != 0 && y != grid.height - 1 {
+      continue;
+    }
+    match *tile {
+      OneWord(_, w) if w != PREFILLED => { words.insert(w); }
+      TwoWords(_, a, b) => { words.insert(a); words.insert(b); }
+      _ => { }
+    }
+  }
+  words
+}
+
+fn count_letter(counts: &mut TreeMap<char, uint>, c: char) {
+  let n = match counts.find(&c) {
+    Some(&n) => n,
+    None => 0
+  };
+  counts.insert(c, n + 1);
+}
+
+// How often each letter appears on the grid, one per cell however many words
+// share it.
+fn letter_distribution<'a>(grid: &CrosswordGrid<'a>) -> TreeMap<char, uint> {
+  let mut counts = TreeMap::new();
+  for tile in grid.tiles.iter() {
+    match tile_char(tile) {
+      Some(c) => count_letter(&mut counts, c),
+      None => { }
+    }
+  }
+  counts
+}
+
+// How many filled cells have each number of filled neighbors, up, down and
+// to the sides.  Mostly twos means the grid is all corridors, with little
+// choice in how a word gets anywhere.
+fn histogram_neighbor_degrees<'a>(grid: &CrosswordGrid<'a>) -> TreeMap<u8, uint> {
+  let mut counts = TreeMap::new();
+  for (degree, tile) in grid.neighbor_counts().tiles.iter().zip(grid.tiles.iter()) {
+    if *tile != NoWords {
+      let n = counts.find(degree).map_or(0, |&n| n);
+      counts.insert(*degree, n + 1);
+    }
+  }
+  counts
+}
+
+// How many cells two words share.
+fn count_crossings<'a>(grid: &CrosswordGrid<'a>) -> uint {
+  grid.tiles.iter().filter(|tile| match **tile {
+    TwoWords(..) => true,
+    _ => false
+  }).count()
+}
+
+// The middle of a word: the average of its cells' coordinates, rounded.
+fn word_center<'a>(grid: &CrosswordGrid<'a>, word: &str) -> Option<Point> {
+  let (mut sx, mut sy, mut n) = (0i, 0i, 0i);
+  for (i, tile) in grid.tiles.iter().enumerate() {
+    if has_word(tile, word) {
+      sx += i as int % grid.width;
+      sy += i as int / grid.width;
+      n += 1;
+    }
+  }
+  if n == 0 {
+    return None;
+  }
+  let round = |sum: int| (sum as f64 / n as f64).round() as int;
+  Some(Point { x: round(sx), y: round(sy) })
+}
+
+// The words on the grid in k groups of ones near each other, by k-means on
+// their centers.  The first center is the first word alphabetically and each
+// next one the word farthest from any center so far, so the same grid
+// always gives the same groups; then words move to their nearest center and
+// centers to the middle of their words until nothing changes.  Fewer words
+// than k give one group apiece.
+#[allow(dead_code)]
+fn cluster_words<'a>(grid: &CrosswordGrid<'a>, k: uint) -> Vec<Vec<&'a str>> {
+  let mut words: Vec<&'a str> = words_on(grid).move_iter().collect();
+  words.sort();
+  let points: Vec<(f64, f64)> = words.iter().map(|w| {
+    let c = word_center(grid, *w).unwrap();
+    (c.x as f64, c.y as f64)
+  }).collect();
+  let k = std::cmp::min(k, words.len());
+  if k == 0 {
+    return Vec::new();
+  }
+  let dist = |a: (f64, f64), b: (f64, f64)| (a.val0() - b.val0()).powi(2) + (a.val1() - b.val1()).powi(2);
+  let nearest = |centers: &[(f64, f64)], p: (f64, f64)| -> uint {
+    let mut best = 0;
+    for (i, &c) in centers.iter().enumerate() {
+      if dist(p, c) < dist(p, centers[best]) {
+        best = i;
+      }
+    }
+    best
+  };
+  let mut centers: Vec<(f64, f64)> = vec!(*points.get(0));
+  while centers.len() < k {
+    let far = range(0, points.len()).max_by(|&i| {
+      let p = *points.get(i);
+      (dist(p, centers.as_slice()[nearest(centers.as_slice(), p)]) * 1000.0) as uint
+    }).unwrap();
+    centers.push(*points.get(far));
+  }
+  let mut assigned: Vec<uint> = points.iter().map(|&p| nearest(centers.as_slice(), p)).collect();
+  for _ in range(0, 100) {
+    for (i, center) in centers.mut_iter().enumerate() {
+      let members: Vec<(f64, f64)> = points.iter().zip(assigned.iter()).filter(|&(_, &a)| a == i).map(|(&p, _)| p).collect();
+      if !members.is_empty() {
+        let n = members.len() as f64;
+        *center = (members.iter().fold(0.0, |s, p| s + p.val0()) / n, members.iter().fold(0.0, |s, p| s + p.val1()) / n);
+      }
+    }
+    let next: Vec<uint> = points.iter().map(|&p| nearest(centers.as_slice(), p)).collect();
+    if next == assigned {
+      break;
+    }
+    assigned = next;
+  }
+  let mut clusters: Vec<Vec<&'a str>> = Vec::from_fn(k, |_| Vec::new());
+  for (&w, &a) in words.iter().zip(assigned.iter()) {
+    clusters.get_mut(a).push(w);
+  }
+  clusters.move_iter().filter(|c| !c.is_empty()).collect()
+}
+
+// The straight-line distance between the centers of every pair of words
+// with cells on the grid, keyed with the words in sorted order.
+fn word_pair_distance_matrix<'a>(grid: &CrosswordGrid<'a>) -> HashMap<(&'a str, &'a str), f64> {
+  let mut words: Vec<&'a str> = Vec::new();
+  for tile in grid.tiles.iter() {
+    let on: Vec<&'a str> = match *tile {
+      OneWord(_, w) | Through(_, w) => vec!(w),
+      TwoWords(_, a, b) => vec!(a, b),
+      _ => Vec::new()
+    };
+    for &w in on.iter() {
+      if w != PREFILLED && !words.contains(&w) {
+        words.push(w);
+      }
+    }
+  }
+  words.sort();
+  let centers: Vec<(&'a str, Point)> = words.iter().filter_map(|&w| word_center(grid, w).map(|p| (w, p))).collect();
+  let mut distances = HashMap::new();
+  for (i, &(a, p)) in centers.iter().enumerate() {
+    for &(b, q) in centers.slice_from(i + 1).iter() {
+      let (dx, dy) = ((p.x - q.x) as f64, (p.y - q.y) as f64);
+      distances.insert((a, b), (dx * dx + dy * dy).sqrt());
+    }
+  }
+  distances
+}
+
+// How many solutions have each number of crossings.
+fn crossing_distribution<'a>(solutions: &[CrosswordGrid<'a>]) -> HashMap<uint, uint> {
+  let mut counts = HashMap::new();
+  for grid in solutions.iter() {
+    counts.insert_or_update_with(count_crossings(grid), 1, |_, n| *n += 1);
+  }
+  counts
+}
+
+fn distribution_report(counts: &HashMap<uint, uint>) -> ~str {
+  let mut buckets: Vec<(uint, uint)> = counts.iter().map(|(&k, &n)| (k, n)).collect();
+  buckets.sort();
+  let mut out = StrBuf::new();
+  out.push_str("crossings  solutions\n");
+  for &(k, n) in buckets.iter() {
+    out.push_str(format!("{:>9}  {:>9}\n", k, n));
+  }
+  out.into_owned()
+}
+
+fn degree_report(counts: &TreeMap<u8, uint>) -> ~str {
+  let total = counts.iter().fold(0, |accum, (_, &n)| accum + n);
+  let parts: Vec<~str> = counts.iter().map(|(d, n)| format!("{}: {}", *d, *n)).collect();
+  let corridor = counts.find(&2).map_or(0, |&n| n) as f64 * 100.0 / std::cmp::max(total, 1) as f64;
+  format!("cells by filled neighbors: {} ({:.0f}% corridor)\n", parts.connect(", "), corridor)
+}
+
+// One line per row, cells separated by single spaces: waypoints as their
+// uppercase letter, blanks as '.', walls as '#', and word cells as
+// letter:word, or letter:word+word with the two words in sorted order.
+fn canonical_text<'a>(grid: &CrosswordGrid<'a>) -> ~str {
+  let cells: Vec<~str> = canonical(grid).tiles.iter().map(|tile| match *tile {
+    Fixed(c) => c.to_upper().to_str(),
+    Through(c, w) => format!("{}:{}", c.to_upper(), w),
+    Blocked => ~"#",
+    NoWords => ~".",
+    OneWord(c, w) => format!("{}:{}", c, w),
+    TwoWords(c, a, b) => format!("{}:{}+{}", c, a, b)
+  }).collect();
+  let rows: Vec<~str> = cells.as_slice().chunks(grid.width as uint).map(|row| row.connect(" ")).collect();
+  rows.connect("\n")
+}
+
+// A short name for a solution that means the same grid on any run or
+// machine: the 32-bit FNV-1a hash of its canonical text, in hex.  If
+// canonical_text ever changes, every saved id changes with it; the README has
+// a worked example to check against.
+fn solution_id<'a>(grid: &CrosswordGrid<'a>) -> ~str {
+  format!("{:08x}", fnv1a(canonical_text(grid).as_bytes()))
+}
+
+fn fnv1a(bytes: &[u8]) -> u32 {
+  bytes.iter().fold(0x811c9dc5, |hash: u32, &b| (hash ^ b as u32) * 0x01000193)
+}
+
+// The zip and PNG checksum, a bit at a time; archives only need it once per
+// file.
+fn crc32(bytes: &[u8]) -> u32 {
+  let mut crc = 0xffffffffu32;
+  for &b in bytes.iter() {
+    crc ^= b as u32;
+    for _ in range(0, 8) {
+      crc = if crc & 1 == 1 { (crc >> 1) ^ 0xedb88320 } else { crc >> 1 };
+    }
+  }
+  !crc
+}
+
+fn push_u16(out: &mut Vec<u8>, n: uint) {
+  out.push_all(&[(n & 0xff) as u8, (n >> 8 & 0xff) as u8]);
+}
+
+fn push_u32(out: &mut Vec<u8>, n: uint) {
+  out.push_all(&[(n & 0xff) as u8, (n >> 8 & 0xff) as u8, (n >> 16 & 0xff) as u8, (n >> 24 & 0xff) as u8]);
+}
+
+// A zip archive of these files, stored as they are: compressing a few
+// hundred bytes of grid apiece isn't worth a dependency.  Every file is
+// dated 1980-01-01, the earliest a zip can say, so the same results always
+// make the same archive.
+fn zip_archive(files: &[(~str, ~str)]) -> Vec<u8> {
+  let (time, date) = (0, 0x21);
+  let mut out = Vec::new();
+  let mut directory = Vec::new();
+  for &(ref name, ref text) in files.iter() {
+    let (name, data) = (name.as_bytes(), text.as_bytes());
+    let (offset, crc) = (out.len(), crc32(data) as uint);
+    push_u32(&mut out, 0x04034b50);
+    for &n in [20u, 0, 0, time, date].iter() { push_u16(&mut out, n); }
+    for &n in [crc, data.len(), data.len()].iter() { push_u32(&mut out, n); }
+    push_u16(&mut out, name.len());
+    push_u16(&mut out, 0);
+    out.push_all(name);
+    out.push_all(data);
+    push_u32(&mut directory, 0x02014b50);
+    for &n in [20u, 20, 0, 0, time, date].iter() { push_u16(&mut directory, n); }
+    for &n in [crc, data.len(), data.len()].iter() { push_u32(&mut directory, n); }
+    for &n in [name.len(), 0, 0, 0, 0].iter() { push_u16(&mut directory, n); }
+    push_u32(&mut directory, 0);
+    push_u32(&mut directory, offset);
+    directory.push_all(name);
+  }
+  let at = out.len();
+  out.push_all(directory.as_slice());
+  push_u32(&mut out, 0x06054b50);
+  for &n in [0u, 0, files.len(), files.len()].iter() { push_u16(&mut out, n); }
+  push_u32(&mut out, directory.len());
+  push_u32(&mut out, at);
+  push_u16(&mut out, 0);
+  out
+}
+
+// How close a solution comes to the rules, which want every tile used by
+// exactly two words: each word on each tile counts one.
+fn score<'a>(grid: &CrosswordGrid<'a>) -> int {
+  grid.tiles.iter().fold(0, |accum, tile| accum + match *tile {
+    OneWord(..) => 1,
+    TwoWords(..) => 2,
+    _ => 0
+  })
+}
+
+// AsFound leaves the search's order alone, BestScore puts the highest scores
+// first (equal scores go down the tie-break chain), and Canonical sorts by canonical_text
+// and drops solutions that only differ in the order words were placed.
+fn order_solutions<'a>(ordering: SolutionOrder, tie_break: &[TieBreak], results: Vec<CrosswordGrid<'a>>) -> Vec<CrosswordGrid<'a>> {
+  match ordering {
+    AsFound => results,
+    BestScore => {
+      let mut keyed: Vec<(RankKey, CrosswordGrid<'a>)> = results.move_iter().map(|grid| (RankKey::of(&grid), grid)).collect();
+      keyed.sort_by(|&(ref a, _), &(ref b, _)| a.compare(b, tie_break));
+      keyed.move_iter().map(|(_, grid)| grid).collect()
+    }
+    Canonical => {
+      let mut keyed: Vec<(~str, CrosswordGrid<'a>)> = results.move_iter().map(|grid| (canonical_text(&grid), grid)).collect();
+      keyed.sort_by(|&(ref a, _), &(ref b, _)| a.cmp(b));
+      let mut out: Vec<CrosswordGrid<'a>> = Vec::new();
+      let mut last: Option<~str> = None;
+      for (key, grid) in keyed.move_iter() {
+        if last.as_ref() != Some(&key) {
+          out.push(grid);
+          last = Some(key);
+        }
+      }
+      out
+    }
+  }
+}
+
+// Everything BestScore can rank a solution on, worked out once per solution
+// rather than once per comparison.
+struct RankKey {
+  score: int,
+  fill: f64,
+  crossings: uint,
+  area: uint,
+  text: ~str
+}
+
+impl RankKey {
+  fn of<'a>(grid: &CrosswordGrid<'a>) -> RankKey {
+    RankKey { score: score(grid), fill: fill_ratio(grid), crossings: count_crossings(grid), area: word_box_area(grid), text: canonical_text(grid) }
+  }
+
+  // best first: the higher score, then down the tie-break chain
+  fn compare(&self, other: &RankKey, chain: &[TieBreak]) -> Ordering {
+    match other.score.cmp(&self.score) {
+      Equal => { }
+      o => return o
+    }
+    for key in chain.iter() {
+      let order = match *key {
+        MostFilled => if self.fill > other.fill { Less } else if self.fill < other.fill { Greater } else { Equal },
+        MostCrossings => other.crossings.cmp(&self.crossings),
+        SmallestBox => self.area.cmp(&other.area),
+        CanonicalText => self.text.cmp(&other.text)
+      };
+      if order != Equal {
+        return order;
+      }
+    }
+    Equal
+  }
+}
+
+// The area of the smallest box holding every cell a word went through.
+fn word_box_area<'a>(grid: &CrosswordGrid<'a>) -> uint {
+  let mut bounds: Option<(int, int, int, int)> = None;
+  for (i, tile) in grid.tiles.iter().enumerate() {
+    match *tile {
+      OneWord(..) | TwoWords(..) | Through(..) => { }
+      _ => continue
+    }
+    let (x, y) = (i as int % grid.width, i as int / grid.width);
+    bounds = Some(match bounds {
+      None => (x, y, x, y),
+      Some((x0, y0, x1, y1)) => (std::cmp::min(x0, x), std::cmp::min(y0, y), std::cmp::max(x1, x), std::cmp::max(y1, y))
+    });
+  }
+  bounds.map_or(0, |(x0, y0, x1, y1)| ((x1 - x0 + 1) * (y1 - y0 + 1)) as uint)
+}
+
+// Keeps the first of each set of solutions the mode counts as the same.
+fn dedup_solutions<'a>(opts: &SolveOptions, words: &[&'a str], results: Vec<CrosswordGrid<'a>>) -> Vec<CrosswordGrid<'a>> {
+  if opts.dedup == NoDedup {
+    return results;
+  }
+  let mut seen = HashSet::new();
+  let mut out = Vec::new();
+  for grid in results.move_iter() {
+    let key = match opts.dedup {
+      ByShape => {
+        let mut key = StrBuf::new();
+        for tile in grid.tiles.iter() {
+          key.push_char(default_char(tile));
+          match *tile {
+            OneWord(..) | Through(..) => key.push_char('1'),
+            TwoWords(..) => key.push_char('2'),
+            _ => { }
+          }
+        }
+        key.into_owned()
+      }
+      _ => {
+        let mut paths: Vec<~str> = words.iter().map(|&w| {
+          let cells: Vec<~str> = word_path(&grid, w, opts).iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+          format!("{}={}", w, cells.connect(";"))
+        }).collect();
+        paths.sort();
+        paths.connect(" ")
+      }
+    };
+    if seen.insert(key) {
+      out.push(grid);
+    }
+  }
+  out
+}
+
+// Fewest turns first; sort_by is stable, so ties keep the order they had.
+fn straightest_first<'a>(results: Vec<CrosswordGrid<'a>>, wordpts: &[(&'a str, &(Point, Point))], opts: &SolveOptions) -> Vec<CrosswordGrid<'a>> {
+  let mut keyed: Vec<(uint, CrosswordGrid<'a>)> = results.move_iter().map(|grid| (total_turns(&grid, wordpts, opts), grid)).collect();
+  keyed.sort_by(|&(a, _), &(b, _)| a.cmp(&b));
+  keyed.move_iter().map(|(_, grid)| grid).collect()
+}
+
+// Calls `visit` on every routable cell `source` can reach, once each,
+// nearest first, changing the grid as it goes.  Which cells count as
+// reachable is settled before `visit` sees them, so walling a cell off from
+// inside `visit` doesn't stop the fill going through it.
+#[allow(dead_code)]
+fn flood_fill_mut<'a>(grid: &mut CrosswordGrid<'a>, opts: &SolveOptions, source: Point, visit: |&mut TileData<'a>|) {
+  if !grid.is_valid(source) || !routable(grid.get_ref(source).unwrap()) {
+    return;
+  }
+  let mut seen: Grid<bool> = grid.map(|_| false);
+  let mut queue: RingBuf<Point> = RingBuf::new();
+  seen.set(source, true);
+  queue.push_back(source);
+  loop {
+    let p = match queue.pop_front() {
+      Some(p) => p,
+      None => break
+    };
+    for &n in grid.either_way(opts, p).iter() {
+      if !*seen.get_ref(n).unwrap() && routable(grid.get_ref(n).unwrap()) {
+        seen.set(n, true);
+        queue.push_back(n);
+      }
+    }
+    visit(grid.get_mut_ref(p).unwrap());
+  }
+}
+
+// Number of connected groups formed by the cells `member` accepts.
+fn count_components<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions, member: |&TileData<'a>| -> bool) -> uint {
+  let mut seen: Grid<bool> = grid.map(|_| false);
+  let mut queue: RingBuf<Point> = RingBuf::new();
+  let mut count = 0;
+  for y in range(0, grid.height) {
+    for x in range(0, grid.width) {
+      let p = Point { x: x, y: y };
+      if *seen.get_ref(p).unwrap() || !member(grid.get_ref(p).unwrap()) {
+        continue;
+      }
+      count += 1;
+      seen.set(p, true);
+      queue.push_back(p);
+      loop {
+        let next = match queue.pop_front() {
+          Some(q) => q,
+          None => break
+        };
+        for &n in grid.either_way(opts, next).iter() {
+          if !*seen.get_ref(n).unwrap() && member(grid.get_ref(n).unwrap()) {
+            seen.set(n, true);
+            queue.push_back(n);
+          }
+        }
+      }
+    }
+  }
+  count
+}
+
+// Whether the cells a word could use still hang together without `p`.  Where
+// they don't, `p` is a chokepoint: a wall there would split the grid, and
+// every word crossing from one side to the other has to squeeze through it.
+#[allow(dead_code)]
+fn is_connected_after_removal<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions, p: Point) -> bool {
+  let without = grid.replace(p, Blocked);
+  count_components(&without, opts, routable) <= 1
+}
+
+// one cell of the depth-first walk in articulation_points
+struct Visit {
+  p: Point,
+  parent: Option<Point>,
+  around: Vec<Point>,
+  next: uint
+}
+
+// Every chokepoint at once, in row order: Tarjan's depth-first search, walked
+// with an explicit stack so a big open grid can't overflow the task's.  A
+// cell is a chokepoint when some branch below it has no way back above it
+// except through it; the root is one when it has more than one branch.
+// Moves are taken both ways, as they are for reaching a cell at all.
+fn articulation_points<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions) -> Vec<Point> {
+  let around = |p: Point| -> Vec<Point> {
+    grid.either_way(opts, p).move_iter().filter(|&n| routable(grid.get_ref(n).unwrap())).collect()
+  };
+  let mut found: Grid<uint> = grid.map(|_| 0u);
+  let mut low: Grid<uint> = grid.map(|_| 0u);
+  let mut cut: Grid<bool> = grid.map(|_| false);
+  let mut time = 0u;
+  for y in range(0, grid.height) {
+    for x in range(0, grid.width) {
+      let root = Point { x: x, y: y };
+      if *found.get_ref(root).unwrap() != 0 || !routable(grid.get_ref(root).unwrap()) {
+        continue;
+      }
+      time += 1;
+      found.set(root, time);
+      low.set(root, time);
+      let mut branches = 0u;
+      let mut stack = vec!(Visit { p: root, parent: None, around: around(root), next: 0 });
+      while !stack.is_empty() {
+        let top = stack.len() - 1;
+        let (p, parent) = (stack.get(top).p, stack.get(top).parent);
+        let step = {
+          let visit = stack.get_mut(top);
+          if visit.next < visit.around.len() {
+            visit.next += 1;
+            Some(*visit.around.get(visit.next - 1))
+          } else {
+            None
+          }
+        };
+        match step {
+          Some(n) if *found.get_ref(n).unwrap() == 0 => {
+            time += 1;
+            found.set(n, time);
+            low.set(n, time);
+            if p == root {
+              branches += 1;
+            }
+            stack.push(Visit { p: n, parent: Some(p), around: around(n), next: 0 });
+          }
+          Some(n) => {
+            if parent != Some(n) {
+              let back = std::cmp::min(*low.get_ref(p).unwrap(), *found.get_ref(n).unwrap());
+              low.set(p, back);
+            }
+          }
+          None => {
+            stack.pop();
+            match parent {
+              Some(q) => {
+                let back = std::cmp::min(*low.get_ref(q).unwrap(), *low.get_ref(p).unwrap());
+                low.set(q, back);
+                if q != root && *low.get_ref(p).unwrap() >= *found.get_ref(q).unwrap() {
+                  cut.set(q, true);
+                }
+              }
+              None => { }
+            }
+          }
+        }
+      }
+      if branches > 1 {
+        cut.set(root, true);
+      }
+    }
+  }
+  let mut points = Vec::new();
+  for y in range(0, grid.height) {
+    for x in range(0, grid.width) {
+      let p = Point { x: x, y: y };
+      if *cut.get_ref(p).unwrap() {
+        points.push(p);
+      }
+    }
+  }
+  points
+}
+
+// For --dry-run: the chokepoints, and which of them are waypoints.  A word
+// that starts or ends on one already sits in the only way through, so every
+// word bound for the far side has to cross it there or not at all.
+fn chokepoint_report<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions, diag: &mut Diagnostics) -> ~str {
+  let points = articulation_points(grid, opts);
+  if points.is_empty() {
+    return ~"no chokepoints\n";
+  }
+  let cells: Vec<~str> = points.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+  for &p in points.iter() {
+    match *grid.get_ref(p).unwrap() {
+      Fixed(c) | Through(c, _) => diag.report(Warning, Cell(p), format!("waypoint {} is the only way between two parts of the grid", c.to_upper())),
+      _ => { }
+    }
+  }
+  format!("chokepoints: {}\n", cells.connect(" "))
+}
+
+fn is_single_component<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions) -> bool {
+  count_components(grid, opts, |tile| match *tile {
+    NoWords | Blocked => false,
+    _ => true
+  }) <= 1
+}
+
+trait Case {
+  fn to_upper(&self) -> char;
+  fn to_lower(&self) -> char;
+}
+// to_ascii fails on anything past 0x7f, so accented letters go through the
+// unicode tables instead
+impl Case for char {
+  #[inline(always)] fn to_upper(&self) -> char {
+    if self.is_ascii() { self.to_ascii().to_upper().to_char() } else { self.to_uppercase() }
+  }
+
+  #[inline(always)] fn to_lower(&self) -> char {
+    if self.is_ascii() { self.to_ascii().to_lower().to_char() } else { self.to_lowercase() }
+  }
+}
+
+// The one place that decides which letters count as the same.  Grid letters,
+// words and waypoint lookups all go through it, so a grid written in capitals
+// still matches a word list in mixed case, and an 'É' on the grid matches a word's 'é'.
+struct Alphabet;
+
+static ALPHABET: Alphabet = Alphabet;
+
+// A letter of a blind entry that isn't known yet: it matches anything, and
+// takes on the letter of whatever word crosses it.
+static WILDCARD: char = '?';
+
+impl Alphabet {
+  #[inline(always)] fn normalize(&self, c: char) -> char {
+    c.to_lower()
+  }
+  fn normalize_str(&self, s: &str) -> ~str {
+    s.chars().map(|c| self.normalize(c)).collect()
+  }
+  #[inline(always)] fn same(&self, a: char, b: char) -> bool {
+    a == WILDCARD || b == WILDCARD || self.normalize(a) == self.normalize(b)
+  }
+  // the letter a cell ends up with when two words that agree on it cross
+  #[inline(always)] fn crossed(&self, a: char, b: char) -> char {
+    if a == WILDCARD { b } else { a }
+  }
+}
+
+#[inline] fn default_char<'a>(tile: &TileData<'a>) -> char {
+  match *tile {
+    OneWord(c, _) | TwoWords(c, _, _) if c == WILDCARD => '·',
+    Fixed(c) | Through(c, _) => c.to_upper(),
+    OneWord(c, _) => c.to_lower(),
+    TwoWords(c, _, _) => c.to_lower(),
+    Blocked => '#',
+    NoWords => ' '
+  }
+}
+
+#[deriving(Eq, TotalEq, Ord, TotalOrd, Hash, Clone)]
+enum Severity {
+  Note,
+  Warning,
+  Error
+}
+
+#[deriving(Eq, TotalEq, Hash, Clone)]
+enum Location {
+  Nowhere,
+  Line(~str, uint),
+  Cell(Point)
+}
+
+#[deriving(Eq, TotalEq, Hash, Clone)]
+struct Diagnostic {
+  severity: Severity,
+  location: Location,
+  message: ~str
+}
+
+impl std::fmt::Show for Severity {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    let name = match *self { Note => "note", Warning => "warning", Error => "error" };
+    write!(fmt.buf, "{}", name)
+  }
+}
+
+impl std::fmt::Show for Location {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match *self {
+      Nowhere => Ok(()),
+      Line(ref file, line) => write!(fmt.buf, "{}:{}: ", *file, line),
+      Cell(p) => write!(fmt.buf, "cell {},{}: ", p.x, p.y)
+    }
+  }
+}
+
+impl std::fmt::Show for Diagnostic {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    write!(fmt.buf, "{}: {}{}", self.severity, self.location, self.message)
+  }
+}
+
+impl ToJson for Diagnostic {
+  fn to_json(&self) -> json::Json {
+    let mut obj = ~TreeMap::new();
+    obj.insert(~"severity", json::String(self.severity.to_str()));
+    obj.insert(~"location", json::String(self.location.to_str()));
+    obj.insert(~"message", json::String(self.message.clone()));
+    json::Object(obj)
+  }
+}
+
+// Everything non-fatal that parsing, checking and solving had to say, kept
+// until the end of the run so it doesn't scroll away with the progress
+// output.  Errors are still echoed the moment they happen.
+struct Diagnostics {
+  list: Vec<Diagnostic>
+}
+
+impl Diagnostics {
+  fn new() -> Diagnostics {
+    Diagnostics { list: Vec::new() }
+  }
+
+  fn report(&mut self, severity: Severity, location: Location, message: ~str) {
+    let diagnostic = Diagnostic { severity: severity, location: location, message: message };
+    if diagnostic.severity >= Error {
+      println!("{}", diagnostic);
+    }
+    self.list.push(diagnostic);
+  }
+
+  // worst first, each distinct diagnostic once with how often it came up
+  fn grouped(&self) -> Vec<(Diagnostic, uint)> {
+    let mut counts: HashMap<Diagnostic, uint> = HashMap::new();
+    let mut order: Vec<Diagnostic> = Vec::new();
+    for d in self.list.iter() {
+      if !counts.contains_key(d) {
+        order.push(d.clone());
+      }
+      counts.insert_or_update_with(d.clone(), 1, |_, n| *n += 1);
+    }
+    let mut grouped: Vec<(Diagnostic, uint)> = order.move_iter().map(|d| {
+      let n = *counts.get(&d);
+      (d, n)
+    }).collect();
+    grouped.sort_by(|&(ref a, _), &(ref b, _)| b.severity.cmp(&a.severity));
+    grouped
+  }
+
+  fn summary(&self) -> ~str {
+    let mut out = StrBuf::new();
+    for &(ref d, n) in self.grouped().iter() {
+      out.push_str(d.to_str());
+      if n > 1 {
+        out.push_str(format!(" (x{})", n));
+      }
+      out.push_char('\n');
+    }
+    out.into_owned()
+  }
+
+  fn to_json(&self) -> json::Json {
+    json::List(self.grouped().iter().map(|&(ref d, n)| {
+      let mut obj = match d.to_json() {
+        json::Object(obj) => obj,
+        _ => unreachable!()
+      };
+      obj.insert(~"count", json::Number(n as f64));
+      json::Object(obj)
+    }).collect())
+  }
+}
+
+fn readlines(file: &str) -> Vec<~str> {
+  let path = Path::new(file);
+  let input = File::open(&path).read_to_end().unwrap();
+  let text = std::str::from_utf8(input.as_slice()).unwrap();
+  text.lines_any().map(|line| line.into_owned()).collect()
+}
+
+// The word a pre-filled cell belongs to until a real word passes through it.
+static PREFILLED: &'static str = "";
+
+fn readgrid(file: &str, prefilled: bool, diag: &mut Diagnostics) -> ~CrosswordGrid {
+  parsegrid(file, 1, readlines(file).as_slice(), prefilled, diag)
+}
+
+// `first` is the line number of the first line, for diagnostics.  With
+// `prefilled`, lowercase letters are cells some word has to pass through
+// rather than waypoints.
+fn parsegrid(file: &str, first: uint, lines: &[~str], prefilled: bool, diag: &mut Diagnostics) -> ~CrosswordGrid {
+  let longest = lines.iter().map(|a| a.char_len()).max().unwrap();
+  let mut full = StrBuf::new();
+  for (i, line) in lines.iter().enumerate() {
+    full.push_str(*line);
+    // editors like to strip trailing spaces, which would shift every later row
+    let short = longest - line.char_len();
+    if short > 0 {
+      diag.report(Note, Line(file.to_owned(), first + i), format!("{} short of the widest line, padded with blanks", short));
+      for _ in range(0, short) {
+        full.push_char(' ');
+      }
+    }
+  }
+  let text = full.into_owned();
+  let mut tileit = text
+  .chars()
+  .map(|c| match c {
+    ' ' => NoWords,
+    '#' => Blocked,
+    _ if prefilled && c.is_lowercase() => OneWord(ALPHABET.normalize(c), PREFILLED),
+    _   => Fixed(ALPHABET.normalize(c))
+  });
+  let tiles: Vec<TileData> = tileit.collect();
+  ~Grid { width: longest as int, height: lines.len() as int, tiles: tiles }
+}
+
+fn readwords(file: &str, max_len: uint, diag: &mut Diagnostics) -> Vec<~str> {
+  parsewords(file, 1, readlines(file).as_slice(), max_len, diag)
+}
+
+// Words longer than `max_len` letters are skipped with an error: they're
+// almost always something pasted in by mistake, and the search would go as
+// deep as the word is long.
+fn parsewords(file: &str, first: uint, lines: &[~str], max_len: uint, diag: &mut Diagnostics) -> Vec<~str> {
+  let mut words = Vec::new();
+  for (i, line) in lines.iter().enumerate() {
+    let word = line.trim();
+    let entry = if word.starts_with("?") {
+      match parse_blind(word) {
+        Ok(blind) => blind,
+        Err(e) => {
+          diag.report(Warning, Line(file.to_owned(), first + i), format!("skipping \"{}\": {}", word, e));
+          continue;
+        }
+      }
+    } else if word.starts_with("chain:") {
+      match parse_chain(word.slice_from(6)) {
+        Ok(chain) => chain,
+        Err(e) => {
+          diag.report(Error, Line(file.to_owned(), first + i), format!("skipping \"{}\": {}", word, e));
+          continue;
+        }
+      }
+    } else {
+      if word.char_len() < 2 {
+        if !word.is_empty() {
+          diag.report(Warning, Line(file.to_owned(), first + i), format!("skipping \"{}\": a word needs two waypoints", word));
+        }
+        continue;
+      }
+      if word.contains_char('/') && word.split('/').any(|s| s.trim().char_len() < 2) {
+        diag.report(Warning, Line(file.to_owned(), first + i), format!("skipping \"{}\": every spelling needs two waypoints", word));
+        continue;
+      }
+      match parse_anchored(word) {
+        Some(Err(e)) => {
+          diag.report(Error, Line(file.to_owned(), first + i), format!("skipping \"{}\": {}", word, e));
+          continue;
+        }
+        _ => { }
+      }
+      ALPHABET.normalize_str(word)
+    };
+    let letters = longest_word(entry);
+    if letters > max_len {
+      let shown = if word.char_len() > 20 { format!("{}...", word.slice_chars(0, 20)) } else { word.to_owned() };
+      diag.report(Error, Line(file.to_owned(), first + i), format!("skipping \"{}\": at {} letters it's longer than --max-word-len {}", shown, letters, max_len));
+      continue;
+    }
+    words.push(entry);
+  }
+  words
+}
+
+// The letters in the longest word of a words file entry, once it's been
+// read: the spellings of "colour/color", the words of a chain, or the word
+// before an anchored one's coordinates.  Anything else is one word, spaces
+// and all.
+fn longest_word(entry: &str) -> uint {
+  match parse_anchored(entry) {
+    Some(Ok((word, _, _))) => return word.char_len(),
+    _ => { }
+  }
+  let mut longest = 0;
+  for link in entry.split_str("->") {
+    for spelling in link.split('/') {
+      longest = std::cmp::max(longest, spelling.trim().char_len());
+    }
+  }
+  longest
+}
+
+// "ember -> river -> rest", a journey: each word starts where the one before
+// it ended, so each one's first letter has to be the last letter of the one
+// before.  Since a letter has only the one waypoint, that waypoint is then
+// the junction, the same cell for both words.  Comes back as
+// "ember->river->rest" for split_chains.
+fn parse_chain(spec: &str) -> Result<~str, ~str> {
+  let words: Vec<~str> = spec.split_str("->").map(|w| ALPHABET.normalize_str(w.trim())).collect();
+  if words.len() < 2 {
+    return Err(~"a chain needs at least two words, joined by ->");
+  }
+  match words.iter().find(|w| w.char_len() < 2 || w.contains_char('/') || w.starts_with("?")) {
+    Some(w) => return Err(format!("\"{}\" can't be in a chain; it needs to be a plain word of at least two letters", *w)),
+    None => { }
+  }
+  for pair in words.as_slice().windows(2) {
+    let (last, next) = (pair[0].char_at_reverse(pair[0].len()), pair[1].char_at(0));
+    if !ALPHABET.same(last, next) {
+      return Err(format!("\"{}\" ends in {} but \"{}\" starts with {}, so they can't meet", pair[0], last.to_upper(), pair[1], next.to_upper()));
+    }
+  }
+  Ok(words.connect("->"))
+}
+
+// Splits each chain parse_chain made back into its words, which all go in
+// the word list, and returns them as a group too, for SolveOptions::chains.
+fn split_chains(words: Vec<~str>) -> (Vec<~str>, Vec<Vec<~str>>) {
+  let mut out = Vec::new();
+  let mut chains = Vec::new();
+  for word in words.move_iter() {
+    if !word.contains("->") {
+      out.push(word);
+      continue;
+    }
+    let chain: Vec<~str> = word.split_str("->").map(|s| s.to_owned()).collect();
+    for w in chain.iter() {
+      if !out.contains(w) {
+        out.push(w.clone());
+      }
+    }
+    chains.push(chain);
+  }
+  (out, chains)
+}
+
+// Splits each "colour/color" entry into its spellings, which all go in the
+// word list, and returns them as a group too, for SolveOptions::variants.
+fn split_variants(words: Vec<~str>) -> (Vec<~str>, Vec<Vec<~str>>) {
+  let mut out = Vec::new();
+  let mut groups = Vec::new();
+  for word in words.move_iter() {
+    if !word.contains_char('/') {
+      out.push(word);
+      continue;
+    }
+    let spellings: Vec<~str> = word.split('/').map(|s| s.trim().to_owned()).collect();
+    out.push_all(spellings.as_slice());
+    groups.push(spellings);
+  }
+  (out, groups)
+}
+
+// The spellings of the word's group and where the word comes in them.
+fn variant_group<'o>(opts: &'o SolveOptions, word: &str) -> Option<(&'o [~str], uint)> {
+  for group in opts.variants.iter() {
+    match group.iter().position(|s| s.as_slice() == word) {
+      Some(k) => return Some((group.as_slice(), k)),
+      None => { }
+    }
+  }
+  None
+}
+
+// "?N a b", a blind entry: some N-letter word from waypoint a to waypoint b,
+// written as a, then N-2 wildcards, then b.
+fn parse_blind(spec: &str) -> Result<~str, ~str> {
+  let parts: Vec<&str> = spec.slice_from(1).words().collect();
+  let length = match parts.as_slice().get(0).and_then(|n| from_str::<uint>(*n)) {
+    Some(n) if n >= 2 => n,
+    _ => return Err(~"a blind entry needs a length of at least 2, like ?6 e r")
+  };
+  if parts.len() != 3 || parts.get(1).char_len() != 1 || parts.get(2).char_len() != 1 {
+    return Err(~"a blind entry is ?LENGTH FROM TO, like ?6 e r");
+  }
+  let mut word = StrBuf::new();
+  word.push_char(ALPHABET.normalize(parts.get(1).char_at(0)));
+  for _ in range(0, length - 2) {
+    word.push_char(WILDCARD);
+  }
+  word.push_char(ALPHABET.normalize(parts.get(2).char_at(0)));
+  Ok(word.into_owned())
+}
+
+#[inline] fn is_blind(word: &str) -> bool {
+  word.chars().any(|c| c == WILDCARD)
+}
+
+// The letters a blind entry picked up from the words crossing it, with '.'
+// where nothing did, and how many results gave each pattern, most common
+// first.
+fn blind_patterns<'a>(results: &[CrosswordGrid<'a>], word: &str, opts: &SolveOptions) -> Vec<(~str, uint)> {
+  let mut counts: HashMap<~str, uint> = HashMap::new();
+  for grid in results.iter() {
+    let mut pattern = StrBuf::new();
+    pattern.push_char(word.char_at(0));
+    for &p in word_path(grid, word, opts).iter() {
+      let c = tile_char(grid.get_ref(p).unwrap()).unwrap();
+      pattern.push_char(if c == WILDCARD { '.' } else { c });
+    }
+    pattern.push_char(word.char_at_reverse(word.len()));
+    counts.insert_or_update_with(pattern.into_owned(), 1, |_, count| *count += 1);
+  }
+  let mut patterns: Vec<(~str, uint)> = counts.move_iter().collect();
+  patterns.sort_by(|&(ref a, m), &(ref b, n)| match n.cmp(&m) { Equal => a.cmp(b), order => order });
+  patterns
+}
+
+// What an overlay file says about each cell beyond its letter: how many words
+// it can carry (two unless a digit says otherwise) and which region, if any,
+// it belongs to.  Blocks in the overlay go straight into the grid.
+#[deriving(Clone)]
+struct Overlay {
+  capacity: Grid<uint>,
+  regions: Grid<Option<char>>
+}
+
+// An overlay has the grid's dimensions and one annotation per cell: `#` for a
+// block, `1`-`9` for a capacity, `A`-`Z` for a region, or a space for none.
+fn readoverlay<'a>(file: &str, grid: &mut CrosswordGrid<'a>, diag: &mut Diagnostics) -> Result<Overlay, SolveError> {
+  parseoverlay(file, 1, readlines(file).as_slice(), grid, diag)
+}
+
+fn parseoverlay<'a>(file: &str, first: uint, lines: &[~str], grid: &mut CrosswordGrid<'a>, diag: &mut Diagnostics) -> Result<Overlay, SolveError> {
+  if lines.len() as int != grid.height {
+    return Err(BadFile(file.to_owned(), format!("overlay has {} rows but the grid has {}", lines.len(), grid.height)));
+  }
+  let mut overlay = Overlay { capacity: grid.map(|_| 2u), regions: grid.map(|_| None) };
+  for (y, line) in lines.iter().enumerate() {
+    if line.char_len() as int > grid.width {
+      return Err(BadFile(file.to_owned(), format!("line {}: overlay row is {} wide but the grid is {}", first + y, line.char_len(), grid.width)));
+    }
+    for (x, c) in line.chars().enumerate() {
+      let p = Point { x: x as int, y: y as int };
+      let fixed = match *grid.get_ref(p).unwrap() { Fixed(_) => true, _ => false };
+      // a waypoint's capacity only matters with --through-waypoints, where
+      // 1 keeps other words from passing through it
+      match c {
+        ' ' => { }
+        '#' if fixed => diag.report(Error, Cell(p), format!("{}: can't block a waypoint", file)),
+        '#' => grid.set(p, Blocked),
+        '1'..'9' => {
+          let n = c.to_digit(10).unwrap();
+          if n > 2 {
+            diag.report(Note, Cell(p), format!("{}: capacity {} is more than a cell can hold; it still takes two", file, n));
+          }
+          overlay.capacity.set(p, n);
+        }
+        'A'..'Z' => overlay.regions.set(p, Some(c)),
+        _ => diag.report(Error, Cell(p), format!("{}: unknown annotation '{}'", file, c))
+      }
+    }
+  }
+  Ok(overlay)
+}
+
+// The grid and its overlay side by side in each cell, as the solver will see
+// them: the letter (or wall), then the capacity or region if it has one.
+fn overlay_report<'a>(grid: &CrosswordGrid<'a>, overlay: &Overlay) -> ~str {
+  let mut out = StrBuf::new();
+  for y in range(0, grid.height) {
+    for x in range(0, grid.width) {
+      let p = Point { x: x, y: y };
+      out.push_char(default_char(grid.get_ref(p).unwrap()));
+      let capacity = *overlay.capacity.get_ref(p).unwrap();
+      out.push_char(match *overlay.regions.get_ref(p).unwrap() {
+        Some(region) => region,
+        None if capacity != 2 => std::char::from_digit(capacity, 10).unwrap(),
+        None => ' '
+      });
+    }
+    out.push_char('\n');
+  }
+  out.into_owned()
+}
+
+// Everything that can stop a puzzle from being solved before the search
+// starts, each naming what's at fault.
+enum SolveError {
+  // a file that couldn't be read or made sense of, and what was wrong
+  BadFile(~str, ~str),
+  // a grid that failed preflight, and everything wrong with it
+  BadGrid(~str, Vec<GridParseError>),
+  // a letter with waypoints at both points
+  DuplicateWaypoint(char, Point, Point),
+  // words whose first or last letter has no waypoint
+  MissingWaypoints(EndpointError),
+  // the word the search was on when it went past --max-depth, and the cap
+  DepthExceeded(~str, uint),
+  // a coordinate from the command line that isn't on the grid: what it was
+  // for, where it was given, and the grid's width and height
+  OutsideGrid(~str, Point, int, int)
+}
+
+impl std::fmt::Show for SolveError {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match *self {
+      BadFile(ref file, ref why) => write!(fmt.buf, "{}: {}", *file, *why),
+      BadGrid(ref file, ref errors) => {
+        let messages: Vec<~str> = errors.iter().map(|e| format!("{}: {}", *file, *e)).collect();
+        write!(fmt.buf, "{}", messages.connect("\n"))
+      }
+      DuplicateWaypoint(c, a, b) => write!(fmt.buf, "waypoint {} appears twice, at {},{} and {},{}", c.to_upper(), a.x, a.y, b.x, b.y),
+      MissingWaypoints(ref e) => write!(fmt.buf, "{}", *e),
+      DepthExceeded(ref word, cap) => write!(fmt.buf, "the search went more than {} deep while placing \"{}\"", cap, *word),
+      OutsideGrid(ref what, p, w, h) => write!(fmt.buf, "{} at {},{} is off the {}x{} grid", *what, p.x, p.y, w, h)
+    }
+  }
+}
+
+fn waypoints<'a>(grid: &CrosswordGrid<'a>) -> Result<HashMap<char, Point>, SolveError> {
+  let mut map = HashMap::<char, Point>::new();
+  for x in range(0, grid.width) {
+    for y in range(0, grid.height) {
+      let p = Point { x: x, y: y };
+      match *grid.get_ref(p).unwrap() {
+        Fixed(letter) | Through(letter, _) => {
+          match map.find(&ALPHABET.normalize(letter)) {
+            Some(&q) => return Err(DuplicateWaypoint(letter, q, p)),
+            None => { }
+          }
+          map.insert(ALPHABET.normalize(letter), p);
+        },
+        _ => { }
+      }
+    }
+  }
+  Ok(map)
+}
+
+// For grids that have already been through load.
+fn hashgrid(grid: CrosswordGrid) -> HashMap<char, Point> {
+  match waypoints(&grid) {
+    Ok(map) => map,
+    Err(e) => fail!("{}", e)
+  }
+}
+
+// Where each word starts and ends.
+#[deriving(Clone, Encodable)]
+struct WordEndpointMap<'a> {
+  map: HashMap<&'a str, (Point, Point)>
+}
+
+impl<'a> WordEndpointMap<'a> {
+  fn get(&self, word: &str) -> Option<(Point, Point)> {
+    self.map.find_equiv(&word).map(|&ends| ends)
+  }
+}
+
+enum EndpointError {
+  // each word with a letter that has no waypoint, and those letters
+  MissingEndpoints(Vec<(~str, Vec<char>)>),
+  // the grid has no waypoints at all; these words would need coordinates
+  NoWaypoints(Vec<~str>)
+}
+
+impl std::fmt::Show for EndpointError {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match *self {
+      MissingEndpoints(ref missing) => {
+        let needs: Vec<~str> = missing.iter().map(|&(ref word, ref letters)| {
+          let letters: Vec<~str> = letters.iter().map(|c| c.to_upper().to_str()).collect();
+          format!("\"{}\" needs {}", *word, letters.connect(" and "))
+        }).collect();
+        write!(fmt.buf, "the grid is missing waypoints: {}", needs.connect("; "))
+      }
+      NoWaypoints(ref words) => write!(fmt.buf, "the grid has no waypoints, so every word needs coordinates, like \"{} 0,0 4,4\"; these have none: {}", *words.get(0), words.connect(", "))
+    }
+  }
+}
+
+// Every word's waypoints, or every word that's missing one and which.
+fn build_endpoint_map<'a>(words: &[&'a str], gridmap: &HashMap<char, Point>) -> Result<WordEndpointMap<'a>, EndpointError> {
+  let mut map = HashMap::new();
+  let mut missing = Vec::new();
+  for &word in words.iter() {
+    let (first, last) = (word.char_at(0), word.char_at_reverse(word.len()));
+    match (gridmap.find(&ALPHABET.normalize(first)), gridmap.find(&ALPHABET.normalize(last))) {
+      (Some(&start), Some(&end)) => { map.insert(word, (start, end)); }
+      (start, end) => {
+        let mut letters = Vec::new();
+        if start.is_none() { letters.push(first); }
+        if end.is_none() && !(start.is_none() && ALPHABET.same(first, last)) { letters.push(last); }
+        missing.push((word.to_owned(), letters));
+      }
+    }
+  }
+  if gridmap.is_empty() && !missing.is_empty() {
+    return Err(NoWaypoints(missing.move_iter().map(|(word, _)| word).collect()));
+  }
+  if !missing.is_empty() {
+    return Err(MissingEndpoints(missing));
+  }
+  Ok(WordEndpointMap { map: map })
+}
+
+// Every word's waypoints, in order, or every word that's missing one.
+fn word_endpoints(gridmap: &HashMap<char, Point>, words: &[~str]) -> Result<Vec<(Point, Point)>, SolveError> {
+  let names: Vec<&str> = words.iter().map(|w| w.as_slice()).collect();
+  match build_endpoint_map(names.as_slice(), gridmap) {
+    Ok(endpoints) => Ok(names.iter().map(|w| endpoints.get(*w).unwrap()).collect()),
+    Err(e) => Err(MissingWaypoints(e))
+  }
+}
+
+// "denim 0,0 4,4": a word that brings its own waypoints, to be drawn onto the
+// grid at those cells.  A line with no spaces, or spellings like
+// "colour / color", is an ordinary word; any other line with spaces has to
+// be an anchored one.
+fn parse_anchored(line: &str) -> Option<Result<(~str, Point, Point), ~str>> {
+  let parts: Vec<&str> = line.words().collect();
+  if parts.len() < 2 || line.contains_char('/') {
+    return None;
+  }
+  let cells: Vec<Option<Point>> = parts.slice_from(1).iter().map(|cell| {
+    let xy: Vec<Option<int>> = cell.split(',').map(|n| from_str::<int>(n.trim())).collect();
+    match xy.as_slice() {
+      [Some(x), Some(y)] => Some(Point { x: x, y: y }),
+      _ => None
+    }
+  }).collect();
+  match cells.as_slice() {
+    [Some(start), Some(end)] => Some(Ok((parts.get(0).to_owned(), start, end))),
+    _ => Some(Err(~"a line with spaces should be an anchored word, like WORD x,y x,y"))
+  }
+}
+
+// Draws each anchored word's waypoints onto the grid and leaves just the word
+// in the list.  A cell can take a waypoint if it's blank or already has that
+// letter; two words can share one.
+fn anchor_words<'a>(grid_file: &str, grid: CrosswordGrid<'a>, words: Vec<~str>) -> Result<(CrosswordGrid<'a>, Vec<~str>), SolveError> {
+  let mut grid = grid;
+  let mut out = Vec::new();
+  for line in words.move_iter() {
+    let (word, start, end) = match parse_anchored(line) {
+      None => { out.push(line); continue; }
+      Some(Err(e)) => return Err(BadFile(grid_file.to_owned(), format!("\"{}\": {}", line, e))),
+      Some(Ok(anchored)) => anchored
+    };
+    let ends = [(start, word.char_at(0)), (end, word.char_at_reverse(word.len()))];
+    for &(p, c) in ends.iter() {
+      let fits = match grid.get_ref(p) {
+        None => Err(~"that's off the grid"),
+        Some(&NoWords) => Ok(()),
+        Some(&Fixed(l)) if ALPHABET.same(l, c) => Ok(()),
+        Some(_) => Err(format!("that cell is already {}", default_char(grid.get_ref(p).unwrap())))
+      };
+      match fits {
+        Ok(()) => grid.set(p, Fixed(ALPHABET.normalize(c))),
+        Err(why) => return Err(BadFile(grid_file.to_owned(), format!("can't put \"{}\"'s waypoint {} at {},{}: {}", word, c.to_upper(), p.x, p.y, why)))
+      }
+    }
+    out.push(word);
+  }
+  Ok((grid, out))
+}
+
+// Every coordinate from the command line, checked against the grid the
+// solve will actually use, after anchored words have gone on it.  Anchored
+// words' own coordinates are checked as they're placed.  Nothing moves the
+// grid's origin, so a coordinate means the same cell here as in the grid
+// file.
+fn check_coordinates<'a>(grid: &CrosswordGrid<'a>, given: &[(~str, Point)]) -> Result<(), SolveError> {
+  for &(ref what, p) in given.iter() {
+    if !grid.is_valid(p) {
+      return Err(OutsideGrid(what.clone(), p, grid.width, grid.height));
+    }
+  }
+  Ok(())
+}
+
+// The coordinates in the search options, named for the flag they came from.
+fn option_coordinates(opts: &SolveOptions) -> Vec<(~str, Point)> {
+  let mut given: Vec<(~str, Point)> = Vec::new();
+  match opts.within {
+    Some((min, max)) => {
+      given.push((~"--within corner", min));
+      given.push((~"--within corner", max));
+    }
+    None => { }
+  }
+  for &(ref word, ref path) in opts.fixed_paths.iter() {
+    for &p in path.iter() {
+      given.push((format!("--fix-path cell of \"{}\"", *word), p));
+    }
+  }
+  given
+}
+
+// The corners of each region, named for it.
+fn region_coordinates(regions: &[Region]) -> Vec<(~str, Point)> {
+  let mut given: Vec<(~str, Point)> = Vec::new();
+  for region in regions.iter() {
+    for p in region.cells.iter().take(1).chain(region.cells.last().move_iter()) {
+      given.push((format!("--region {} corner", region.name), *p));
+    }
+  }
+  given
+}
+
+// Things wrong with a grid that no word list could fix.
+enum GridParseError {
+  DisconnectedGrid(uint),
+  IsolatedAnchor(char, Point),
+  AnchorOutsideBox(char, Point)
+}
+
+impl std::fmt::Show for GridParseError {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match *self {
+      DisconnectedGrid(n) => write!(fmt.buf, "grid splits into {} separate regions", n),
+      IsolatedAnchor(c, p) => write!(fmt.buf, "waypoint {} at {},{} has no free cell or other waypoint next to it", c.to_upper(), p.x, p.y),
+      AnchorOutsideBox(c, p) => write!(fmt.buf, "waypoint {} at {},{} is outside --within", c.to_upper(), p.x, p.y)
+    }
+  }
+}
+
+// Whether a word could ever be routed through (or end on) this tile.
+#[inline] fn routable<'a>(tile: &TileData<'a>) -> bool {
+  match *tile {
+    Fixed(_) | NoWords | OneWord(..) | TwoWords(..) | Through(..) => true,
+    Blocked => false
+  }
+}
+
+// Every problem found with the grid, not just the first.
+fn preflight<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions, diag: &mut Diagnostics) -> Result<(), Vec<GridParseError>> {
+  let mut errors = Vec::new();
+  let regions = count_components(grid, opts, routable);
+  if regions > 1 {
+    errors.push(DisconnectedGrid(regions));
+  } else {
+    for &p in articulation_points(grid, opts).iter() {
+      diag.report(Note, Cell(p), ~"the grid splits in two without this cell");
+    }
+  }
+  // a word has to be able to leave its first waypoint and arrive at its last,
+  // which it can do straight onto a waypoint next door: a two-letter word
+  // needs nothing else, and --through-waypoints can cross it
+  for y in range(0, grid.height) {
+    for x in range(0, grid.width) {
+      let p = Point { x: x, y: y };
+      match *grid.get_ref(p).unwrap() {
+        Fixed(c) => {
+          let free = grid.either_way(opts, p).iter().any(|&n| match *grid.get_ref(n).unwrap() {
+            NoWords | Fixed(_) => true,
+            OneWord(_, w) => w == PREFILLED,
+            _ => false
+          });
+          if !free {
+            errors.push(IsolatedAnchor(c, p));
+          }
+          match opts.within {
+            Some((min, max)) if !GridView { min: min, max: max }.contains(p) => errors.push(AnchorOutsideBox(c, p)),
+            _ => { }
+          }
+        }
+        _ => { }
+      }
+    }
+  }
+  if errors.is_empty() { Ok(()) }
+  else                 { Err(errors) }
+}
+
+// The cell farthest from `from` by moves through routable cells, and how
+// many moves away it is.
+fn farthest_from<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions, from: Point) -> (Point, uint) {
+  let mut dist: Grid<Option<uint>> = grid.map(|_| None);
+  let mut queue = RingBuf::new();
+  let mut far = (from, 0u);
+  dist.set(from, Some(0));
+  queue.push_back(from);
+  loop {
+    let p = match queue.pop_front() {
+      Some(p) => p,
+      None => return far
+    };
+    let d = dist.get_ref(p).unwrap().unwrap();
+    if d > far.val1() {
+      far = (p, d);
+    }
+    for &n in steps_from(opts, p).iter().filter(|&&n| grid.is_valid(n)) {
+      if routable(grid.get_ref(n).unwrap()) && dist.get_ref(n).unwrap().is_none() {
+        dist.set(n, Some(d + 1));
+        queue.push_back(n);
+      }
+    }
+  }
+}
+
+// How many moves it takes to get across the grid, the long way: a
+// breadth-first search from any cell, then another from the farthest cell
+// that found.  That's exact when the open cells form a tree, like a maze,
+// and otherwise can come up short, never long.  It says nothing about how
+// long a word can be, since a word can wind back and forth.
+fn grid_diameter<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions) -> uint {
+  let first = grid.tiles.iter().position(|t| routable(t));
+  match first {
+    Some(i) => {
+      let start = Point { x: i as int % grid.width, y: i as int / grid.width };
+      let (far, _) = farthest_from(grid, opts, start);
+      farthest_from(grid, opts, far).val1()
+    }
+    None => 0
+  }
+}
+
+// Words that can't fit between their waypoints at all, and words longer than
+// the grid is across, which only fit by doubling back.
+fn length_report<'a>(grid: &CrosswordGrid<'a>, words: &[~str], opts: &SolveOptions, diag: &mut Diagnostics) {
+  let room = grid.tiles.iter().filter(|t| match **t { NoWords => true, OneWord(_, w) => w == PREFILLED, _ => false }).count();
+  let across = grid_diameter(grid, opts);
+  for w in words.iter() {
+    let (cells, moves) = (w.char_len() - 2, w.char_len() - 1);
+    if cells > room {
+      diag.report(Warning, Nowhere, format!("\"{}\" needs {} cells between its waypoints, and the grid only has {} open", *w, cells, room));
+    } else if moves > across {
+      diag.report(Note, Nowhere, format!("\"{}\" is longer than the grid is across (about {} moves), so it will most likely double back", *w, across));
+    }
+  }
+}
+
+// The letters each pair of words could share a cell on, keyed with the words
+// in sorted order.  Only the letters between the waypoints count, since
+// those are the only cells a word has; a pair with none can never cross,
+// and has to find room side by side.
+fn possible_crossings(words: &[&str]) -> HashMap<(~str, ~str), Vec<char>> {
+  let inner = |w: &str| -> Vec<char> {
+    let letters: Vec<char> = w.chars().map(|c| ALPHABET.normalize(c)).collect();
+    let mut inner: Vec<char> = if letters.len() > 2 { Vec::from_slice(letters.slice(1, letters.len() - 1)) } else { Vec::new() };
+    inner.sort();
+    inner.dedup();
+    inner
+  };
+  let mut out = HashMap::new();
+  for (i, &a) in words.iter().enumerate() {
+    for &b in words.slice_from(i + 1).iter() {
+      let (of_a, of_b) = (inner(a), inner(b));
+      let shared: Vec<char> = of_a.move_iter().filter(|&c| of_b.iter().any(|&d| ALPHABET.same(c, d))).collect();
+      let key = if a <= b { (a.to_owned(), b.to_owned()) } else { (b.to_owned(), a.to_owned()) };
+      out.insert(key, shared);
+    }
+  }
+  out
+}
+
+// Lines up the letters on the grid against the letters the words need.  Each
+// word has to start and end on a waypoint carrying its first and last letter,
+// so an endpoint letter with no waypoint sinks the word outright; otherwise
+// the more words share a waypoint, the more crowded it gets around it.
+fn letter_report<'a>(grid: &CrosswordGrid<'a>, words: &[~str]) -> ~str {
+  let on_grid = letter_distribution(grid);
+  let mut in_words = TreeMap::new();
+  let mut as_endpoint = TreeMap::new();
+  for word in words.iter() {
+    for c in word.chars() {
+      count_letter(&mut in_words, c);
+    }
+    count_letter(&mut as_endpoint, word.char_at(0));
+    count_letter(&mut as_endpoint, word.char_at_reverse(word.len()));
+  }
+  let mut letters: Vec<char> = in_words.iter().map(|(&c, _)| c).collect();
+  for (&c, _) in on_grid.iter() {
+    if !letters.contains(&c) {
+      letters.push(c);
+    }
+  }
+  letters.sort();
+  let count = |counts: &TreeMap<char, uint>, c: char| counts.find(&c).map_or(0, |&n| n);
+  let mut out = StrBuf::new();
+  out.push_str("letter  grid  words  endpoints\n");
+  for &c in letters.iter() {
+    let ends = count(&as_endpoint, c);
+    let note = if ends > 0 && count(&on_grid, c) == 0 { "  no waypoint!" } else { "" };
+    out.push_str(format!("{:>6} {:>5} {:>6} {:>10}{}\n", c, count(&on_grid, c), count(&in_words, c), ends, note));
+  }
+  out.into_owned()
+}
+
+// What the path search saw at one cell.  Normal solves pass NoTrace, whose
+// methods inline away to nothing; `explain` records these into a tree.
+enum TraceEvent<'a> {
+  Entered(Point, uint, char),
+  Reached(Point, uint),
+  Died(Point, uint, DeadEnd<'a>)
+}
+
+enum DeadEnd<'a> {
+  OutOfBounds,
+  OutsideView,
+  OutsideBox,
+  AtCapacity,
+  NearAnchor(char),
+  Occupied(TileData<'a>),
+  AlreadyOnPath,
+  LetterMismatch(char, char),
+  TooFar(int, int),
+  WrongParity(int, int)
+}
+
+impl<'a> std::fmt::Show for DeadEnd<'a> {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match *self {
+      OutOfBounds => write!(fmt.buf, "out of bounds"),
+      OutsideView => write!(fmt.buf, "outside the region of interest"),
+      OutsideBox => write!(fmt.buf, "outside --within"),
+      AtCapacity => write!(fmt.buf, "cell already carries as many words as it can"),
+      NearAnchor(c) => write!(fmt.buf, "next to waypoint {}, which isn't this word's", c.to_upper()),
+      Occupied(Fixed(c)) => write!(fmt.buf, "occupied by waypoint {}", c.to_upper()),
+      Occupied(Through(c, w)) => write!(fmt.buf, "waypoint {}, which \"{}\" already passes through", c.to_upper(), w),
+      Occupied(Blocked) => write!(fmt.buf, "blocked"),
+      Occupied(TwoWords(_, a, b)) => write!(fmt.buf, "occupied by \"{}\" and \"{}\"", a, b),
+      Occupied(_) => write!(fmt.buf, "occupied"),
+      AlreadyOnPath => write!(fmt.buf, "already on this word's path"),
+      LetterMismatch(found, wanted) => write!(fmt.buf, "letter mismatch: has '{}', needs '{}'", found, wanted),
+      TooFar(steps, left) => write!(fmt.buf, "distance prune: needs {} steps with {} left", steps, left),
+      WrongParity(dist, left) => write!(fmt.buf, "parity prune: {} away with {} steps left", dist, left)
+    }
+  }
+}
+
+trait Tracer<'a> {
+  fn enabled(&self) -> bool;
+  fn trace(&mut self, event: TraceEvent<'a>);
+}
+
+struct NoTrace;
+
+impl<'a> Tracer<'a> for NoTrace {
+  #[inline(always)] fn enabled(&self) -> bool { false }
+  #[inline(always)] fn trace(&mut self, _: TraceEvent<'a>) { }
+}
+
+// Writes one indented line per event until `limit` nodes have been seen,
+// then just keeps counting.
+struct TreeTrace {
+  out: StrBuf,
+  limit: uint,
+  nodes: uint,
+  placements: uint
+}
+
+impl TreeTrace {
+  fn new(limit: uint) -> TreeTrace {
+    TreeTrace { out: StrBuf::new(), limit: limit, nodes: 0, placements: 0 }
+  }
+  fn truncated(&self) -> bool {
+    self.nodes > self.limit
+  }
+}
+
+impl<'a> Tracer<'a> for TreeTrace {
+  fn enabled(&self) -> bool {
+    self.nodes < self.limit
+  }
+  fn trace(&mut self, event: TraceEvent<'a>) {
+    match event {
+      Reached(..) => self.placements += 1,
+      _ => { }
+    }
+    self.nodes += 1;
+    if self.truncated() {
+      return;
+    }
+    let line = match event {
+      Entered(p, depth, c) => format!("{}({},{}) {}", "  ".repeat(depth), p.x, p.y, c),
+      Reached(p, depth) => format!("{}({},{}) reached destination", "  ".repeat(depth), p.x, p.y),
+      Died(p, depth, why) => format!("{}({},{}) x {}", "  ".repeat(depth), p.x, p.y, why)
+    };
+    self.out.push_str(line);
+    self.out.push_char('\n');
+  }
+}
+
+// The things that can stop a search before it's seen everything.
+#[deriving(Eq, Clone)]
+enum Limit {
+  Beam,
+  NodeBudget,
+  Cancelled,
+  Timeout,
+  WordTimeout,
+  RegionOfInterest,
+  DepthCap
+}
+
+// A rectangle of a grid, corners included.
+#[deriving(Clone)]
+struct GridView {
+  min: Point,
+  max: Point
+}
+
+impl GridView {
+  #[inline(always)] fn contains(&self, p: Point) -> bool {
+    p.x >= self.min.x && p.x <= self.max.x && p.y >= self.min.y && p.y <= self.max.y
+  }
+}
+
+// The box spanned by a word's endpoints, grown by `border` on every side and
+// clipped to the grid.  A word with s spare steps can stray at most s/2 cells
+// outside that box, so any border at least that big loses nothing.
+fn region_of_interest<'a>(grid: &CrosswordGrid<'a>, start: Point, end: Point, border: uint) -> GridView {
+  let border = border as int;
+  GridView {
+    min: Point { x: std::cmp::max(std::cmp::min(start.x, end.x) - border, 0),
+                 y: std::cmp::max(std::cmp::min(start.y, end.y) - border, 0) },
+    max: Point { x: std::cmp::min(std::cmp::max(start.x, end.x) + border, grid.width - 1),
+                 y: std::cmp::min(std::cmp::max(start.y, end.y) + border, grid.height - 1) }
+  }
+}
+
+// Per-search state threaded through allpaths: the options, whatever is
+// watching the traversal, and whether something has called it off.
+// Deadlines are in precise_time_ns terms.
+struct Search<'o, T> {
+  opts: &'o SolveOptions,
+  trace: T,
+  nodes: uint,
+  halted: Option<Limit>,
+  deadline: Option<u64>,
+  word_deadline: Option<u64>,
+  // the current word's region of interest, and whether it turned a cell away
+  view: Option<GridView>,
+  left_view: bool,
+  // how many words are already on the grids being searched
+  words_deep: uint
+}
+
+fn seconds_from_now(seconds: f64) -> u64 {
+  time::precise_time_ns() + (seconds * 1e9) as u64
+}
+
+impl<'o, T> Search<'o, T> {
+  fn new(opts: &'o SolveOptions, trace: T) -> Search<'o, T> {
+    let deadline = opts.timeout.map(seconds_from_now);
+    Search { opts: opts, trace: trace, nodes: 0, halted: None, deadline: deadline, word_deadline: None, view: None, left_view: false, words_deep: 0 }
+  }
+
+  fn cancelled(&self) -> bool {
+    match self.opts.cancel {
+      Some(ref flag) => flag.load(SeqCst),
+      None => false
+    }
+  }
+
+  // the global deadline outranks the word's, so a word timeout can never
+  // stretch the solve past --timeout
+  fn poll(&mut self) {
+    if self.cancelled() {
+      self.halted = Some(Cancelled);
+      return;
+    }
+    if self.deadline.is_none() && self.word_deadline.is_none() {
+      return;
+    }
+    let now = time::precise_time_ns();
+    match self.deadline {
+      Some(deadline) if now >= deadline => {
+        self.halted = Some(Timeout);
+        return;
+      }
+      _ => { }
+    }
+    match self.word_deadline {
+      Some(deadline) if now >= deadline => self.halted = Some(WordTimeout),
+      _ => { }
+    }
+  }
+}
+
+// A word's first and last letters are its two waypoints, which stay as they
+// are: only the letters between them go on the grid.  So an N-letter word
+// takes N-1 moves and fills N-2 cells, and a two-letter word needs its
+// waypoints right next to each other and fills nothing.  That's why a search
+// starts with allpaths2 on the first waypoint, which only steps off it, and
+// each allpaths call after that writes s's first letter onto `start`, with
+// s's last letter kept for `dest`: it's done when it steps onto `dest` with
+// just that letter left.  The step bound counts the same way, comparing the
+// moves to `dest` with the moves s has left, one fewer than its letters.
+// Words can have letters outside ASCII, so all of this counts chars, not
+// bytes.
+fn allpaths<'a, T: Tracer<'a>>(search: &mut Search<T>, grid: & CrosswordGrid<'a>, word: &'a str, start: Point, dest: Point, s: &'a str, accum: &mut Vec<CrosswordGrid<'a>>) {
+  if search.halted.is_some() {
+    return;
+  }
+  search.nodes += 1;
+  match search.opts.max_nodes {
+    Some(max) if search.nodes > max => {
+      search.halted = Some(NodeBudget);
+      return;
+    }
+    _ => { }
+  }
+  // reading the clock and the cancel flag every node would be wasted; this
+  // is often enough
+  if search.nodes % 1024 == 0 {
+    search.poll();
+    if search.halted.is_some() {
+      return;
+    }
+  }
+  let len = s.char_len() as int - 1;
+  let depth = word.char_len() - s.char_len();
+  match search.opts.max_depth {
+    Some(max) if search.words_deep + depth > max => {
+      search.halted = Some(DepthCap);
+      return;
+    }
+    _ => { }
+  }
+  if start == dest && len == 0 {
+    if paranoid(search.opts) {
+      let cells = grid.tiles.iter().filter(|t| has_word(*t, word)).count();
+      assert!(cells == word.char_len() - 2, "\"{}\" reached its last waypoint on {} cells, not {}", word, cells, word.char_len() - 2);
+    }
+    search.trace.trace(Reached(start, depth));
+    accum.push(grid.clone());
+    return;
+  }
+  match search.opts.bound {
+    Some(ref bound) => {
+      let steps = bound.min_steps(start, dest);
+      if steps > len {
+        search.trace.trace(Died(start, depth, TooFar(steps, len)));
+        return;
+      }
+      if !bound.parity_ok(start, dest, len) {
+        search.trace.trace(Died(start, depth, WrongParity(start.dist(dest), len)));
+        return;
+      }
+    }
+    None => { }
+  }
+  match grid.get_ref(start) {
+    None => search.trace.trace(Died(start, depth, OutOfBounds)),
+    // skip if wrong character, or the character is part of this word
+    Some(&OneWord(_, w)) if w == word => {
+      search.trace.trace(Died(start, depth, AlreadyOnPath))
+    }
+    Some(&OneWord(c, w)) if w == PREFILLED && ALPHABET.same(c, s.char_at(0)) => {
+      search.trace.trace(Entered(start, depth, c));
+      let newgrid = grid.replace(start, OneWord(c, word));
+      allpaths2(search, &newgrid, word, start, dest, s, accum)
+    }
+    Some(&OneWord(_, _)) if search.opts.capacity.as_ref().map_or(false, |cap| *cap.get_ref(start).unwrap() < 2) => {
+      search.trace.trace(Died(start, depth, AtCapacity))
+    }
+    Some(&OneWord(c, w)) if ALPHABET.same(c, s.char_at(0)) => {
+      let c = ALPHABET.crossed(c, s.char_at(0));
+      search.trace.trace(Entered(start, depth, c));
+      let newtile: TileData<'a> = TwoWords(c, w, word);
+      let newgrid = grid.replace(start, newtile);
+      allpaths2(search, &newgrid, word, start, dest, s, accum)
+    }
+    Some(&OneWord(c, _)) => {
+      search.trace.trace(Died(start, depth, LetterMismatch(c, s.char_at(0))))
+    }
+    Some(&NoWords) => {
+      search.trace.trace(Entered(start, depth, s.char_at(0)));
+      let newtile = OneWord(s.char_at(0), word);
+      let newgrid = grid.replace(start, newtile);
+      allpaths2(search, &newgrid, word, start, dest, s, accum)
+    }
+    // the waypoint is one use of the cell and the word passing through it a
+    // second, so it takes only one, and none with a capacity of 1
+    Some(&Fixed(c)) if search.opts.through_waypoints && passable_waypoint(word, c) && ALPHABET.same(c, s.char_at(0)) => {
+      if search.opts.capacity.as_ref().map_or(false, |cap| *cap.get_ref(start).unwrap() < 2) {
+        search.trace.trace(Died(start, depth, AtCapacity));
+        return;
+      }
+      search.trace.trace(Entered(start, depth, c));
+      let newgrid = grid.replace(start, Through(c, word));
+      allpaths2(search, &newgrid, word, start, dest, s, accum)
+    }
+    Some(&tile) => search.trace.trace(Died(start, depth, Occupied(tile)))
+  }
+}
+
+// Whether allpaths would go on from `p` with `s` left: the step bound lets
+// it, and the cell takes s's first letter.
+fn could_enter<'a>(opts: &SolveOptions, grid: &CrosswordGrid<'a>, word: &str, p: Point, dest: Point, s: &str) -> bool {
+  let len = s.char_len() as int - 1;
+  match opts.bound {
+    Some(ref bound) if bound.min_steps(p, dest) > len || !bound.parity_ok(p, dest, len) => return false,
+    _ => { }
+  }
+  let roomy = opts.capacity.as_ref().map_or(true, |cap| *cap.get_ref(p).unwrap() >= 2);
+  match *grid.get_ref(p).unwrap() {
+    NoWords => true,
+    OneWord(_, w) if w == word => false,
+    OneWord(c, w) if w == PREFILLED => ALPHABET.same(c, s.char_at(0)),
+    OneWord(c, _) => roomy && ALPHABET.same(c, s.char_at(0)),
+    Fixed(c) => opts.through_waypoints && roomy && passable_waypoint(word, c) && ALPHABET.same(c, s.char_at(0)),
+    _ => false
+  }
+}
+
+fn allpaths2<'a, T: Tracer<'a>>(search: &mut Search<T>, grid: & CrosswordGrid<'a>, word: &'a str, start: Point, dest: Point, s: &'a str, accum: &mut Vec<CrosswordGrid<'a>>) {
+  let mystring: & str = s.slice_from(s.char_range_at(0).next);
+  let depth = word.char_len() - mystring.char_len();
+
+  let opts = search.opts;
+  // on the first step, a symmetric grid only needs searching on one side of
+  // its axis; the other side's paths are the reflections of those
+  let mirror = if opts.symmetry && s.char_len() == word.char_len() { symmetry_of(opts, grid, start, dest) } else { None };
+  let mut steps = steps_from(opts, start);
+  if opts.toward_dest {
+    steps.sort_by(|a, b| a.dist(dest).cmp(&b.dist(dest)));
+  }
+  for &p in steps.iter() {
+    let side = mirror.map_or(0, |m| m.side(grid, p));
+    if side < 0 {
+      continue;
+    }
+    let before = accum.len();
+    if !grid.is_valid(p) {
+      if search.trace.enabled() {
+        search.trace.trace(Died(p, depth, OutOfBounds));
+      }
+    } else if opts.within.map_or(false, |(min, max)| !GridView { min: min, max: max }.contains(p)) {
+      search.trace.trace(Died(p, depth, OutsideBox));
+    } else if search.view.map_or(false, |view| !view.contains(p)) {
+      // only a cell the search would have gone on from counts as lost
+      if could_enter(opts, grid, word, p, dest, mystring) {
+        search.left_view = true;
+      }
+      search.trace.trace(Died(p, depth, OutsideView));
+    } else if opts.isolate_anchors && p != dest && foreign_anchor(grid, word, p).is_some() {
+      search.trace.trace(Died(p, depth, NearAnchor(foreign_anchor(grid, word, p).unwrap())));
+    } else {
+      allpaths(search, grid, word, p, dest, mystring, accum);
+    }
+    if side > 0 {
+      for i in range(before, accum.len()) {
+        let reflected = mirror.unwrap().apply(accum.get(i));
+        accum.push(reflected);
+      }
+    }
+  }
+}
+
+// a word never passes through either of its own waypoints
+#[inline] fn passable_waypoint(word: &str, c: char) -> bool {
+  !ALPHABET.same(c, word.char_at(0)) && !ALPHABET.same(c, word.char_at_reverse(word.len()))
+}
+
+// A waypoint right next to `p` (not diagonally) that isn't one of `word`'s own.
+fn foreign_anchor<'a>(grid: &CrosswordGrid<'a>, word: &str, p: Point) -> Option<char> {
+  let (first, last) = (word.char_at(0), word.char_at_reverse(word.len()));
+  for &n in grid.neighbors(p, FOUR_WAYS.as_slice()).iter() {
+    match *grid.get_ref(n).unwrap() {
+      Fixed(c) | Through(c, _) if !ALPHABET.same(c, first) && !ALPHABET.same(c, last) => return Some(c),
+      _ => { }
+    }
+  }
+  None
+}
+
+// A reflection of the grid onto itself.
+#[deriving(Clone, Eq)]
+enum Mirror {
+  // x becomes width-1-x
+  LeftRight,
+  // y becomes height-1-y
+  TopBottom
+}
+
+impl Mirror {
+  #[allow(dead_code)]
+  fn point<T>(&self, grid: &Grid<T>, p: Point) -> Point {
+    match *self {
+      LeftRight => Point { x: grid.width - 1 - p.x, y: p.y },
+      TopBottom => Point { x: p.x, y: grid.height - 1 - p.y }
+    }
+  }
+  fn offset(&self, (x, y): (int, int)) -> (int, int) {
+    match *self {
+      LeftRight => (-x, y),
+      TopBottom => (x, -y)
+    }
+  }
+  // -1 or 1 for either side of the axis, 0 on it
+  fn side<T>(&self, grid: &Grid<T>, p: Point) -> int {
+    let twice = match *self {
+      LeftRight => 2 * p.x - (grid.width - 1),
+      TopBottom => 2 * p.y - (grid.height - 1)
+    };
+    if twice < 0 { -1 } else if twice > 0 { 1 } else { 0 }
+  }
+  fn apply<T: Clone>(&self, grid: &Grid<T>) -> Grid<T> {
+    match *self {
+      LeftRight => grid.flip_h(),
+      TopBottom => grid.flip_v()
+    }
+  }
+}
+
+// An axis the search for a word can't tell the two sides of: the grid, its
+// capacities and the moves all look the same reflected, and both endpoints
+// sit on the axis.
+fn symmetry_of<'a>(opts: &SolveOptions, grid: &CrosswordGrid<'a>, start: Point, end: Point) -> Option<Mirror> {
+  // the offset rows of a hex grid don't reflect onto themselves
+  if opts.topology == Hex {
+    return None;
+  }
+  for &mirror in [LeftRight, TopBottom].iter() {
+    if mirror.side(grid, start) != 0 || mirror.side(grid, end) != 0 {
+      continue;
+    }
+    if !opts.offsets.iter().all(|&o| opts.offsets.contains(&mirror.offset(o))) {
+      continue;
+    }
+    if mirror.apply(grid) != *grid || opts.capacity.as_ref().map_or(false, |cap| mirror.apply(cap) != *cap) {
+      continue;
+    }
+    // nor does a --within box off the axis, so the reflections would land
+    // outside it
+    let boxed = opts.within.map_or(true, |(min, max)| match mirror {
+      LeftRight => min.x + max.x == grid.width - 1,
+      TopBottom => min.y + max.y == grid.height - 1
+    });
+    if !boxed {
+      continue;
+    }
+    return Some(mirror);
+  }
+  None
+}
+
+// Whether a solve saw everything there was to see.  This only records limits
+// that actually cut something off (a beam wider than the accumulator ever got
+// leaves the search exhaustive), and only the first one to do so.  A word
+// that fit none of the grids it was given leaves no complete set at all, so
+// Exhaustive always means every word was placed.
+#[deriving(Eq, Clone)]
+enum Completeness<'a> {
+  Exhaustive,
+  TruncatedBy(Limit, &'a str),
+  Stuck(&'a str)
+}
+
+impl<'a> std::fmt::Show for Completeness<'a> {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match *self {
+      Exhaustive => write!(fmt.buf, "exhaustive"),
+      TruncatedBy(Beam, word) => write!(fmt.buf, "search truncated by beam at word '{}'", word),
+      TruncatedBy(NodeBudget, word) => write!(fmt.buf, "search truncated by node budget at word '{}'", word),
+      TruncatedBy(Cancelled, word) => write!(fmt.buf, "search cancelled at word '{}'", word),
+      TruncatedBy(Timeout, word) => write!(fmt.buf, "search timed out at word '{}'", word),
+      TruncatedBy(WordTimeout, word) => write!(fmt.buf, "search truncated by word timeout at word '{}'", word),
+      TruncatedBy(RegionOfInterest, word) => write!(fmt.buf, "search truncated by region of interest at word '{}'", word),
+      TruncatedBy(DepthCap, word) => write!(fmt.buf, "search went too deep at word '{}'", word),
+      Stuck(word) => write!(fmt.buf, "stuck on word '{}'", word)
+    }
+  }
+}
+
+// What a solve turned up.  If some word couldn't be placed, or the search was
+// stopped outright, it ends there and the solutions are the grids it had
+// before trying that word.  Words whose own timeout ran out before a single
+// placement turned up are skipped and listed in timed_out instead.
+struct SolveResult<'a> {
+  solutions: Vec<CrosswordGrid<'a>>,
+  stuck_on: Option<&'a str>,
+  completeness: Completeness<'a>,
+  timed_out: Vec<&'a str>,
+  // what each word did to the grid count, in the order they were searched
+  word_stats: Vec<WordStats<'a>>,
+  // for each solution, the optional words it placed and the ones it left out
+  optional_words: Vec<(Vec<&'a str>, Vec<&'a str>)>,
+  // for each solution, the spelling it used of each variant group
+  spellings: Vec<Vec<&'a str>>,
+  // spellings that fit none of the grids they were tried on, and why
+  spelling_misses: Vec<(&'a str, FailureExplanation)>
+}
+
+// One word's step of a solve: how many grids went in, how many of those it
+// found any placement on, and how many grids came out.
+struct WordStats<'a> {
+  word: &'a str,
+  grids_in: uint,
+  grids_placed: uint,
+  grids_out: uint,
+  // cells the search visited placing it
+  nodes: uint
+}
+
+impl<'a> WordStats<'a> {
+  // grids out per grid in; under 1 means the word cut the count down
+  fn factor(&self) -> f64 {
+    self.grids_out as f64 / std::cmp::max(self.grids_in, 1) as f64
+  }
+}
+
+impl<'a> SolveResult<'a> {
+  fn start(grid: &CrosswordGrid<'a>) -> SolveResult<'a> {
+    SolveResult { solutions: vec!(grid.clone()), stuck_on: None, completeness: Exhaustive, timed_out: Vec::new(), word_stats: Vec::new(), optional_words: Vec::new(), spellings: Vec::new(), spelling_misses: Vec::new() }
+  }
+
+  fn truncate(&mut self, limit: Limit, word: &'a str) {
+    if self.completeness == Exhaustive {
+      self.completeness = TruncatedBy(limit, word);
+    }
+  }
+
+  // `word` fit none of the grids; what's left are the grids from before it
+  fn stick(&mut self, word: &'a str) {
+    self.stuck_on = Some(word);
+    if self.completeness == Exhaustive {
+      self.completeness = Stuck(word);
+    }
+  }
+
+  // a search cut off by --max-depth is a failure, not just an incomplete one
+  fn error(&self, opts: &SolveOptions) -> Option<SolveError> {
+    match self.completeness {
+      TruncatedBy(DepthCap, word) => Some(DepthExceeded(word.to_owned(), opts.max_depth.unwrap())),
+      _ => None
+    }
+  }
+
+  // "12 solutions" when that's all there are, "at least 12" otherwise, and
+  // none at all when a word couldn't go on the grids it was given
+  fn count_str(&self) -> ~str {
+    let mut out = match (self.stuck_on, &self.completeness) {
+      (Some(word), &Stuck(_)) => format!("no solutions; stuck on '{}' after {} partial grids", word, self.solutions.len()),
+      (Some(word), limit) => format!("no solutions; stuck on '{}' after {} partial grids; {}", word, self.solutions.len(), *limit),
+      (None, &Exhaustive) => format!("{} solutions, exhaustive", self.solutions.len()),
+      (None, limit) => format!("at least {} solutions; {}", self.solutions.len(), *limit)
+    };
+    if !self.timed_out.is_empty() {
+      let words: Vec<~str> = self.timed_out.iter().map(|w| w.to_owned()).collect();
+      out = out + format!("; timed out before placing {}", words.connect(", "));
+    }
+    out
+  }
+}
+
+// Fails if `grid` isn't `reference` with `word` correctly added somewhere:
+// waypoints and walls moved, or the cells carrying the word not a single
+// unbroken path spelling it out.  The search never does either; this is for
+// catching it if it ever starts to.
+fn check_placement<'a>(grid: &CrosswordGrid<'a>, reference: &CrosswordGrid<'a>, word: &'a str, opts: &SolveOptions) {
+  for (i, (tile, was)) in grid.tiles.iter().zip(reference.tiles.iter()).enumerate() {
+    match (*was, *tile) {
+      (Fixed(c), Through(d, w)) if opts.through_waypoints && c == d && w == word => { }
+      (Fixed(_), _) | (Blocked, _) if tile != was => {
+        fail!("placing \"{}\" changed cell {},{} from {:?} to {:?}", word, i as int % grid.width, i as int / grid.width, *was, *tile)
+      }
+      _ => { }
+    }
+  }
+  let cells = grid.tiles.iter().filter(|t| has_word(*t, word)).count();
+  if cells != word.char_len() - 2 {
+    fail!("\"{}\" is on {} cells, not {}", word, cells, word.char_len() - 2);
+  }
+  if word_path(grid, word, opts).len() != cells {
+    fail!("\"{}\"'s cells don't make a path spelling it", word);
+  }
+}
+
+// `grid` with `word` written along exactly these interior cells, if they're
+// all free to take it.
+fn place_along<'a>(opts: &SolveOptions, grid: &CrosswordGrid<'a>, word: &'a str, path: &[Point]) -> Option<CrosswordGrid<'a>> {
+  let mut out = grid.clone();
+  for (&p, c) in path.iter().zip(word.chars().skip(1)) {
+    let room = opts.capacity.as_ref().map_or(true, |cap| *cap.get_ref(p).unwrap() >= 2);
+    let tile = match *grid.get_ref(p).unwrap() {
+      NoWords => OneWord(c, word),
+      OneWord(l, w) if w == PREFILLED && ALPHABET.same(l, c) => OneWord(l, word),
+      OneWord(l, w) if w != word && room && ALPHABET.same(l, c) => TwoWords(ALPHABET.crossed(l, c), w, word),
+      Fixed(l) if opts.through_waypoints && room && passable_waypoint(word, l) && ALPHABET.same(l, c) => Through(l, word),
+      _ => return None
+    };
+    out.set(p, tile);
+  }
+  Some(out)
+}
+
+// "word=x,y;x,y;..." for --fix-path: the cells between the word's waypoints,
+// which can be left off either end or given too
+fn parse_fixed_path(spec: &str) -> Result<(~str, Vec<Point>), ~str> {
+  let eq = match spec.find('=') {
+    Some(i) => i,
+    None => return Err(format!("\"{}\" should look like WORD=x,y;x,y;...", spec))
+  };
+  let word = ALPHABET.normalize_str(spec.slice_to(eq).trim());
+  let mut path = Vec::new();
+  for cell in spec.slice_from(eq + 1).split(';').filter(|c| !c.trim().is_empty()) {
+    let xy: Vec<Option<int>> = cell.split(',').map(|n| from_str::<int>(n.trim())).collect();
+    match xy.as_slice() {
+      [Some(x), Some(y)] => path.push(Point { x: x, y: y }),
+      _ => return Err(format!("bad cell \"{}\": expected x,y", cell))
+    }
+  }
+  Ok((word, path))
+}
+
+// Checks a fixed path against the word and its waypoints, dropping the
+// waypoints if they were given.
+fn check_fixed_path(opts: &SolveOptions, word: &str, start: Point, end: Point, path: &[Point]) -> Result<Vec<Point>, ~str> {
+  let mut cells = Vec::from_slice(path);
+  if cells.len() > 0 && *cells.get(0) == start { cells.remove(0); }
+  if cells.len() > 0 && *cells.last().unwrap() == end { cells.pop(); }
+  if cells.len() != word.char_len() - 2 {
+    return Err(format!("\"{}\" needs {} cells between its waypoints, not {}", word, word.char_len() - 2, cells.len()));
+  }
+  let mut full = vec!(start);
+  full.push_all(cells.as_slice());
+  full.push(end);
+  for pair in full.as_slice().windows(2) {
+    if !steps_from(opts, pair[0]).contains(&pair[1]) {
+      return Err(format!("\"{}\" can't step from {},{} to {},{}", word, pair[0].x, pair[0].y, pair[1].x, pair[1].y));
+    }
+  }
+  Ok(cells)
+}
+
+// On in debug builds (anything not built with --cfg ndebug), or with --paranoid.
+#[inline(always)] fn paranoid(opts: &SolveOptions) -> bool {
+  opts.paranoid || cfg!(not(ndebug))
+}
+
+fn add_word<'a, T: Tracer<'a>>(search: &mut Search<T>, diag: &mut Diagnostics, progress: SolveResult<'a>, wordpt: &[(&'a str, &(Point, Point))])-> SolveResult<'a> {
+  let next = wordpt.head();
+  if next.is_none() {
+    return progress;
+  }
+  let mut progress = progress;
+  let (word, &(start, end)) = *next.unwrap();
+  search.poll();
+  search.word_deadline = search.opts.word_timeout.map(seconds_from_now);
+  search.view = search.opts.roi_border.map(|border| region_of_interest(progress.solutions.get(0), start, end, border));
+  search.left_view = false;
+  search.words_deep = progress.word_stats.len();
+  println!("searching \"{}\" on {} grids", word, progress.solutions.len());
+  let nodes_before = search.nodes;
+  // A later spelling only goes on the grids no earlier one fit, and a grid
+  // none has fit yet waits for the next.  With --consistent-variants, once
+  // any grid has an earlier spelling the rest are out.
+  let (earlier, later) = match variant_group(search.opts, word) {
+    Some((group, k)) => (group.slice_to(k), k + 1 < group.len()),
+    None => (&[], false)
+  };
+  let spelled = |grid: &CrosswordGrid| earlier.iter().any(|s| contains_word_cell(grid, *s));
+  let settled = search.opts.consistent_variants && progress.solutions.iter().any(|g| spelled(g));
+  let mut out: Vec<CrosswordGrid> = Vec::new();
+  let mut placed = 0;
+  for i in progress.solutions.iter() {
+    if search.halted.is_some() {
+      break;
+    }
+    if spelled(i) {
+      out.push(i.clone());
+      continue;
+    }
+    if settled {
+      continue;
+    }
+    let before = out.len();
+    match search.opts.fixed_paths.iter().find(|&&(ref w, _)| w.as_slice() == word) {
+      Some(&(_, ref path)) => match place_along(search.opts, i, word, path.as_slice()) {
+        Some(grid) => out.push(grid),
+        None => { }
+      },
+      None => allpaths2(search, i, word, start, end, word, &mut out)
+    }
+    if paranoid(search.opts) {
+      for grid in out.slice_from(before).iter() {
+        check_placement(grid, i, word, search.opts);
+      }
+    }
+    if out.len() > before {
+      placed += 1;
+    } else if later {
+      out.push(i.clone());
+    }
+  }
+  if later && placed == 0 && !progress.solutions.is_empty() {
+    let why = explain_failure(search.opts, progress.solutions.get(0), word, start, end);
+    diag.report(Note, Nowhere, format!("\"{}\" doesn't fit: {}; trying the next spelling", word, why));
+    progress.spelling_misses.push((word, why));
+  }
+  progress.word_stats.push(WordStats { word: word, grids_in: progress.solutions.len(), grids_placed: placed, grids_out: out.len(), nodes: search.nodes - nodes_before });
+  search.word_deadline = None;
+  search.view = None;
+  if search.left_view {
+    progress.truncate(RegionOfInterest, word);
+  }
+  match search.halted {
+    Some(WordTimeout) => {
+      search.halted = None;
+      progress.truncate(WordTimeout, word);
+      if out.is_empty() {
+        diag.report(Warning, Nowhere, format!("timed out before finding any paths for \"{}\"; skipping it", word));
+        progress.timed_out.push(word);
+        return add_word(search, diag, progress, wordpt.slice_from(1));
+      }
+      diag.report(Warning, Nowhere, format!("timed out searching \"{}\"; keeping the {} placements found so far", word, out.len()));
+    }
+    Some(limit) => {
+      let message = match limit {
+        Cancelled => format!("cancelled while searching \"{}\"", word),
+        Timeout => format!("timed out while searching \"{}\"", word),
+        DepthCap => format!("went past --max-depth while searching \"{}\"", word),
+        _ => format!("ran out of nodes while searching \"{}\"", word)
+      };
+      diag.report(Error, Nowhere, message);
+      progress.truncate(limit, word);
+      progress.stick(word);
+      return progress;
+    }
+    None => { }
+  }
+  // an optional word (with cells to put it in) can also be left out of every
+  // grid it was given
+  if is_optional(search.opts, word) && word.char_len() > 2 {
+    out.push_all(progress.solutions.as_slice());
+  }
+  if out.is_empty() && !earlier.is_empty() {
+    let why = explain_failure(search.opts, progress.solutions.get(0), word, start, end);
+    let mut tried: Vec<~str> = progress.spelling_misses.iter().filter(|&&(w, _)| earlier.iter().any(|s| s.as_slice() == w)).map(|&(w, ref why)| format!("\"{}\": {}", w, *why)).collect();
+    tried.push(format!("\"{}\": {}", word, why));
+    diag.report(Error, Nowhere, format!("no spelling of \"{}\" fits; {}", earlier[0], tried.connect("; ")));
+    progress.stick(word);
+    return progress;
+  }
+  if out.is_empty() {
+    let why = explain_failure(search.opts, progress.solutions.get(0), word, start, end);
+    diag.report(Error, Nowhere, format!("could not produce any paths to fit \"{}\": {}", word, why));
+    progress.stick(word);
+    return progress;
+  }
+  match search.opts.beam {
+    Some(width) if out.len() > width => {
+      out.truncate(width);
+      progress.truncate(Beam, word);
+    }
+    _ => { }
+  }
+  if search.opts.verbose && !out.is_empty() && wordpt.len() > 1 {
+    println!("  next: {}", slack_summary(search.opts, out.get(0), wordpt.slice_from(1)));
+  }
+  progress.solutions = out;
+  add_word(search, diag, progress, wordpt.slice_from(1))
+}
+
+// The fewest moves from start to end through cells a word could still take,
+// on or off a crossing: breadth first, so the first time end comes up is the
+// shortest way there.
+fn open_distance<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions, start: Point, end: Point) -> Option<uint> {
+  let mut dist: Grid<Option<uint>> = grid.map(|_| None);
+  let mut queue = RingBuf::new();
+  dist.set(start, Some(0));
+  queue.push_back(start);
+  loop {
+    let p = match queue.pop_front() {
+      Some(p) => p,
+      None => return None
+    };
+    let d = dist.get_ref(p).unwrap().unwrap();
+    for &n in steps_from(opts, p).iter().filter(|&&n| grid.is_valid(n)) {
+      if n == end {
+        return Some(d + 1);
+      }
+      let open = match *grid.get_ref(n).unwrap() { NoWords | OneWord(..) => true, _ => false };
+      if open && dist.get_ref(n).unwrap().is_none() {
+        dist.set(n, Some(d + 1));
+        queue.push_back(n);
+      }
+    }
+  }
+}
+
+// For -v: how much room each of the next few words has on `grid`, as the
+// moves it has to make beyond the fewest that would get it from one waypoint
+// to the other.  A word with none left has exactly one length of route and
+// is the likeliest to get stuck.
+fn slack_summary<'a>(opts: &SolveOptions, grid: &CrosswordGrid<'a>, words: &[(&'a str, &(Point, Point))]) -> ~str {
+  let shown = 5;
+  let mut parts: Vec<~str> = words.iter().take(shown).map(|&(word, &(start, end))| {
+    let moves = word.char_len() - 1;
+    match open_distance(grid, opts, start, end) {
+      Some(d) if d > moves => format!("{}(too far!)", word),
+      Some(d) if d == moves => format!("{}(slack 0!)", word),
+      Some(d) => format!("{}(slack {})", word, moves - d),
+      None => format!("{}(cut off!)", word)
+    }
+  }).collect();
+  if words.len() > shown {
+    parts.push(format!("and {} more", words.len() - shown));
+  }
+  parts.connect(", ")
+}
+
+// Which words did the most to narrow the search, as an aligned table: each
+// word's grids out per grid in, out of the grids it was fed how many it fit
+// on at all, its placements per grid it fit on, and how many different
+// routes it takes across the final results.  Most constraining first.
+fn constraint_table<'a>(result: &SolveResult<'a>, opts: &SolveOptions) -> ~str {
+  let mut stats: Vec<&WordStats<'a>> = result.word_stats.iter().collect();
+  stats.sort_by(|a, b| {
+    let (fa, fb) = (a.factor(), b.factor());
+    if fa < fb { Less } else if fa > fb { Greater } else { Equal }
+  });
+  let width = std::cmp::max(stats.iter().map(|s| s.word.char_len()).max().unwrap_or(0), 4);
+  let mut out = StrBuf::new();
+  out.push_str(format!("{:<w$}  {:>10}  {:>7}  {:>11}  {:>6}\n", "word", "out per in", "fit on", "per fitting", "routes", w = width));
+  for s in stats.iter() {
+    let mut routes = HashSet::new();
+    for grid in result.solutions.iter() {
+      routes.insert(word_path(grid, s.word, opts));
+    }
+    let fit = s.grids_placed as f64 * 100.0 / std::cmp::max(s.grids_in, 1) as f64;
+    let per_fitting = s.grids_out as f64 / std::cmp::max(s.grids_placed, 1) as f64;
+    out.push_str(format!("{:<w$}  {:>10.3f}  {:>6.1f}%  {:>11.2f}  {:>6}\n", s.word, s.factor(), fit, per_fitting, routes.len(), w = width));
+  }
+  out.into_owned()
+}
+
+// Which optional words the first result uses, and how often each is used
+// across them all.
+fn optional_report<'a>(result: &SolveResult<'a>) -> ~str {
+  let &(ref placed, ref omitted) = result.optional_words.get(0);
+  let mut out = StrBuf::new();
+  out.push_str(format!("first result places {} optional words and leaves out {}\n", placed.len(), omitted.len()));
+  if !omitted.is_empty() {
+    out.push_str(format!("left out: {}\n", omitted.connect(", ")));
+  }
+  let mut words: Vec<&'a str> = placed.clone();
+  words.push_all(omitted.as_slice());
+  words.sort();
+  for &w in words.iter() {
+    let uses = result.optional_words.iter().filter(|&&(ref p, _)| p.contains(&w)).count();
+    out.push_str(format!("\"{}\" is in {} of {} results\n", w, uses, result.solutions.len()));
+  }
+  out.into_owned()
+}
+
+// Once every word's in, a pre-filled cell no word went through means the
+// solution doesn't match the drawing.
+fn drop_unclaimed<'a>(result: &mut SolveResult<'a>, diag: &mut Diagnostics) {
+  if result.stuck_on.is_some() {
+    return;
+  }
+  let before = result.solutions.len();
+  result.solutions.retain(|grid| !grid.tiles.iter().any(|t| match *t { OneWord(_, w) => w == PREFILLED, _ => false }));
+  if result.solutions.len() < before {
+    diag.report(Note, Nowhere, format!("dropped {} of {} results that left a pre-filled cell unused", before - result.solutions.len(), before));
+  }
+}
+
+fn solve<'a>(opts: &SolveOptions, diag: &mut Diagnostics, grid: &CrosswordGrid<'a>, wordpts: &[(&'a str, &(Point, Point))]) -> SolveResult<'a> {
+  // a word's spellings are searched one right after another, in their order
+  let mut grouped: Vec<(&'a str, &(Point, Point))> = Vec::new();
+  for &(w, pts) in wordpts.iter() {
+    if grouped.iter().any(|&(g, _)| g == w) {
+      continue;
+    }
+    match variant_group(opts, w) {
+      Some((group, _)) => for s in group.iter() {
+        match wordpts.iter().find(|&&(v, _)| v == s.as_slice()) {
+          Some(&found) => grouped.push(found),
+          None => { }
+        }
+      },
+      None => grouped.push((w, pts))
+    }
+  }
+  let wordpts = grouped.as_slice();
+  let mut search = Search::new(opts, NoTrace);
+  let mut result = add_groups(&mut search, diag, grid, wordpts);
+  drop_unclaimed(&mut result, diag);
+  result.solutions = order_solutions(opts.ordering, opts.tie_break.as_slice(), result.solutions);
+  let words: Vec<&'a str> = wordpts.iter().map(|&(w, _)| w).collect();
+  result.solutions = dedup_solutions(opts, words.as_slice(), result.solutions);
+  if opts.prefer_straight {
+    result.solutions = straightest_first(result.solutions, wordpts, opts);
+  }
+  let optional: Vec<&'a str> = wordpts.iter().map(|&(w, _)| w).filter(|w| is_optional(opts, *w)).collect();
+  for grid in result.solutions.iter() {
+    let mut placed = Vec::new();
+    let mut omitted = Vec::new();
+    for &w in optional.iter() {
+      // a two-letter word has no cells, and always fits
+      if w.char_len() <= 2 || contains_word_cell(grid, w) { placed.push(w) } else { omitted.push(w) }
+    }
+    result.optional_words.push((placed, omitted));
+    let chosen: Vec<&'a str> = opts.variants.iter().filter_map(|group| {
+      wordpts.iter().map(|&(w, _)| w).find(|&w| group.iter().any(|s| s.as_slice() == w) && (w.char_len() <= 2 || contains_word_cell(grid, w)))
+    }).collect();
+    result.spellings.push(chosen);
+  }
+  result
+}
+
+// Moves from `from` to every cell a word could still take, going either way
+// along each move, since a word's route can be walked from both ends.
+fn open_distances<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions, from: Point) -> Grid<Option<uint>> {
+  let mut dist: Grid<Option<uint>> = grid.map(|_| None);
+  let mut queue = RingBuf::new();
+  dist.set(from, Some(0));
+  queue.push_back(from);
+  loop {
+    let p = match queue.pop_front() {
+      Some(p) => p,
+      None => return dist
+    };
+    let d = dist.get_ref(p).unwrap().unwrap();
+    for &n in grid.either_way(opts, p).iter() {
+      let open = match *grid.get_ref(n).unwrap() { NoWords | OneWord(..) => true, _ => false };
+      if open && dist.get_ref(n).unwrap().is_none() {
+        dist.set(n, Some(d + 1));
+        queue.push_back(n);
+      }
+    }
+  }
+}
+
+// The cells a word could possibly use: those it can get to from its first
+// waypoint and still make it to its last in the letters it has.
+fn reachable_cells<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions, word: &str, start: Point, end: Point) -> Grid<bool> {
+  let moves = word.char_len() - 1;
+  let (from, to) = (open_distances(grid, opts, start), open_distances(grid, opts, end));
+  let mut i = 0;
+  from.map(|d| {
+    let near = match (*d, *to.tiles.get(i)) {
+      (Some(a), Some(b)) => a > 0 && b > 0 && a + b <= moves,
+      _ => false
+    };
+    i += 1;
+    near
+  })
+}
+
+// The words split into groups that can't get in each other's way, as
+// indexes into `wordpts`: two words go in the same group when there's a cell
+// both could reach, and so on through every word either meets.  Options
+// that make words reach further than their own cells, or that cut the search
+// short in ways that depend on all the words together, keep them in one.
+fn independent_groups<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions, wordpts: &[(&'a str, &(Point, Point))]) -> Vec<Vec<uint>> {
+  let together = opts.isolate_anchors || opts.through_waypoints || opts.symmetry || opts.beam.is_some() || opts.roi_border.is_some() || opts.max_depth.is_some() || !opts.fixed_paths.is_empty() || !opts.optional.is_empty() || !opts.variants.is_empty();
+  if together || wordpts.len() < 2 {
+    return vec!(range(0, wordpts.len()).collect());
+  }
+  let reach: Vec<Grid<bool>> = wordpts.iter().map(|&(w, &(start, end))| reachable_cells(grid, opts, w, start, end)).collect();
+  let mut group: Vec<uint> = range(0, wordpts.len()).collect();
+  for i in range(0, wordpts.len()) {
+    for j in range(i + 1, wordpts.len()) {
+      let meet = reach.get(i).tiles.iter().zip(reach.get(j).tiles.iter()).any(|(&a, &b)| a && b);
+      let (gi, gj) = (*group.get(i), *group.get(j));
+      if meet && gi != gj {
+        for g in group.mut_iter() {
+          if *g == gj { *g = gi; }
+        }
+      }
+    }
+  }
+  let mut groups: Vec<Vec<uint>> = Vec::new();
+  let mut labels: Vec<uint> = Vec::new();
+  for (i, &g) in group.iter().enumerate() {
+    match labels.iter().position(|&l| l == g) {
+      Some(k) => groups.get_mut(k).push(i),
+      None => {
+        labels.push(g);
+        groups.push(vec!(i));
+      }
+    }
+  }
+  groups
+}
+
+// add_word over every word, one independent group at a time: each group is
+// solved on the empty grid, and the results are every way of laying one
+// result from each group over the others, which merge_grids can always do
+// since no two groups can reach the same cell.  That's the same set as
+// solving them all together, without dragging every partial solution of one
+// group through the search of the next.  If two groups meet anyway, the
+// grouping was wrong, and the words are solved together after all.
+fn add_groups<'a, T: Tracer<'a>>(search: &mut Search<T>, diag: &mut Diagnostics, grid: &CrosswordGrid<'a>, wordpts: &[(&'a str, &(Point, Point))]) -> SolveResult<'a> {
+  let groups = independent_groups(grid, search.opts, wordpts);
+  if groups.len() < 2 {
+    return add_word(search, diag, SolveResult::start(grid), wordpts);
+  }
+  diag.report(Note, Nowhere, format!("the words fall into {} groups that can't meet; solving each on its own", groups.len()));
+  let mut result = SolveResult::start(grid);
+  for group in groups.iter() {
+    let part: Vec<(&'a str, &(Point, Point))> = group.iter().map(|&i| wordpts[i]).collect();
+    let found = add_word(search, diag, SolveResult::start(grid), part.as_slice());
+    let mut joined = Vec::new();
+    for a in result.solutions.iter() {
+      for b in found.solutions.iter() {
+        match merge_grids(a, b) {
+          Some(merged) => joined.push(merged),
+          None => {
+            diag.report(Warning, Nowhere, ~"two groups of words that shouldn't have met did; solving them together instead");
+            return add_word(search, diag, SolveResult::start(grid), wordpts);
+          }
+        }
+      }
+    }
+    result.solutions = joined;
+    result.stuck_on = result.stuck_on.or(found.stuck_on);
+    if result.completeness == Exhaustive {
+      result.completeness = found.completeness;
+    }
+    result.timed_out.push_all(found.timed_out.as_slice());
+    result.word_stats.push_all_move(found.word_stats);
+    result.spelling_misses.push_all_move(found.spelling_misses);
+  }
+  result
+}
+
+// Which spelling of each variant group the results went with, and how often.
+fn spelling_report<'a>(result: &SolveResult<'a>) -> ~str {
+  let mut counts: TreeMap<&'a str, uint> = TreeMap::new();
+  for chosen in result.spellings.iter() {
+    for &w in chosen.iter() {
+      let n = counts.find(&w).map_or(0, |n| *n);
+      counts.insert(w, n + 1);
+    }
+  }
+  let mut out = StrBuf::new();
+  for (w, n) in counts.iter() {
+    out.push_str(format!("spelled \"{}\" in {} of {} results\n", *w, *n, result.solutions.len()));
+  }
+  out.into_owned()
+}
+
+fn is_optional(opts: &SolveOptions, word: &str) -> bool {
+  opts.optional.iter().any(|w| w.as_slice() == word)
+}
+
+// How many ways `word` fits on `grid` by itself, giving up after visiting
+// `cap` cells.  The flag says whether the count is the whole story.
+fn count_paths<'a>(opts: &SolveOptions, grid: &CrosswordGrid<'a>, word: &'a str, start: Point, end: Point, cap: uint) -> (uint, bool) {
+  let mut capped = opts.clone();
+  capped.max_nodes = Some(cap);
+  let mut search = Search::new(&capped, NoTrace);
+  let mut found = Vec::new();
+  allpaths2(&mut search, grid, word, start, end, word, &mut found);
+  (found.len(), search.halted.is_none())
+}
+
+static PATH_COUNT_CAP: uint = 200000;
+
+// What every path of a word has in common.
+enum Forcing {
+  // there's no path at all
+  Impossible,
+  // too many paths to look at them all
+  TooManyPaths,
+  // the cells on every path, and the letter they have if it's always the same
+  Forced(Vec<(Point, Option<char>)>)
+}
+
+fn forced_cells<'a>(opts: &SolveOptions, grid: &CrosswordGrid<'a>, word: &'a str, start: Point, end: Point) -> Forcing {
+  let mut capped = opts.clone();
+  capped.max_nodes = Some(PATH_COUNT_CAP);
+  let mut search = Search::new(&capped, NoTrace);
+  let mut found = Vec::new();
+  allpaths2(&mut search, grid, word, start, end, word, &mut found);
+  if search.halted.is_some() {
+    return TooManyPaths;
+  }
+  if found.is_empty() {
+    return Impossible;
+  }
+  let mut forced = Vec::new();
+  for (i, tile) in found.get(0).tiles.iter().enumerate() {
+    if !has_word(tile, word) || !found.iter().all(|g| has_word(g.tiles.get(i), word)) {
+      continue;
+    }
+    let letter = tile_char(tile);
+    // a blind entry's unknown letters aren't anything to draw in
+    let same = letter != Some(WILDCARD) && found.iter().all(|g| tile_char(g.tiles.get(i)) == letter);
+    forced.push((Point { x: i as int % grid.width, y: i as int / grid.width }, if same { letter } else { None }));
+  }
+  Forced(forced)
+}
+
+// Each word's forced cells, for --dry-run, and the grid with every forced
+// letter drawn in as a pre-filled cell.
+fn forced_report<'a>(opts: &SolveOptions, grid: &CrosswordGrid<'a>, words: &'a [~str], paths: &[(Point, Point)], diag: &mut Diagnostics) -> (~str, CrosswordGrid<'a>) {
+  let mut out = StrBuf::new();
+  let mut committed = grid.clone();
+  for (word, &(start, end)) in words.iter().zip(paths.iter()) {
+    match forced_cells(opts, grid, word.as_slice(), start, end) {
+      Impossible => out.push_str(format!("\"{}\" has no path at all\n", *word)),
+      TooManyPaths => { }
+      Forced(ref cells) if cells.is_empty() => { }
+      Forced(ref cells) => {
+        let listed: Vec<~str> = cells.iter().map(|&(p, letter)| match letter {
+          Some(c) => format!("{},{} '{}'", p.x, p.y, c),
+          None => format!("{},{}", p.x, p.y)
+        }).collect();
+        out.push_str(format!("\"{}\" always goes through {}\n", *word, listed.connect(", ")));
+        for &(p, letter) in cells.iter() {
+          match (letter, *committed.get_ref(p).unwrap()) {
+            (Some(c), NoWords) => committed.set(p, OneWord(c, PREFILLED)),
+            (Some(c), OneWord(d, w)) if w == PREFILLED && c != d => {
+              diag.report(Warning, Cell(p), format!("\"{}\" needs '{}' here, and another word '{}'", *word, c, d));
+            }
+            _ => { }
+          }
+        }
+      }
+    }
+  }
+  (out.into_owned(), committed)
+}
+
+// The search space if no word got in another's way: the product of each
+// word's path count on the blank grid.  Counts that hit the cap make it an
+// underestimate.
+fn estimate_space<'a>(opts: &SolveOptions, grid: &CrosswordGrid<'a>, wordpts: &[(&'a str, &(Point, Point))]) -> f64 {
+  wordpts.iter().fold(1.0, |accum, &(word, &(start, end))| {
+    let (paths, _) = count_paths(opts, grid, word, start, end, PATH_COUNT_CAP);
+    accum * paths as f64
+  })
+}
+
+// How much of a word's length its endpoints use up, in tenths: 10 means it
+// has to go straight there, 0 that it starts and ends on the same spot.
+fn tightness(word: &str, start: Point, end: Point) -> uint {
+  let steps = std::cmp::max(word.char_len() as int - 1, 1);
+  (start.dist(end) * 10 / steps) as uint
+}
+
+#[deriving(Encodable, Decodable, Clone)]
+struct ModelEntry {
+  length: uint,
+  tightness: uint,
+  priority: f64,
+  samples: uint
+}
+
+// Word priorities by (length, tightness), learned from how many placements
+// words like that had on the puzzles it was built from.  Words from buckets
+// with few placements go first: the search is cheapest when the grids pile
+// up as late as possible.
+#[deriving(Encodable, Decodable, Clone)]
+struct PlacementModel {
+  entries: Vec<ModelEntry>
+}
+
+impl PlacementModel {
+  // the closest bucket, counting a letter of length as a whole unit of tightness
+  fn priority(&self, length: uint, tightness: uint) -> Option<f64> {
+    let diff = |a: uint, b: uint| if a > b { a - b } else { b - a };
+    let mut best: Option<(uint, f64)> = None;
+    for e in self.entries.iter() {
+      let d = diff(e.length, length) * 10 + diff(e.tightness, tightness);
+      if best.map_or(true, |(bd, _)| d < bd) {
+        best = Some((d, e.priority));
+      }
+    }
+    best.map(|(_, p)| p)
+  }
+
+  fn to_json_str(&self) -> ~str {
+    json::Encoder::str_encode(self)
+  }
+
+  fn from_json_str(text: &str) -> Result<PlacementModel, ~str> {
+    let parsed = match json::from_str(text) {
+      Ok(j) => j,
+      Err(e) => return Err(format!("{:?}", e))
+    };
+    let mut decoder = json::Decoder::new(parsed);
+    match Decodable::decode(&mut decoder) {
+      Ok(model) => Ok(model),
+      Err(e) => Err(format!("{:?}", e))
+    }
+  }
+}
+
+fn collect_placement_statistics<'a>(puzzles: &[(CrosswordGrid<'a>, Vec<~str>)]) -> PlacementModel {
+  let opts = SolveOptions::new();
+  let mut sums: TreeMap<(uint, uint), (f64, uint)> = TreeMap::new();
+  for &(ref grid, ref words) in puzzles.iter() {
+    let names: Vec<&str> = words.iter().map(|w| w.as_slice()).collect();
+    let endpoints = match build_endpoint_map(names.as_slice(), &hashgrid(grid.clone())) {
+      Ok(endpoints) => endpoints,
+      Err(e) => fail!("{}", e)
+    };
+    for word in words.iter() {
+      let (start, end) = endpoints.get(*word).unwrap();
+      let (count, _) = count_paths(&opts, grid, word.as_slice(), start, end, PATH_COUNT_CAP);
+      let key = (word.char_len(), tightness(*word, start, end));
+      let (sum, n) = sums.find(&key).map_or((0.0, 0), |&v| v);
+      sums.insert(key, (sum - ((count + 1) as f64).log2(), n + 1));
+    }
+  }
+  let entries = sums.iter().map(|(&(length, tightness), &(sum, n))| {
+    ModelEntry { length: length, tightness: tightness, priority: sum / n as f64, samples: n }
+  }).collect();
+  PlacementModel { entries: entries }
+}
+
+// Fewest paths after this many cells visited wins; that's plenty to tell an
+// easy word from a hard one.
+static CONSTRAINT_PROBE_CAP: uint = 5000;
+
+// The word with the fewest ways to fit on the grid as it stands, estimated by
+// counting its paths for a little while.  Ties go to the earlier word.  Each
+// word's count is kept in `counts`, so picking from what's left over and over
+// only counts each word once.
+fn most_constrained_word<'a, 'g>(opts: &SolveOptions, grid: &CrosswordGrid<'g>, words: &[&'a str], endpoints: &WordEndpointMap, counts: &mut HashMap<&'a str, uint>) -> Option<&'a str> {
+  let mut best: Option<(uint, &'a str)> = None;
+  for &word in words.iter() {
+    let paths = *counts.find_or_insert_with(word, |w| {
+      let (start, end) = endpoints.get(*w).unwrap();
+      count_paths(opts, grid, *w, start, end, CONSTRAINT_PROBE_CAP).val0()
+    });
+    if best.map_or(true, |(fewest, _)| paths < fewest) {
+      best = Some((paths, word));
+    }
+  }
+  best.map(|(_, word)| word)
+}
+
+enum WordOrdering {
+  MostConstrained,
+  Learned(PlacementModel)
+}
+
+// Runs solves, choosing the order words get searched in: the word with the
+// fewest paths first unless it's been given a learned model.
+struct CrosswordSolver {
+  opts: SolveOptions,
+  ordering: WordOrdering
+}
+
+impl CrosswordSolver {
+  fn new(opts: SolveOptions) -> CrosswordSolver {
+    CrosswordSolver { opts: opts, ordering: MostConstrained }
+  }
+
+  fn with_learned_ordering(self, model: PlacementModel) -> CrosswordSolver {
+    CrosswordSolver { ordering: Learned(model), ..self }
+  }
+
+  // Storing true here from any task makes the running solve unwind and
+  // return what it has, marked as cancelled.
+  #[allow(dead_code)]
+  fn cancel_handle(&mut self) -> Arc<AtomicBool> {
+    let flag = Arc::new(AtomicBool::new(false));
+    self.opts.cancel = Some(flag.clone());
+    flag
+  }
+
+  // indices into `words`, in the order to search them
+  fn word_order<'g>(&self, grid: &CrosswordGrid<'g>, endpoints: &WordEndpointMap, words: &[~str]) -> Vec<uint> {
+    let mut order: Vec<uint> = range(0, words.len()).collect();
+    match self.ordering {
+      // the most constrained of the words left, over and over; they're all
+      // counted on the blank grid, so the counts carry over between picks
+      MostConstrained => {
+        let mut left = order.clone();
+        let mut counts = HashMap::new();
+        order.clear();
+        while !left.is_empty() {
+          let names: Vec<&str> = left.iter().map(|&i| words[i].as_slice()).collect();
+          let next = most_constrained_word(&self.opts, grid, names.as_slice(), endpoints, &mut counts).unwrap();
+          let at = names.iter().position(|&w| w == next).unwrap();
+          order.push(left.remove(at).unwrap());
+        }
+      }
+      Learned(ref model) => {
+        let priorities: Vec<f64> = range(0, words.len()).map(|i| {
+          let (start, end) = endpoints.get(words[i]).unwrap();
+          model.priority(words[i].char_len(), tightness(words[i], start, end)).unwrap_or(0.0)
+        }).collect();
+        order.sort_by(|&a, &b| {
+          let (pa, pb) = (*priorities.get(a), *priorities.get(b));
+          if pa > pb { Less }
+          else if pa < pb { Greater }
+          else { words[a].len().cmp(&words[b].len()) }
+        });
+      }
+    }
+    order
+  }
+
+  fn solve<'a>(&self, diag: &mut Diagnostics, grid: &CrosswordGrid<'a>, wordpts: &[(&'a str, &(Point, Point))]) -> SolveResult<'a> {
+    solve(&self.opts, diag, grid, wordpts)
+  }
+
+  // why `word` has no placement on `grid`, when solve gets stuck on it
+  #[allow(dead_code)]
+  fn explain_failure<'a>(&self, grid: &CrosswordGrid<'a>, word: &str, start: Point, end: Point) -> FailureExplanation {
+    explain_failure(&self.opts, grid, word, start, end)
+  }
+}
+
+// Why a word has nowhere to go on a grid, from the coarsest reason to the
+// finest.
+enum FailureExplanation {
+  // no route at all between the waypoints, even over other words
+  NoPath,
+  // the waypoints are further apart than the word is long
+  TooShort,
+  // every route runs into cells that are already full
+  AllCellsOccupied,
+  // the furthest the word got before hitting another word's letter: where,
+  // the letter there, and the one the word needed
+  CharacterMismatch(Point, char, char),
+  // routes are open, just none the exact length of the word
+  NoExactPath,
+  // a walk of the right letters exists, so it can only be crossing itself
+  Unexplained
+}
+
+impl std::fmt::Show for FailureExplanation {
+  fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+    match *self {
+      NoPath => write!(fmt.buf, "its waypoints aren't connected"),
+      TooShort => write!(fmt.buf, "it's too short to reach from one waypoint to the other"),
+      AllCellsOccupied => write!(fmt.buf, "every route is blocked by full cells"),
+      CharacterMismatch(p, found, wanted) => write!(fmt.buf, "it needs '{}' at {},{} but another word put '{}' there", wanted, p.x, p.y, found),
+      NoExactPath => write!(fmt.buf, "no open route is exactly its length"),
+      Unexplained => write!(fmt.buf, "every route would have it cross itself")
+    }
+  }
+}
+
+// Whether `end` can be reached from `start` stepping only on cells `passable`
+// accepts.
+fn reaches<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions, start: Point, end: Point, passable: |Point, &TileData<'a>| -> bool) -> bool {
+  let mut seen: Grid<bool> = grid.map(|_| false);
+  let mut queue: RingBuf<Point> = RingBuf::new();
+  seen.set(start, true);
+  queue.push_back(start);
+  loop {
+    let next = match queue.pop_front() {
+      Some(q) => q,
+      None => return false
+    };
+    for &n in grid.steps(opts, next).iter() {
+      if n == end {
+        return true;
+      }
+      if !*seen.get_ref(n).unwrap() && passable(n, grid.get_ref(n).unwrap()) {
+        seen.set(n, true);
+        queue.push_back(n);
+      }
+    }
+  }
+}
+
+fn explain_failure<'a>(opts: &SolveOptions, grid: &CrosswordGrid<'a>, word: &str, start: Point, end: Point) -> FailureExplanation {
+  let steps = word.char_len() as int - 1;
+  match opts.bound {
+    Some(ref bound) if bound.min_steps(start, end) > steps => return TooShort,
+    _ => { }
+  }
+  if !reaches(grid, opts, start, end, |_, tile| match *tile { Fixed(_) => opts.through_waypoints, Blocked => false, _ => true }) {
+    return NoPath;
+  }
+  let has_room = |p: Point| opts.capacity.as_ref().map_or(true, |cap| *cap.get_ref(p).unwrap() >= 2);
+  if !reaches(grid, opts, start, end, |p, tile| match *tile {
+    NoWords => true,
+    OneWord(_, w) => w != word && has_room(p),
+    Fixed(c) => opts.through_waypoints && passable_waypoint(word, c) && has_room(p),
+    _ => false
+  }) {
+    return AllCellsOccupied;
+  }
+  // walk the word letter by letter, breadth first, without worrying about it
+  // crossing itself; at most one visit per cell per letter
+  let letters: Vec<char> = word.chars().collect();
+  let mut seen: HashSet<(Point, int)> = HashSet::new();
+  let mut queue: RingBuf<(Point, int)> = RingBuf::new();
+  let mut mismatch: Option<(int, Point, char, char)> = None;
+  queue.push_back((start, 0));
+  loop {
+    let (p, depth) = match queue.pop_front() {
+      Some(state) => state,
+      None => break
+    };
+    for &n in grid.steps(opts, p).iter() {
+      let next = depth + 1;
+      if next == steps {
+        if n == end {
+          return Unexplained;
+        }
+        continue;
+      }
+      let wanted = *letters.get(next as uint);
+      let open = match *grid.get_ref(n).unwrap() {
+        NoWords => true,
+        OneWord(c, w) if w != word && has_room(n) => {
+          let matches = ALPHABET.same(c, wanted);
+          if !matches && mismatch.map_or(true, |(d, _, _, _)| next > d) {
+            mismatch = Some((next, n, c, wanted));
+          }
+          matches
+        }
+        Fixed(c) if opts.through_waypoints && passable_waypoint(word, c) && has_room(n) => ALPHABET.same(c, wanted),
+        _ => false
+      };
+      if open && seen.insert((n, next)) {
+        queue.push_back((n, next));
+      }
+    }
+  }
+  match mismatch {
+    Some((_, p, found, wanted)) => CharacterMismatch(p, found, wanted),
+    None => NoExactPath
+  }
+}
+
+// Reads a grid, its overlay if it has one, and its words, shortest word
+// first, and checks the grid over.
+fn load<'a>(grid_file: &str, overlay_file: Option<&str>, words_file: &str, opts: &SolveOptions, diag: &mut Diagnostics) -> Result<(CrosswordGrid<'a>, Option<Overlay>, Vec<~str>), SolveError> {
+  let mut grid = *readgrid(grid_file, opts.prefilled, diag);
+  let overlay = match overlay_file {
+    Some(file) => Some(try!(readoverlay(file, &mut grid, diag))),
+    None => None
+  };
+  let words = readwords(words_file, opts.max_word_len, diag);
+  checked(grid_file, grid, overlay, words, opts, diag)
+}
+
+// With --prefilled, a word whose first or last letter has no waypoint can
+// carry on from a fragment drawn in beforehand instead: if exactly one
+// fragment has an open end with that letter, and the word's next letter in
+// from that end next to it, that cell becomes the word's waypoint.
+fn imply_anchors<'a>(grid: CrosswordGrid<'a>, words: &[~str], opts: &SolveOptions, diag: &mut Diagnostics) -> CrosswordGrid<'a> {
+  if !opts.prefilled {
+    return grid;
+  }
+  let mut grid = grid;
+  let drawn = |tile: &TileData| match *tile { OneWord(c, w) if w == PREFILLED => Some(ALPHABET.normalize(c)), _ => None };
+  for word in words.iter().flat_map(|entry| entry.split('/')) {
+    let letters: Vec<char> = word.chars().map(|c| ALPHABET.normalize(c)).collect();
+    if letters.len() < 3 {
+      continue;
+    }
+    let n = letters.len();
+    for &(end, next) in [(letters[0], letters[1]), (letters[n - 1], letters[n - 2])].iter() {
+      if grid.tiles.iter().any(|tile| *tile == Fixed(end)) {
+        continue;
+      }
+      let mut ends = Vec::new();
+      for (i, tile) in grid.tiles.iter().enumerate() {
+        let p = Point { x: i as int % grid.width, y: i as int / grid.width };
+        if drawn(tile) != Some(end) {
+          continue;
+        }
+        let joined: Vec<Point> = grid.either_way(opts, p).move_iter().filter(|&q| drawn(grid.get_ref(q).unwrap()).is_some()).collect();
+        if joined.len() == 1 && drawn(grid.get_ref(*joined.get(0)).unwrap()) == Some(next) {
+          ends.push(p);
+        }
+      }
+      match ends.as_slice() {
+        [] => { }
+        [p] => {
+          grid.set(p, Fixed(end));
+          diag.report(Note, Cell(p), format!("\"{}\" carries on from the fragment ending here, which becomes waypoint {}", word, end.to_upper()));
+        }
+        _ => diag.report(Warning, Nowhere, format!("\"{}\" could carry on from {} fragments ending in {}; add a waypoint to pick one", word, ends.len(), end.to_upper()))
+      }
+    }
+  }
+  grid
+}
+
+// Shortest word first, and only if the grid passes preflight and has no
+// letter twice.
+fn checked<'a>(grid_file: &str, grid: CrosswordGrid<'a>, overlay: Option<Overlay>, words: Vec<~str>, opts: &SolveOptions, diag: &mut Diagnostics) -> Result<(CrosswordGrid<'a>, Option<Overlay>, Vec<~str>), SolveError> {
+  let (grid, mut words) = try!(anchor_words(grid_file, grid, words));
+  let grid = imply_anchors(grid, words.as_slice(), opts, diag);
+  words.sort_by(|a,b| a.len().cmp(&b.len()));
+  match preflight(&grid, opts, diag) {
+    Err(errors) => return Err(BadGrid(grid_file.to_owned(), errors)),
+    Ok(()) => { }
+  }
+  try!(check_coordinates(&grid, option_coordinates(opts).as_slice()));
+  length_report(&grid, words.as_slice(), opts, diag);
+  try!(waypoints(&grid));
+  Ok((grid, overlay, words))
+}
+
+// A whole puzzle in one file, as sections that each start with a [name]
+// header line:
+//
+//   [metadata]     key = value lines: title, author, anything else
+//   [grid]         the grid, exactly as in a grid file
+//   [overlay]      optional, exactly as in an overlay file
+//   [words]        one word per line
+//   [constraints]  free-form lines, kept for later passes to read
+//   [config]       key = value lines, each the long name of a solve option
+//                  and its value (empty for flags)
+//
+// A grid or overlay section runs right up to the next header, and every line
+// of it is a row, blank ones included.  In the other sections blank lines and
+// lines starting with ; are skipped.  The grid and words sections are
+// required; each section can appear only once.
+#[deriving(Eq, Clone)]
+struct Puzzle {
+  metadata: Vec<(~str, ~str)>,
+  grid: Vec<~str>,
+  overlay: Option<Vec<~str>>,
+  words: Vec<~str>,
+  constraints: Vec<~str>,
+  config: Vec<(~str, ~str)>
+}
+
+type SectionParser = fn(&mut Puzzle, &[~str]) -> Result<(), ~str>;
+
+// name, whether its lines are rows taken verbatim, and its parser
+static SECTIONS: [(&'static str, bool, SectionParser), ..6] = [
+  ("metadata", false, parse_metadata),
+  ("grid", true, parse_grid_section),
+  ("overlay", true, parse_overlay_section),
+  ("words", false, parse_words_section),
+  ("constraints", false, parse_constraints),
+  ("config", false, parse_config)
+];
+
+fn content_lines<'l>(lines: &'l [~str]) -> Vec<&'l str> {
+  lines.iter().map(|l| l.trim()).filter(|l| !l.is_empty() && !l.starts_with(";")).collect()
+}
+
+fn parse_pairs(lines: &[~str]) -> Result<Vec<(~str, ~str)>, ~str> {
+  content_lines(lines).iter().map(|line| match line.find('=') {
+    Some(i) => Ok((line.slice_to(i).trim().to_owned(), line.slice_from(i + 1).trim().to_owned())),
+    None if !line.contains_char(' ') => Ok((line.to_owned(), ~"")),
+    None => Err(format!("expected key = value, not \"{}\"", *line))
+  }).collect()
+}
+
+fn parse_metadata(puzzle: &mut Puzzle, lines: &[~str]) -> Result<(), ~str> {
+  puzzle.metadata = try!(parse_pairs(lines));
+  Ok(())
+}
+
+fn parse_grid_section(puzzle: &mut Puzzle, lines: &[~str]) -> Result<(), ~str> {
+  if lines.is_empty() {
+    return Err(~"the grid is empty");
+  }
+  puzzle.grid = Vec::from_slice(lines);
+  Ok(())
+}
+
+fn parse_overlay_section(puzzle: &mut Puzzle, lines: &[~str]) -> Result<(), ~str> {
+  puzzle.overlay = Some(Vec::from_slice(lines));
+  Ok(())
+}
+
+fn parse_words_section(puzzle: &mut Puzzle, lines: &[~str]) -> Result<(), ~str> {
+  puzzle.words = content_lines(lines).iter().map(|l| l.to_owned()).collect();
+  Ok(())
+}
+
+fn parse_constraints(puzzle: &mut Puzzle, lines: &[~str]) -> Result<(), ~str> {
+  puzzle.constraints = content_lines(lines).iter().map(|l| l.to_owned()).collect();
+  Ok(())
+}
+
+fn parse_config(puzzle: &mut Puzzle, lines: &[~str]) -> Result<(), ~str> {
+  puzzle.config = try!(parse_pairs(lines));
+  Ok(())
+}
+
+// Splits the text into sections and hands each to its parser.  Errors carry
+// the line number they were found at.
+fn parse_puzzle(lines: &[~str]) -> Result<Puzzle, ~str> {
+  let mut puzzle = Puzzle { metadata: Vec::new(), grid: Vec::new(), overlay: None, words: Vec::new(), constraints: Vec::new(), config: Vec::new() };
+  let mut seen: Vec<&str> = Vec::new();
+  let mut i = 0;
+  while i < lines.len() {
+    let header = lines[i].trim();
+    if header.is_empty() || header.starts_with(";") {
+      i += 1;
+      continue;
+    }
+    if !header.starts_with("[") || !header.ends_with("]") {
+      return Err(format!("line {}: expected a [section] header", i + 1));
+    }
+    let name = header.slice(1, header.len() - 1);
+    let parser = match SECTIONS.iter().find(|&&(n, _, _)| n == name) {
+      Some(&(_, _, parser)) => parser,
+      None => return Err(format!("line {}: unknown section [{}]", i + 1, name))
+    };
+    if seen.contains(&name) {
+      return Err(format!("line {}: second [{}] section", i + 1, name));
+    }
+    seen.push(name);
+    let start = i + 1;
+    let mut end = start;
+    while end < lines.len() && !is_section_header(lines[end]) {
+      end += 1;
+    }
+    match parser(&mut puzzle, lines.slice(start, end)) {
+      Ok(()) => { }
+      Err(e) => return Err(format!("line {}: [{}]: {}", start, name, e))
+    }
+    i = end;
+  }
+  for &required in ["grid", "words"].iter() {
+    if !seen.contains(&required) {
+      return Err(format!("no [{}] section", required));
+    }
+  }
+  Ok(puzzle)
+}
+
+fn is_section_header(line: &str) -> bool {
+  let line = line.trim();
+  line.starts_with("[") && line.ends_with("]") && SECTIONS.iter().any(|&(n, _, _)| n == line.slice(1, line.len() - 1))
+}
+
+// The inverse of parse_puzzle: parsing what this writes gives back an equal
+// Puzzle.  Empty optional sections are left out.
+#[allow(dead_code)]
+fn write_puzzle(puzzle: &Puzzle) -> ~str {
+  let mut out = StrBuf::new();
+  let pairs = |out: &mut StrBuf, name: &str, pairs: &Vec<(~str, ~str)>| {
+    if pairs.is_empty() { return; }
+    out.push_str(format!("[{}]\n", name));
+    for &(ref k, ref v) in pairs.iter() {
+      if v.is_empty() { out.push_str(format!("{}\n", *k)); }
+      else            { out.push_str(format!("{} = {}\n", *k, *v)); }
+    }
+    out.push_char('\n');
+  };
+  // no blank line after the rows when they're verbatim, since it would be
+  // read back as one more row
+  let rows = |out: &mut StrBuf, name: &str, rows: &Vec<~str>| {
+    out.push_str(format!("[{}]\n", name));
+    for row in rows.iter() {
+      out.push_str(*row);
+      out.push_char('\n');
+    }
+    if !SECTIONS.iter().any(|&(n, verbatim, _)| n == name && verbatim) {
+      out.push_char('\n');
+    }
+  };
+  pairs(&mut out, "metadata", &puzzle.metadata);
+  rows(&mut out, "grid", &puzzle.grid);
+  match puzzle.overlay {
+    Some(ref overlay) => rows(&mut out, "overlay", overlay),
+    None => { }
+  }
+  rows(&mut out, "words", &puzzle.words);
+  if !puzzle.constraints.is_empty() {
+    rows(&mut out, "constraints", &puzzle.constraints);
+  }
+  pairs(&mut out, "config", &puzzle.config);
+  out.into_owned()
+}
+
+// Section line numbers for diagnostics: the line after the section's header.
+fn section_line(lines: &[~str], name: &str) -> uint {
+  let header = format!("[{}]", name);
+  lines.iter().position(|l| l.trim() == header.as_slice()).map_or(1, |i| i + 2)
+}
+
+// Like load, for a combined puzzle file.  Its [config] is read separately, by
+// with_puzzle_config.
+fn load_puzzle<'a>(file: &str, opts: &SolveOptions, diag: &mut Diagnostics) -> Result<(CrosswordGrid<'a>, Option<Overlay>, Vec<~str>), SolveError> {
+  let lines = readlines(file);
+  let puzzle = match parse_puzzle(lines.as_slice()) {
+    Ok(p) => p,
+    Err(e) => return Err(BadFile(file.to_owned(), e))
+  };
+  let mut grid = *parsegrid(file, section_line(lines.as_slice(), "grid"), puzzle.grid.as_slice(), opts.prefilled, diag);
+  let overlay = match puzzle.overlay {
+    Some(ref rows) => Some(try!(parseoverlay(file, section_line(lines.as_slice(), "overlay"), rows.as_slice(), &mut grid, diag))),
+    None => None
+  };
+  let words = parsewords(file, section_line(lines.as_slice(), "words"), puzzle.words.as_slice(), opts.max_word_len, diag);
+  checked(file, grid, overlay, words, opts, diag)
+}
+
+// The command line with a puzzle file's [config] added as the options it
+// names.  Anything given on the command line wins.
+fn with_puzzle_config(args: &[~str], flags: &[OptGroup], matches: &Matches, file: &str) -> ~[~str] {
+  let mut args = args.to_owned();
+  let config = match parse_puzzle(readlines(file).as_slice()) {
+    Ok(puzzle) => puzzle.config,
+    Err(_) => return args
+  };
+  for &(ref k, ref v) in config.iter() {
+    if !flags.iter().any(|f| f.long_name.as_slice() == k.as_slice()) {
+      fail!("{}: [config]: no such option as {}", file, *k);
+    }
+    if matches.opt_present(*k) {
+      continue;
+    }
+    args.push(format!("--{}", *k));
+    if !v.is_empty() {
+      args.push(v.clone());
+    }
+  }
+  args
+}
+
+// A named set of cells.  Regions can overlap; a cell counts towards each one
+// it's in.
+struct Region {
+  name: ~str,
+  cells: Vec<Point>
+}
+
+// "NAME x1,y1:x2,y2" or "NAME=x1,y1:x2,y2", corners included
+fn parse_region(spec: &str) -> Result<Region, ~str> {
+  let split = match spec.find(|c: char| c == '=' || c.is_whitespace()) {
+    Some(i) => i,
+    None => return Err(format!("\"{}\" needs a name and corners", spec))
+  };
+  let (name, corners) = (spec.slice_to(split).to_owned(), spec.slice_from(split + 1).trim());
+  let points: Vec<Option<(int, int)>> = corners.split(':').map(|corner| {
+    let xy: Vec<Option<int>> = corner.split(',').map(|n| from_str::<int>(n.trim())).collect();
+    match xy.as_slice() {
+      [Some(x), Some(y)] => Some((x, y)),
+      _ => None
+    }
+  }).collect();
+  match points.as_slice() {
+    [Some((x1, y1)), Some((x2, y2))] => {
+      let mut cells = Vec::new();
+      for y in range(std::cmp::min(y1, y2), std::cmp::max(y1, y2) + 1) {
+        for x in range(std::cmp::min(x1, x2), std::cmp::max(x1, x2) + 1) {
+          cells.push(Point { x: x, y: y });
+        }
+      }
+      Ok(Region { name: name, cells: cells })
+    }
+    _ => Err(format!("\"{}\" should be two corners like 0,0:4,4", corners))
+  }
+}
+
+// One region per region letter in the overlay, named after the letter.
+fn overlay_regions(overlay: &Overlay) -> Vec<Region> {
+  let mut regions: TreeMap<char, Vec<Point>> = TreeMap::new();
+  for (i, r) in overlay.regions.tiles.iter().enumerate() {
+    match *r {
+      Some(c) => {
+        let p = Point { x: i as int % overlay.regions.width, y: i as int / overlay.regions.width };
+        let mut cells = regions.pop(&c).unwrap_or(Vec::new());
+        cells.push(p);
+        regions.insert(c, cells);
+      }
+      None => { }
+    }
+  }
+  regions.move_iter().map(|(c, cells)| Region { name: c.to_str(), cells: cells }).collect()
+}
+
+// How full a grid is, overall and in each region.  Only cells a word could
+// use count: walls and waypoints are left out of both sides.  Waypoints are
+// counted on their own: each one should start or end some word on the grid.
+struct GridStats {
+  cells: uint,
+  filled: uint,
+  // name, filled, cells
+  regions: Vec<(~str, uint, uint)>,
+  // waypoints no word on the grid starts or ends at, or passes through
+  unused_waypoints: Vec<(char, Point)>
+}
+
+#[inline] fn fill_counts<'a, I: Iterator<&'a TileData<'a>>>(mut tiles: I) -> (uint, uint) {
+  tiles.fold((0, 0), |(filled, cells), tile| match *tile {
+    NoWords => (filled, cells + 1),
+    OneWord(..) | TwoWords(..) => (filled + 1, cells + 1),
+    Fixed(_) | Through(..) | Blocked => (filled, cells)
+  })
+}
+
+// the share of the cells a word could use that one does, 1.0 when there are none
+fn fill_ratio<'a>(grid: &CrosswordGrid<'a>) -> f64 {
+  let (filled, cells) = fill_counts(grid.tiles.iter());
+  if cells == 0 { 1.0 } else { filled as f64 / cells as f64 }
+}
+
+// `wordpts` has every word in the puzzle and its waypoints; only the ones that
+// made it onto the grid use theirs.  A two-letter word has no cells, so it's
+// always there.
+fn grid_stats<'a>(grid: &CrosswordGrid<'a>, regions: &[Region], wordpts: &[(&str, &(Point, Point))]) -> GridStats {
+  let (filled, cells) = fill_counts(grid.tiles.iter());
+  let regions = regions.iter().map(|region| {
+    // every region was checked against the grid when it was read
+    let (filled, cells) = fill_counts(region.cells.iter().map(|&p| grid.get_ref(p).unwrap()));
+    (region.name.clone(), filled, cells)
+  }).collect();
+  let placed: Vec<&(Point, Point)> = wordpts.iter().filter(|&&(w, _)| w.char_len() <= 2 || contains_word_cell(grid, w)).map(|&(_, ends)| ends).collect();
+  let mut unused = Vec::new();
+  for (i, tile) in grid.tiles.iter().enumerate() {
+    let p = Point { x: i as int % grid.width, y: i as int / grid.width };
+    match *tile {
+      Fixed(c) if !placed.iter().any(|&&(start, end)| start == p || end == p) => unused.push((c, p)),
+      _ => { }
+    }
+  }
+  GridStats { cells: cells, filled: filled, regions: regions, unused_waypoints: unused }
+}
+
+// The cells a grid leaves off every one of `paths`, in the order they're
+// stored.  Each path runs from waypoint to waypoint, both included, and only
+// walls don't need one; so a fully packed grid has none left over.
+fn uncovered_cells<'a>(grid: &CrosswordGrid<'a>, paths: &[Vec<Point>]) -> Vec<Point> {
+  let mut covered: HashSet<Point> = HashSet::new();
+  for path in paths.iter() {
+    covered.extend(path.iter().map(|&p| p));
+  }
+  let mut uncovered = Vec::new();
+  for (i, tile) in grid.tiles.iter().enumerate() {
+    let p = Point { x: i as int % grid.width, y: i as int / grid.width };
+    if *tile != Blocked && !covered.contains(&p) {
+      uncovered.push(p);
+    }
+  }
+  uncovered
+}
+
+fn covers_all_cells<'a>(grid: &CrosswordGrid<'a>, paths: &[Vec<Point>]) -> bool {
+  uncovered_cells(grid, paths).is_empty()
+}
+
+// Each placed word's cells from waypoint to waypoint, as covers_all_cells
+// wants them.
+fn placed_paths<'a>(grid: &CrosswordGrid<'a>, wordpts: &[(&'a str, &(Point, Point))], opts: &SolveOptions) -> Vec<Vec<Point>> {
+  wordpts.iter().filter(|&&(w, _)| w.char_len() <= 2 || contains_word_cell(grid, w)).map(|&(w, &(start, end))| {
+    WordPath::new(grid, w, start, end, opts).cells
+  }).collect()
+}
+
+impl GridStats {
+  fn region_percent(&self, name: &str) -> Option<f64> {
+    self.regions.iter().find(|&&(ref n, _, _)| n.as_slice() == name).map(|&(_, filled, cells)| {
+      if cells == 0 { 100.0 } else { filled as f64 * 100.0 / cells as f64 }
+    })
+  }
+
+  fn summary(&self) -> ~str {
+    let mut out = StrBuf::new();
+    out.push_str(format!("{} of {} cells filled", self.filled, self.cells));
+    for &(ref name, filled, cells) in self.regions.iter() {
+      out.push_str(format!("; {} {}/{}", *name, filled, cells));
+    }
+    if !self.unused_waypoints.is_empty() {
+      let unused: Vec<~str> = self.unused_waypoints.iter().map(|&(c, p)| format!("{} at {},{}", c.to_upper(), p.x, p.y)).collect();
+      out.push_str(format!("; no word uses waypoint {}", unused.connect(", ")));
+    }
+    out.into_owned()
+  }
+}
+
+// "theme=70": region theme at least 70% filled
+fn parse_fill_requirement(spec: &str) -> Result<(~str, f64), ~str> {
+  match spec.find('=') {
+    Some(i) => match from_str::<f64>(spec.slice_from(i + 1).trim_right_chars(&'%')) {
+      Some(percent) => Ok((spec.slice_to(i).to_owned(), percent)),
+      None => Err(format!("\"{}\" needs a percentage after the =", spec))
+    },
+    None => Err(format!("\"{}\" should look like NAME=PERCENT", spec))
+  }
+}
+
+// Stand-ins for words when a grid is drawn with symbols.  Capitals are left
+// out so they can't be mistaken for waypoints.
+static WORD_SYMBOLS: &'static str = "123456789abcdefghijklmnopqrstuvwxyz@$%&*+=?";
+
+// Each word's symbol, in word-list order.  Past the last symbol, words start
+// sharing them.
+fn word_symbols<'w>(words: &'w [~str], diag: &mut Diagnostics) -> Vec<(&'w str, char)> {
+  let symbols: Vec<char> = WORD_SYMBOLS.chars().collect();
+  if words.len() > symbols.len() {
+    diag.report(Warning, Nowhere, format!("only {} symbols for {} words; some will share", symbols.len(), words.len()));
+  }
+  words.iter().enumerate().map(|(i, w)| (w.as_slice(), *symbols.get(i % symbols.len()))).collect()
+}
+
+// A grid with every tile drawn as the symbols of the words on it, two
+// characters a tile, then the legend of which symbol is which word.
+fn render_symbols<'a>(grid: &CrosswordGrid<'a>, symbols: &[(&str, char)]) -> ~str {
+  let symbol = |word: &str| symbols.iter().find(|&&(w, _)| w == word).map_or(' ', |&(_, c)| c);
+  let mut out = StrBuf::new();
+  for y in range(0, grid.height) {
+    for x in range(0, grid.width) {
+      match *grid.get_ref(Point { x: x, y: y }).unwrap() {
+        OneWord(_, w) => { out.push_char(symbol(w)); out.push_char(' '); }
+        TwoWords(_, a, b) => { out.push_char(symbol(a)); out.push_char(symbol(b)); }
+        Through(c, w) => { out.push_char(c.to_upper()); out.push_char(symbol(w)); }
+        ref tile => { out.push_char(default_char(tile)); out.push_char(' '); }
+      }
+    }
+    out.push_char('\n');
+  }
+  for &(w, c) in symbols.iter().filter(|&&(w, _)| contains_word_cell(grid, w)) {
+    out.push_str(format!("  {}  {}\n", c, w));
+  }
+  out.into_owned()
+}
+
+// A bordered table of the words on a grid: how many cells each was written
+// into, the waypoints it runs between, and its length in letters.
+fn render_legend<'a>(grid: &CrosswordGrid<'a>) -> ~str {
+  let mut words: Vec<&'a str> = Vec::new();
+  let mut waypoints = HashMap::new();
+  for (i, tile) in grid.tiles.iter().enumerate() {
+    let p = Point { x: i as int % grid.width, y: i as int / grid.width };
+    match *tile {
+      Fixed(c) => { waypoints.insert(ALPHABET.normalize(c), p); }
+      Through(c, w) => { waypoints.insert(ALPHABET.normalize(c), p); words.push(w); }
+      OneWord(_, w) if w != PREFILLED => words.push(w),
+      TwoWords(_, a, b) => { words.push(a); words.push(b); }
+      _ => { }
+    }
+  }
+  let mut counts: TreeMap<&'a str, uint> = TreeMap::new();
+  for &w in words.iter() {
+    let n = counts.find(&w).map_or(0, |&n| n);
+    counts.insert(w, n + 1);
+  }
+  let at = |c: char| waypoints.find(&ALPHABET.normalize(c)).map_or(~"?", |p| format!("{},{}", p.x, p.y));
+  let header = [~"word", ~"cells", ~"start", ~"end", ~"length"];
+  let mut rows: Vec<~[~str]> = vec!(header);
+  for (&w, &n) in counts.iter() {
+    rows.push(~[w.to_owned(), n.to_str(), at(w.char_at(0)), at(w.char_at_reverse(w.len())), w.char_len().to_str()]);
+  }
+  let widths: Vec<uint> = range(0, header.len()).map(|i| rows.iter().map(|r| r[i].char_len()).max().unwrap()).collect();
+  let dashes: Vec<~str> = widths.iter().map(|&w| "-".repeat(w + 2)).collect();
+  let rule = format!("+{}+\n", dashes.connect("+"));
+  let mut out = StrBuf::new();
+  out.push_str(rule.as_slice());
+  for (i, row) in rows.iter().enumerate() {
+    for (cell, &width) in row.iter().zip(widths.iter()) {
+      out.push_str(format!("| {}{} ", *cell, " ".repeat(width - cell.char_len())));
+    }
+    out.push_str("|\n");
+    if i == 0 {
+      out.push_str(rule.as_slice());
+    }
+  }
+  out.push_str(rule.as_slice());
+  out.into_owned()
+}
+
+// What gets printed about the results.  Every output flag is read in
+// output_plan and nowhere else, so they can't disagree with each other.
+#[deriving(Clone)]
+struct OutputPlan {
+  // the first K results in full, with a symbol per word instead of letters
+  best: Option<uint>,
+  symbols: bool,
+  // each word on its own after the merged grid
+  per_word: bool,
+  // the results of every iteration, not just the last
+  per_iteration: bool,
+  // draw the best results with only the cells the search filled in
+  added: bool,
+  // or with each word's cells marked by the parity of their place in it
+  parity: bool,
+  // which way up to print every grid
+  orientation: Orientation,
+  // only what the first version printed: the merged grid and each word's,
+  // with no legend, count or diagnostics
+  legacy: bool
+}
+
+// How printed grids are turned from the way the grid file has them: first
+// transposed if asked, then turned clockwise a quarter at a time.  Only the
+// drawing changes; the solve, and every coordinate it reports in warnings
+// and certificates, stay the grid file's.
+#[deriving(Clone, Eq)]
+struct Orientation {
+  transpose: bool,
+  turns: uint
+}
+
+impl Orientation {
+  fn apply<T: Clone>(&self, grid: &Grid<T>) -> Grid<T> {
+    let mut out = if self.transpose { grid.transpose() } else { grid.clone() };
+    for _ in range(0, self.turns) {
+      out = out.rotate();
+    }
+    out
+  }
+  fn is_identity(&self) -> bool {
+    !self.transpose && self.turns == 0
+  }
+  fn describe(&self) -> ~str {
+    match (self.transpose, self.turns) {
+      (false, 0) => ~"as given",
+      (true, 0) => ~"transposed",
+      (false, n) => format!("rotated {}", n * 90),
+      (true, n) => format!("transposed, then rotated {}", n * 90)
+    }
+  }
+}
+
+fn output_flags() -> ~[OptGroup] {
+  ~[
+    optopt("", "best", "print the first K results in full (ordered by score unless --order says otherwise)", "K"),
+    optflag("", "symbols", "draw --best results with a symbol per word and a legend"),
+    optflag("", "added", "draw --best results with only the cells the search filled in"),
+    optflag("", "parity", "draw --best results with each word's cells marked by whether they're its even or odd letters"),
+    optflag("", "per-word", "after the merged grid, show where each word goes on its own"),
+    optopt("", "rotate", "print every grid turned clockwise by 90, 180 or 270 degrees", "DEGREES"),
+    optflag("", "transpose", "print every grid with its rows and columns swapped, before any --rotate"),
+    optflag("", "per-iteration", "show the results of every iteration, not just the last"),
+    optflag("", "legacy-output", "print only what the first version did: the merged grid and every word on its own, after every iteration")
+  ]
+}
+
+fn output_plan(matches: &Matches) -> OutputPlan {
+  match parse_output_plan(matches) {
+    Ok(plan) => plan,
+    Err(e) => fail!("{}", e)
+  }
+}
+
+fn parse_output_plan(matches: &Matches) -> Result<OutputPlan, ~str> {
+  let legacy = matches.opt_present("legacy-output");
+  let best = match try!(count_flag(matches, "best")) {
+    Some(0) => return Err(~"--best must be at least 1"),
+    k => k
+  };
+  let turns = match matches.opt_str("rotate") {
+    None => 0,
+    Some(ref n) if n.as_slice() == "90" || n.as_slice() == "180" || n.as_slice() == "270" => from_str::<uint>(n.as_slice()).unwrap() / 90,
+    Some(n) => return Err(format!("--rotate takes 90, 180 or 270, not \"{}\"", n))
+  };
+  Ok(OutputPlan {
+    best: best,
+    symbols: matches.opt_present("symbols"),
+    per_word: legacy || matches.opt_present("per-word"),
+    per_iteration: legacy || matches.opt_present("per-iteration"),
+    added: matches.opt_present("added"),
+    parity: matches.opt_present("parity"),
+    orientation: Orientation {
+      transpose: matches.opt_present("transpose"),
+      turns: turns
+    },
+    legacy: legacy
+  })
+}
+
+// a grid turned the way the plan says, and a hex grid drawn staggered so
+// that each cell's neighbors look it
+fn drawn_for(opts: &SolveOptions, plan: &OutputPlan, grid: StringGrid) -> ~str {
+  let grid = plan.orientation.apply(&grid);
+  match opts.topology {
+    Square => format!("{}", grid),
+    Hex => format!("{}", HexGrid::new(grid))
+  }
+}
+
+// Everything printed about one set of results: the merged grid, the best few
+// in full if asked for (drawn with word symbols and a legend if `symbols`),
+// then each word on its own if the plan says so.
+fn render_results<'a, 'b>(blank: &CrosswordGrid<'b>, results: &Vec<CrosswordGrid<'a>>, words: &[~str], opts: &SolveOptions, plan: &OutputPlan, regions: &[Region], diag: &mut Diagnostics) -> ~str {
+  let (best, symbols) = (plan.best, plan.symbols);
+  // a filter like drop_unclaimed can leave nothing to merge
+  if results.is_empty() {
+    return ~"no results\n";
+  }
+  let mut out = StrBuf::new();
+  if plan.legacy {
+    out.push_str(format!("{}\n", drawn_for(opts, plan, results.flatten())));
+    for w in words.iter() {
+      out.push_str(format!("Showing only \"{}\":\n", *w));
+      out.push_str(format!("{}\n", drawn_for(opts, plan, results.flattenWord(*w))));
+    }
+    return out.into_owned();
+  }
+  out.push_str(format!("{}\n", drawn_for(opts, plan, results.flatten())));
+  if !results.is_empty() {
+    // the legend's coordinates are the printed grid's
+    out.push_str(render_legend(&plan.orientation.apply(results.get(0))));
+  }
+  match best {
+    Some(k) => {
+      let legend = if symbols { word_symbols(words, diag) } else { Vec::new() };
+      let paths = word_endpoints(&hashgrid(blank.clone()), words).unwrap_or(Vec::new());
+      let wordpts: Vec<(&str, &(Point, Point))> = words.iter().map(|w| w.as_slice()).zip(paths.iter()).collect();
+      for (i, grid) in results.iter().take(k).enumerate() {
+        out.push_str(format!("result {} (score {}, {:.0f}% filled, id {}):\n", i + 1, score(grid), fill_ratio(grid) * 100.0, solution_id(grid)));
+        let stats = grid_stats(grid, regions, wordpts.as_slice());
+        if !regions.is_empty() || !stats.unused_waypoints.is_empty() {
+          out.push_str(format!("{}\n", stats.summary()));
+        }
+        if plan.added {
+          out.push_str(format!("{}\n", drawn_for(opts, plan, added_cells(blank, grid))));
+        } else if plan.parity {
+          out.push_str(format!("{}\n", drawn_for(opts, plan, parity_cells(grid, words, opts))));
+        } else if symbols {
+          out.push_str(render_symbols(&plan.orientation.apply(grid), legend.as_slice()));
+        } else {
+          out.push_str(format!("{}\n", drawn_for(opts, plan, grid.to_strgrid())));
+        }
+      }
+    }
+    None => { }
+  }
+  if !results.is_empty() {
+    for chain in opts.chains.iter() {
+      out.push_str(format!("chain {}:\n{}\n", chain.connect(" -> "), drawn_for(opts, plan, chain_cells(results.get(0), chain.as_slice()))));
+    }
+  }
+  let split = results.iter().filter(|grid| !is_single_component(*grid, opts)).count();
+  if split > 0 {
+    diag.report(Warning, Nowhere, format!("{} of {} solutions leave the words in separate pieces", split, results.len()));
+  }
+  for w in words.iter().filter(|w| is_blind(**w)) {
+    if !results.is_empty() {
+      let patterns: Vec<~str> = blind_patterns(results.as_slice(), *w, opts).iter().map(|&(ref p, n)| format!("{} ({})", *p, n)).collect();
+      out.push_str(format!("\"{}\" letter patterns: {}\n", *w, patterns.connect(", ")));
+    }
+  }
+  if !plan.per_word {
+    return out.into_owned();
+  }
+  let waypoints = if results.is_empty() { HashMap::new() } else { hashgrid(results.get(0).clone()) };
+  for w in words.iter() {
+    out.push_str(format!("Showing only \"{}\":\n", *w));
+    out.push_str(format!("{}\n", drawn_for(opts, plan, results.flattenWord(*w))));
+    out.push_str(format!("path entropy: {:.3f} bits\n", path_entropy(results.as_slice(), *w, opts)));
+    match (waypoints.find(&ALPHABET.normalize(w.char_at(0))), waypoints.find(&ALPHABET.normalize(w.char_at_reverse(w.len())))) {
+      (Some(&start), Some(&end)) => {
+        let path = WordPath::new(results.get(0), w.as_slice(), start, end, opts);
+        out.push_str(format!("first result: {} cells, {} within a step of them\n", path.cells.len(), path.neighborhood));
+      }
+      _ => { }
+    }
+  }
+  out.into_owned()
+}
+
+// Picks the word order for each iteration and keeps track of what each one
+// turned up.  The first iteration uses the given order and the next n-1
+// rotate it, so every word is searched first once; after that the order is
+// walked with strides coprime to n, which moves every word somewhere new
+// rather than repeating a rotation.  An exhaustive search finds the same
+// complete solutions in any order, so extra iterations only pay off when a
+// word can't be placed or the search is cut short.  What's been seen is kept
+// as a hash of each canonical grid, so a long run holds no grids of its own.
+struct IterationPlan {
+  words: uint,
+  iteration: uint,
+  seen: HashSet<u64>,
+  yields: Vec<uint>,
+  // --per-iteration-cap
+  cap: Option<uint>
+}
+
+impl IterationPlan {
+  fn new(words: uint) -> IterationPlan {
+    IterationPlan { words: words, iteration: 0, seen: HashSet::new(), yields: Vec::new(), cap: None }
+  }
+
+  fn next_order(&mut self) -> Vec<uint> {
+    let (n, k) = (self.words, self.iteration);
+    self.iteration += 1;
+    if n == 0 {
+      return Vec::new();
+    }
+    let strides: Vec<uint> = range(2, n).filter(|s| s.gcd(&n) == 1).collect();
+    if k < n || strides.is_empty() {
+      return Vec::from_fn(n, |i| (i + k) % n);
+    }
+    let round = k - n;
+    let stride = *strides.get(round % strides.len());
+    let offset = (round / strides.len()) % n;
+    Vec::from_fn(n, |i| (i * stride + offset) % n)
+  }
+
+  // after this many, next_order starts handing out orders it already has
+  fn distinct_orders(&self) -> uint {
+    let n = self.words;
+    if n == 0 {
+      return 1;
+    }
+    n * (1 + range(2, n).filter(|s| s.gcd(&n) == 1).count())
+  }
+
+  // whether each of these results is one no earlier iteration had found
+  fn record<'a>(&mut self, results: &[CrosswordGrid<'a>]) -> Vec<bool> {
+    let fresh: Vec<bool> = results.iter().map(|grid| self.seen.insert(std::hash::hash(&canonical(grid)))).collect();
+    self.yields.push(fresh.iter().filter(|&&f| f).count());
+    fresh
+  }
+
+  // Keeps only the first `cap` of an iteration's results, if there's a cap,
+  // and records the ones kept.  Returns whether any were cut, and which of
+  // the rest are new.
+  fn take<'a>(&mut self, results: &mut Vec<CrosswordGrid<'a>>) -> (bool, Vec<bool>) {
+    let capped = match self.cap {
+      Some(k) if results.len() > k => {
+        results.truncate(k);
+        true
+      }
+      _ => false
+    };
+    (capped, self.record(results.as_slice()))
+  }
+}
+
+// flags understood by every mode that runs the search
+fn search_flags() -> ~[OptGroup] {
+  ~[
+    optopt("a", "adjacency", "moves a word can make: 4 (default), 8, knight, hex, or dx,dy;dx,dy;...", "MOVES"),
+    optflag("", "no-bound", "don't prune paths that can't reach their endpoint in time"),
+    optflag("v", "verbose", "after each word, show how much slack the next few have"),
+    optflag("", "toward-dest", "try each word's next step nearest its last waypoint first"),
+    optopt("", "order", "order of the results: found (default), score or canonical", "ORDER"),
+    optopt("", "beam", "keep at most this many grids after each word", "N"),
+    optopt("", "max-nodes", "stop searching after visiting this many cells", "N"),
+    optopt("", "max-depth", "give up if the search gets more than N deep, counting words placed and letters into the current one", "N"),
+    optopt("", "timeout", "stop searching after this many seconds", "SECONDS"),
+    optopt("", "word-timeout", "move on from any one word after this many seconds", "SECONDS"),
+    optopt("", "roi-border", "keep each word within N cells of the box around its endpoints", "N"),
+    optopt("", "within", "keep every word inside the box with these corners", "X,Y;X,Y"),
+    optopt("", "max-word-len", "skip words in the words file longer than this (default 64)", "N"),
+    optopt("", "overlay", "read blocks, capacities and regions from this file", "FILE"),
+    optflag("", "prefer-straight", "put the results whose words turn least first"),
+    optflag("", "symmetry", "search one side of a mirror-symmetric grid and reflect what it finds"),
+    optflag("", "isolate-anchors", "don't let words pass next to other words' waypoints"),
+    optflag("", "prefilled", "lowercase letters in the grid are word cells already drawn in, not waypoints"),
+    optflag("", "paranoid", "double-check every placement the search makes"),
+    optmulti("", "fix-path", "put a word exactly along these cells and search only the rest", "WORD=x,y;x,y;..."),
+    optmulti("", "optional", "a word the results don't have to use", "WORD"),
+    optflag("", "through-waypoints", "let a word pass through another word's waypoint when it needs that letter there"),
+    optflag("", "consistent-variants", "make every result use the same spelling of a word given as spelling/spelling"),
+    optopt("", "dedup", "drop results that are the same as an earlier one: none (default), shape, or paths", "MODE"),
+    optopt("", "tie-break", "what to look at first among results with the same score: fill, crossings, box, canonical (default in that order)", "KEYS")
+  ]
+}
+
+fn solve_options(matches: &Matches) -> SolveOptions {
+  match parse_solve_options(matches) {
+    Ok(opts) => opts,
+    Err(e) => fail!("{}", e)
+  }
+}
+
+fn parse_solve_options(matches: &Matches) -> Result<SolveOptions, ~str> {
+  let mut opts = match matches.opt_str("a") {
+    // every hex move is one of the eight ways, so anything that only needs
+    // to know roughly how words move, like the step bound, can go by those;
+    // adjacency itself goes through steps_from
+    Some(ref spec) if spec.as_slice() == "hex" => {
+      let mut opts = SolveOptions::with_offsets(Vec::from_slice(EIGHT_WAYS.as_slice()));
+      opts.topology = Hex;
+      opts
+    }
+    Some(spec) => match parse_offsets(spec) {
+      Ok(offsets) => SolveOptions::with_offsets(offsets),
+      Err(e) => return Err(format!("--adjacency: {}", e))
+    },
+    None => SolveOptions::new()
+  };
+  if matches.opt_present("no-bound") {
+    opts.bound = None;
+  }
+  opts.prefer_straight = matches.opt_present("prefer-straight");
+  opts.symmetry = matches.opt_present("symmetry");
+  opts.isolate_anchors = matches.opt_present("isolate-anchors");
+  opts.prefilled = matches.opt_present("prefilled");
+  opts.paranoid = matches.opt_present("paranoid");
+  opts.through_waypoints = matches.opt_present("through-waypoints");
+  opts.consistent_variants = matches.opt_present("consistent-variants");
+  opts.verbose = matches.opt_present("verbose");
+  opts.toward_dest = matches.opt_present("toward-dest");
+  for spec in matches.opt_strs("fix-path").iter() {
+    match parse_fixed_path(*spec) {
+      Ok(fixed) => opts.fixed_paths.push(fixed),
+      Err(e) => return Err(format!("--fix-path: {}", e))
+    }
+  }
+  opts.optional = matches.opt_strs("optional").iter().map(|w| ALPHABET.normalize_str(*w)).collect();
+  opts.beam = try!(count_flag(matches, "beam"));
+  opts.max_nodes = try!(count_flag(matches, "max-nodes"));
+  opts.max_depth = try!(count_flag(matches, "max-depth"));
+  opts.roi_border = try!(count_flag(matches, "roi-border"));
+  opts.max_word_len = try!(count_flag(matches, "max-word-len")).unwrap_or(DEFAULT_MAX_WORD_LEN);
+  opts.within = match matches.opt_str("within") {
+    Some(spec) => Some(try!(parse_box(spec).map_err(|e| format!("--within: {}", e)))),
+    None => None
+  };
+  opts.timeout = try!(seconds_flag(matches, "timeout"));
+  opts.word_timeout = try!(seconds_flag(matches, "word-timeout"));
+  opts.ordering = match matches.opt_str("order") {
+    None => AsFound,
+    Some(order) => match order.as_slice() {
+      "found" => AsFound,
+      "score" => BestScore,
+      "canonical" => Canonical,
+      _ => return Err(~"--order must be found, score or canonical")
+    }
+  };
+  match matches.opt_str("tie-break") {
+    Some(spec) => opts.tie_break = match parse_tie_break(spec) {
+      Ok(chain) => chain,
+      Err(e) => return Err(format!("--tie-break: {}", e))
+    },
+    None => { }
+  }
+  opts.dedup = match matches.opt_str("dedup") {
+    None => NoDedup,
+    Some(mode) => match mode.as_slice() {
+      "none" => NoDedup,
+      "shape" => ByShape,
+      "paths" => ByWordPaths,
+      _ => return Err(~"--dedup must be none, shape or paths")
+    }
+  };
+  try!(opts.validate());
+  Ok(opts)
+}
+
+// explain [options] grid words: trace one word's path search cell by cell
+fn explain(args: &[~str]) {
+  let mut flags = search_flags();
+  flags.push(optopt("w", "word", "the word to trace", "WORD"));
+  flags.push(optopt("o", "output", "file to write the trace to (default explain-WORD.txt, - for stdout)", "FILE"));
+  flags.push(optopt("n", "limit", "stop recording after this many nodes (default 10000)", "N"));
+  flags.push(optflag("p", "partial", "trace on the first grid left after placing the words searched before it"));
+  let matches = match getopts(args, flags) {
+    Ok(m) => m,
+    Err(f) => fail!(f.to_err_msg())
+  };
+  if matches.free.len() != 2 {
+    fail!("usage: explain --word WORD [--output FILE] [--limit N] [--partial] grid words");
+  }
+  let word = match matches.opt_str("w") {
+    Some(w) => ALPHABET.normalize_str(w),
+    None => fail!("explain needs --word")
+  };
+  let limit = match matches.opt_str("n") {
+    Some(n) => from_str::<uint>(n).expect("--limit must be a number"),
+    None => 10000
+  };
+  let output = matches.opt_str("o").unwrap_or(format!("explain-{}.txt", word));
+  let opts = solve_options(&matches);
+  let mut diag = Diagnostics::new();
+
+  let blankgrid = *readgrid(matches.free[0], opts.prefilled, &mut diag);
+  let mut words = readwords(matches.free[1], opts.max_word_len, &mut diag);
+  words.sort_by(|a,b| a.len().cmp(&b.len()));
+  let names: Vec<&str> = words.iter().map(|w| w.as_slice()).collect();
+  let endpoints = match build_endpoint_map(names.as_slice(), &hashgrid(blankgrid.clone())) {
+    Ok(endpoints) => endpoints,
+    Err(e) => fail!("{}", e)
+  };
+  let (start, end) = match endpoints.get(word) {
+    Some(ends) => ends,
+    None => fail!("\"{}\" isn't in the word list", word)
+  };
+
+  let grid = if matches.opt_present("p") {
+    let before: Vec<&~str> = words.iter().take_while(|w| **w != word).collect();
+    if before.len() == words.len() {
+      fail!("\"{}\" isn't in the word list", word);
+    }
+    let paths: Vec<(Point, Point)> = before.iter().map(|w| endpoints.get(**w).unwrap()).collect();
+    let worditer = before.iter().map(|x| x.as_slice());
+    let wordpts: Vec<(&str, &(Point, Point))> = worditer.zip(paths.iter()).collect();
+    let partial = solve(&opts, &mut diag, &blankgrid, wordpts.as_slice());
+    partial.solutions.get(0).clone()
+  } else {
+    blankgrid.clone()
+  };
+
+  let mut search = Search::new(&opts, TreeTrace::new(limit));
+  let mut found: Vec<CrosswordGrid> = Vec::new();
+  allpaths2(&mut search, &grid, word.as_slice(), start, end, word.as_slice(), &mut found);
+  let trace = search.trace;
+
+  let mut report = StrBuf::new();
+  report.push_str(format!("\"{}\" from ({},{}) to ({},{}) on {}\n", word, start.x, start.y, end.x, end.y, tile_summary(&grid)));
+  report.push_str(trace.out.as_slice());
+  if trace.truncated() {
+    report.push_str(format!("... stopped recording after {} of {} nodes\n", limit, trace.nodes));
+  }
+  report.push_str(format!("{} placements\n", trace.placements));
+  if output.as_slice() == "-" {
+    print!("{}", report);
+  } else {
+    let path = Path::new(output.as_slice());
+    File::create(&path).write_str(report.as_slice()).unwrap();
+    println!("wrote {} nodes ({} placements) to {}", trace.nodes, trace.placements, output);
+  }
+  print!("{}", diag.summary());
+}
+
+// Solves one manifest entry, returning its report, its count line, the word
+// it got stuck on if any, and how many cells it searched.
+fn solve_entry(grid_file: ~str, words_file: ~str, args: ~[~str]) -> Result<(~str, ~str, Option<~str>, uint), ~str> {
+  let mut flags = search_flags();
+  flags.push_all(output_flags());
+  let matches = match getopts(args, flags) {
+    Ok(m) => m,
+    Err(f) => return Err(f.to_err_msg())
+  };
+  let mut opts = try!(parse_solve_options(&matches));
+  let plan = try!(parse_output_plan(&matches));
+  let mut diag = Diagnostics::new();
+  let overlay_file = matches.opt_str("overlay");
+  let (blankgrid, overlay, words) = try!(load(grid_file, overlay_file.as_ref().map(|f| f.as_slice()), words_file, &opts, &mut diag).map_err(|e| e.to_str()));
+  let (words, chains) = split_chains(words);
+  let (words, variants) = split_variants(words);
+  opts.chains = chains;
+  opts.variants = variants;
+  opts.capacity = overlay.map(|o| o.capacity);
+  let gridmap: HashMap<char, Point> = hashgrid(blankgrid.clone());
+  let paths = try!(word_endpoints(&gridmap, words.as_slice()).map_err(|e| e.to_str()));
+  let worditer = words.iter().map(|x| x.as_slice());
+  let wordpts: Vec<(&str, &(Point, Point))> = worditer.zip(paths.iter()).collect();
+  let result = solve(&opts, &mut diag, &blankgrid, wordpts.as_slice());
+  match result.error(&opts) {
+    Some(e) => return Err(e.to_str()),
+    None => { }
+  }
+  let mut report = StrBuf::new();
+  report.push_str(format!("{}\n", result.count_str()));
+  report.push_str(render_results(&blankgrid, &result.solutions, words.as_slice(), &opts, &plan, [], &mut diag));
+  report.push_str(diag.summary());
+  let nodes = result.word_stats.iter().fold(0, |n, stats| n + stats.nodes);
+  Ok((report.into_owned(), result.count_str(), result.stuck_on.map(|w| w.to_owned()), nodes))
+}
+
+// The same grid and words files byte for byte, with the same options, get
+// the same key.  None if either file can't be read; the entry's own task will
+// report that.
+fn entry_key(grid_file: &Path, words_file: &Path, args: &[~str]) -> Option<~str> {
+  let grid = match File::open(grid_file).read_to_end() { Ok(bytes) => bytes, Err(_) => return None };
+  let words = match File::open(words_file).read_to_end() { Ok(bytes) => bytes, Err(_) => return None };
+  Some(format!("{:08x}.{:08x} {}", fnv1a(grid.as_slice()), fnv1a(words.as_slice()), args.connect(" ")))
+}
+
+// What a batch run did: a line for each puzzle, then the tally, how many of
+// the puzzles solved, and how many cells were searched solving them.
+struct BatchSummary {
+  lines: Vec<~str>,
+  solved: uint,
+  nodes: uint
+}
+
+// Each manifest line names a grid file and a words file, relative to the
+// manifest, followed by any solve flags for that puzzle alone and optionally
+// -o FILE for its report (GRID.out by default).  Blank lines and lines
+// starting with # are skipped.  Every puzzle runs in its own task so one bad
+// file doesn't take the rest of the batch with it, and a puzzle that's already
+// been solved with the same options has its report copied, not solved again.
+fn batch(manifest: &str) -> BatchSummary {
+  let dir = Path::new(manifest).dir_path();
+  let mut summary = BatchSummary { lines: Vec::new(), solved: 0, nodes: 0 };
+  let mut cache: HashMap<~str, (~str, ~str, ~str, Option<~str>)> = HashMap::new();
+  let lines = readlines(manifest);
+  let entries: Vec<&~str> = lines.iter().filter(|l| !l.trim().is_empty() && !l.trim().starts_with("#")).collect();
+  for line in entries.iter() {
+    let tokens: ~[~str] = line.words().map(|w| w.to_owned()).collect();
+    if tokens.len() < 2 {
+      let skipped = format!("{}: skipping \"{}\": needs a grid and a words file", manifest, **line);
+      println!("{}", skipped);
+      summary.lines.push(skipped);
+      continue;
+    }
+    let mut args: ~[~str] = ~[];
+    let mut out_file = None;
+    let mut i = 2;
+    while i < tokens.len() {
+      if tokens[i].as_slice() == "-o" && i + 1 < tokens.len() {
+        out_file = Some(tokens[i + 1].clone());
+        i += 2;
+      } else {
+        args.push(tokens[i].clone());
+        i += 1;
+      }
+    }
+    let grid_file = dir.join(tokens[0].as_slice());
+    let words_file = dir.join(tokens[1].as_slice());
+    let out_file = match out_file {
+      Some(f) => dir.join(f.as_slice()),
+      None => grid_file.with_extension("out")
+    };
+    let name = tokens[0].clone();
+    let key = entry_key(&grid_file, &words_file, args);
+    let cached = key.as_ref().and_then(|k| cache.find(k)).map(|entry| entry.clone());
+    let outcome = match cached {
+      Some((first, report, count, stuck)) => {
+        println!("{}: same puzzle as {}, reusing its results", name, first);
+        Ok(Ok((report, count, stuck, 0)))
+      }
+      None => {
+        let (g, w) = (grid_file.as_str().unwrap().to_owned(), words_file.as_str().unwrap().to_owned());
+        std::task::try(proc() { solve_entry(g, w, args) })
+      }
+    };
+    let outcome = match outcome {
+      Ok(Ok((report, count, stuck, nodes))) => match File::create(&out_file).write_str(report.as_slice()) {
+        Ok(()) => {
+          match key {
+            Some(k) => { cache.find_or_insert(k, (name.clone(), report, count.clone(), stuck.clone())); }
+            None => { }
+          }
+          summary.nodes += nodes;
+          Ok(Ok((count, stuck)))
+        }
+        Err(e) => Ok(Err(format!("{}: {}", out_file.display(), e)))
+      },
+      Ok(Err(e)) => Ok(Err(e)),
+      Err(e) => Err(e)
+    };
+    let line = match outcome {
+      Ok(Ok((count, None))) => {
+        summary.solved += 1;
+        format!("{}: solved, {}", name, count)
+      }
+      Ok(Ok((count, Some(word)))) => format!("{}: stuck on \"{}\", {} placed before it", name, word, count),
+      Ok(Err(e)) => format!("{}: {}", name, e),
+      Err(_) => format!("{}: failed", name)
+    };
+    println!("{}", line);
+    summary.lines.push(line);
+  }
+  let tally = format!("solved {} of {} puzzles", summary.solved, entries.len());
+  println!("{}", tally);
+  summary.lines.push(tally);
+  summary
+}
+
+// learn --output FILE grid words [grid words ...]: build a word-ordering
+// model from some puzzles, for --word-model
+fn learn(args: &[~str]) {
+  let flags = ~[optopt("o", "output", "file to write the model to", "FILE")];
+  let matches = match getopts(args, flags) {
+    Ok(m) => m,
+    Err(f) => fail!(f.to_err_msg())
+  };
+  let output = matches.opt_str("o").expect("learn needs --output");
+  if matches.free.is_empty() || matches.free.len() % 2 != 0 {
+    fail!("usage: learn --output FILE grid words [grid words ...]");
+  }
+  let opts = SolveOptions::new();
+  let mut diag = Diagnostics::new();
+  let mut puzzles = Vec::new();
+  for pair in matches.free.chunks(2) {
+    match load(pair[0], None, pair[1], &opts, &mut diag) {
+      Ok((grid, _, words)) => puzzles.push((grid, words)),
+      Err(e) => println!("skipping {}", e)
+    }
+  }
+  let model = collect_placement_statistics(puzzles.as_slice());
+  let path = Path::new(output.as_slice());
+  File::create(&path).write_str(model.to_json_str()).unwrap();
+  println!("learned {} buckets from {} puzzles", model.entries.len(), puzzles.len());
+  print!("{}", diag.summary());
+}
+
+// render [--only ID] FILE: print results saved with --save as letter grids.
+// Any unambiguous prefix of an id will do.
+fn render(args: &[~str]) {
+  let flags = ~[optopt("", "only", "print only the result with this id", "ID")];
+  let matches = match getopts(args, flags) {
+    Ok(m) => m,
+    Err(f) => fail!(f.to_err_msg())
+  };
+  if matches.free.len() != 1 {
+    fail!("usage: render [--only ID] FILE");
+  }
+  let only = matches.opt_str("only");
+  let lines = readlines(matches.free[0]);
+  let mut blocks: Vec<(~str, Vec<~str>)> = Vec::new();
+  for line in lines.iter() {
+    if line.starts_with("solution ") {
+      blocks.push((line.slice_from("solution ".len()).trim().to_owned(), Vec::new()));
+    } else if !line.trim().is_empty() {
+      // letter:word cells become the letter, and blanks go back to spaces
+      let row: ~str = line.words().map(|cell| match cell {
+        "." => ' ',
+        _ => cell.char_at(0)
+      }).collect();
+      match blocks.mut_last() {
+        Some(&(_, ref mut rows)) => rows.push(row),
+        None => { }
+      }
+    }
+  }
+  let chosen: Vec<&(~str, Vec<~str>)> = blocks.iter().filter(|&&(ref id, _)| match only {
+    Some(ref prefix) => id.starts_with(*prefix),
+    None => true
+  }).collect();
+  match only {
+    Some(ref prefix) if chosen.is_empty() => fail!("no result with id {}", *prefix),
+    Some(ref prefix) if chosen.len() > 1 => fail!("{} results have ids starting with {}", chosen.len(), *prefix),
+    _ => { }
+  }
+  for &&(ref id, ref rows) in chosen.iter() {
+    println!("solution {}\n{}\n", *id, rows.connect("\n"));
+  }
+}
+
+// Proof that a puzzle has the solution it says, checkable without solving:
+// each word's route as moves from its first waypoint, in the same dx,dy;...
+// form as --adjacency.  The hashes run in a chain from the puzzle's own (its
+// blank grid and word list), through every word and its moves in turn, to
+// the one at the end, so editing any of them shows.
+#[deriving(Encodable, Decodable, Clone)]
+struct Certificate {
+  puzzle: ~str,
+  solution: ~str,
+  words: Vec<CertifiedWord>,
+  hash: ~str
+}
+
+#[deriving(Encodable, Decodable, Clone)]
+struct CertifiedWord {
+  word: ~str,
+  moves: ~str,
+  hash: ~str
+}
+
+fn puzzle_hash<'a>(grid: &CrosswordGrid<'a>, words: &[~str]) -> ~str {
+  format!("{:08x}", fnv1a(format!("{}\n{}", canonical_text(grid), words.connect("\n")).as_bytes()))
+}
+
+#[inline] fn chain_hash(previous: &str, word: &str, moves: &str) -> ~str {
+  format!("{:08x}", fnv1a(format!("{}\n{}\n{}", previous, word, moves).as_bytes()))
+}
+
+fn encode_moves(cells: &[Point]) -> ~str {
+  let moves: Vec<~str> = cells.windows(2).map(|w| format!("{},{}", w[1].x - w[0].x, w[1].y - w[0].y)).collect();
+  moves.connect(";")
+}
+
+fn certify<'a>(blank: &CrosswordGrid<'a>, solution: &CrosswordGrid<'a>, words: &[~str], paths: &[(Point, Point)], opts: &SolveOptions) -> Certificate {
+  let puzzle = puzzle_hash(blank, words);
+  let mut hash = puzzle.clone();
+  let mut certified = Vec::new();
+  for (word, &(start, end)) in words.iter().zip(paths.iter()) {
+    let moves = encode_moves(WordPath::new(solution, word.as_slice(), start, end, opts).cells.as_slice());
+    hash = chain_hash(hash, *word, moves);
+    certified.push(CertifiedWord { word: word.clone(), moves: moves, hash: hash.clone() });
+  }
+  Certificate { puzzle: puzzle, solution: solution_id(solution), words: certified, hash: hash }
+}
+
+// Replays a certificate on the blank grid, returning the solution's id if
+// every hash matches and every word goes down exactly as it says.
+fn verify_certificate<'a>(cert: &Certificate, blank: &CrosswordGrid<'a>, words: &'a [~str], paths: &[(Point, Point)], opts: &SolveOptions) -> Result<~str, ~str> {
+  if cert.puzzle != puzzle_hash(blank, words) {
+    return Err(~"the certificate is for a different puzzle");
+  }
+  if cert.words.len() != words.len() {
+    return Err(format!("the certificate has {} words and the puzzle {}", cert.words.len(), words.len()));
+  }
+  let mut grid = blank.clone();
+  let mut hash = cert.puzzle.clone();
+  for ((entry, word), &(start, end)) in cert.words.iter().zip(words.iter()).zip(paths.iter()) {
+    if entry.word != *word {
+      return Err(format!("the certificate has \"{}\" where the puzzle has \"{}\"", entry.word, *word));
+    }
+    hash = chain_hash(hash, entry.word, entry.moves);
+    if entry.hash != hash {
+      return Err(format!("\"{}\" has been changed since the certificate was made", *word));
+    }
+    let moves = try!(parse_offsets(entry.moves).map_err(|e| format!("\"{}\": {}", *word, e)));
+    if moves.len() != word.char_len() - 1 {
+      return Err(format!("\"{}\" has {} moves, not {}", *word, moves.len(), word.char_len() - 1));
+    }
+    let mut cells = vec!(start);
+    for &(x, y) in moves.iter() {
+      let last = *cells.last().unwrap();
+      if !steps_from(opts, last).contains(&last.offset(x, y)) {
+        return Err(format!("\"{}\" makes a move of {},{} from {},{}, which isn't allowed", *word, x, y, last.x, last.y));
+      }
+      cells.push(last.offset(x, y));
+    }
+    if *cells.last().unwrap() != end {
+      return Err(format!("\"{}\" doesn't end on its last waypoint", *word));
+    }
+    grid = match place_along(opts, &grid, word.as_slice(), cells.slice(1, cells.len() - 1)) {
+      Some(next) => next,
+      None => return Err(format!("\"{}\" doesn't fit where the certificate puts it", *word))
+    };
+  }
+  if cert.hash != hash {
+    return Err(~"the certificate's final hash doesn't match its words");
+  }
+  let id = solution_id(&grid);
+  if cert.solution != id {
+    return Err(format!("the words make solution {}, not {}", id, cert.solution));
+  }
+  Ok(id)
+}
+
+// A second, deliberately naive solver, for checking the real one: list every
+// route of every word on the empty grid on its own, then try every
+// combination of them.  It shares none of the search's pruning, ordering or
+// bookkeeping, only the rules, so it's only any use on tiny puzzles and with
+// none of the options that change those rules.
+fn brute_force<'a>(grid: &CrosswordGrid<'a>, wordpts: &[(&'a str, &(Point, Point))], opts: &SolveOptions) -> Vec<~str> {
+  let routes: Vec<Vec<Vec<Point>>> = wordpts.iter().map(|&(word, &(start, end))| {
+    let mut found = Vec::new();
+    let mut path = Vec::new();
+    brute_routes(grid, opts, word.char_len() - 2, start, end, &mut path, &mut found);
+    found
+  }).collect();
+  let mut out = Vec::new();
+  brute_combine(grid, wordpts, routes.as_slice(), &mut out);
+  out.sort();
+  out
+}
+
+// every way to go `left` blank cells from `at` and then step onto `end`,
+// never using a cell twice
+fn brute_routes<'a>(grid: &CrosswordGrid<'a>, opts: &SolveOptions, left: uint, at: Point, end: Point, path: &mut Vec<Point>, found: &mut Vec<Vec<Point>>) {
+  let next: Vec<Point> = steps_from(opts, at).move_iter().filter(|&p| grid.is_valid(p)).collect();
+  if left == 0 {
+    if next.contains(&end) {
+      found.push(path.clone());
+    }
+    return;
+  }
+  for &n in next.iter() {
+    if *grid.get_ref(n).unwrap() == NoWords && !path.contains(&n) {
+      path.push(n);
+      brute_routes(grid, opts, left - 1, n, end, path, found);
+      path.pop();
+    }
+  }
+}
+
+fn brute_combine<'a>(grid: &CrosswordGrid<'a>, wordpts: &[(&'a str, &(Point, Point))], routes: &[Vec<Vec<Point>>], out: &mut Vec<~str>) {
+  if wordpts.is_empty() {
+    out.push(canonical_text(grid));
+    return;
+  }
+  let (word, _) = wordpts[0];
+  let letters: Vec<char> = word.chars().collect();
+  'routes: for route in routes[0].iter() {
+    let mut next = grid.clone();
+    for (k, &p) in route.iter().enumerate() {
+      let letter = *letters.get(k + 1);
+      let tile = match *next.get_ref(p).unwrap() {
+        NoWords => OneWord(letter, word),
+        OneWord(c, other) if ALPHABET.same(c, letter) => TwoWords(ALPHABET.crossed(c, letter), other, word),
+        _ => continue 'routes
+      };
+      next.set(p, tile);
+    }
+    brute_combine(&next, wordpts.slice_from(1), routes.slice_from(1), out);
+  }
+}
+
+// Whatever the options ask for that brute_force doesn't know about.
+fn brute_force_unsupported(opts: &SolveOptions) -> Vec<&'static str> {
+  let mut unsupported = Vec::new();
+  let flags = [
+    ("--overlay", opts.capacity.is_some()), ("--prefilled", opts.prefilled), ("--through-waypoints", opts.through_waypoints),
+    ("--isolate-anchors", opts.isolate_anchors), ("--symmetry", opts.symmetry), ("--fix-path", !opts.fixed_paths.is_empty()),
+    ("--optional", !opts.optional.is_empty()), ("alternate spellings", !opts.variants.is_empty()), ("--dedup", opts.dedup != NoDedup),
+    ("--beam", opts.beam.is_some()), ("--max-nodes", opts.max_nodes.is_some()), ("--max-depth", opts.max_depth.is_some()),
+    ("--timeout", opts.timeout.is_some()), ("--word-timeout", opts.word_timeout.is_some()), ("--roi-border", opts.roi_border.is_some()), ("--within", opts.within.is_some())
+  ];
+  for &(name, set) in flags.iter() {
+    if set {
+      unsupported.push(name);
+    }
+  }
+  unsupported
+}
+
+// crosscheck [options] grid words: solve a small puzzle both ways and make
+// sure they agree on every result.  Exits with status 1 if they don't.
+fn crosscheck(args: &[~str]) {
+  let matches = match getopts(args, search_flags()) {
+    Ok(m) => m,
+    Err(f) => fail!(f.to_err_msg())
+  };
+  if matches.free.len() != 2 {
+    fail!("usage: crosscheck [options] grid words");
+  }
+  let mut opts = solve_options(&matches);
+  let mut diag = Diagnostics::new();
+  let overlay_file = matches.opt_str("overlay");
+  let (blank, overlay, words) = match load(matches.free[0], overlay_file.as_ref().map(|f| f.as_slice()), matches.free[1], &opts, &mut diag) {
+    Ok(puzzle) => puzzle,
+    Err(e) => fail!("{}", e)
+  };
+  let (words, chains) = split_chains(words);
+  let (words, variants) = split_variants(words);
+  opts.chains = chains;
+  opts.variants = variants;
+  opts.capacity = overlay.map(|o| o.capacity);
+  let unsupported = brute_force_unsupported(&opts);
+  if !unsupported.is_empty() {
+    fail!("the brute force can't check {}", unsupported.connect(", "));
+  }
+  if words.iter().any(|w| w.contains_char(WILDCARD)) {
+    fail!("the brute force can't check blind entries");
+  }
+  let paths = match word_endpoints(&hashgrid(blank.clone()), words.as_slice()) {
+    Ok(paths) => paths,
+    Err(e) => fail!("{}", e)
+  };
+  let wordpts: Vec<(&str, &(Point, Point))> = words.iter().map(|w| w.as_slice()).zip(paths.iter()).collect();
+  let result = solve(&opts, &mut diag, &blank, wordpts.as_slice());
+  let mut searched: Vec<~str> = result.solutions.iter().map(canonical_text).collect();
+  searched.sort();
+  let brute = brute_force(&blank, wordpts.as_slice(), &opts);
+  print!("{}", diag.summary());
+  if searched == brute {
+    println!("both find the same {} results", brute.len());
+    return;
+  }
+  println!("the search finds {} results and the brute force {}", searched.len(), brute.len());
+  for text in searched.iter().filter(|t| !brute.contains(*t)).take(5) {
+    println!("only the search finds:\n{}\n", *text);
+  }
+  for text in brute.iter().filter(|t| !searched.contains(*t)).take(5) {
+    println!("only the brute force finds:\n{}\n", *text);
+  }
+  std::os::set_exit_status(1);
+}
+
+fn check(args: &[~str]) {
+  let mut flags = search_flags();
+  flags.push(optopt("", "certificate", "the certificate to check", "FILE"));
+  let matches = match getopts(args, flags) {
+    Ok(m) => m,
+    Err(f) => fail!(f.to_err_msg())
+  };
+  if matches.free.len() != 2 {
+    fail!("usage: check --certificate FILE grid words");
+  }
+  let file = matches.opt_str("certificate").expect("check needs --certificate");
+  let cert: Certificate = match json::from_str(readlines(file).connect("\n")) {
+    Ok(parsed) => match Decodable::decode(&mut json::Decoder::new(parsed)) {
+      Ok(cert) => cert,
+      Err(e) => fail!("{}: {:?}", file, e)
+    },
+    Err(e) => fail!("{}: {:?}", file, e)
+  };
+  let mut opts = solve_options(&matches);
+  let mut diag = Diagnostics::new();
+  let overlay_file = matches.opt_str("overlay");
+  let (blank, overlay, words) = match load(matches.free[0], overlay_file.as_ref().map(|f| f.as_slice()), matches.free[1], &opts, &mut diag) {
+    Ok(puzzle) => puzzle,
+    Err(e) => fail!("{}", e)
+  };
+  let (words, chains) = split_chains(words);
+  let (words, variants) = split_variants(words);
+  opts.chains = chains;
+  opts.variants = variants;
+  opts.capacity = overlay.map(|o| o.capacity);
+  let paths = match word_endpoints(&hashgrid(blank.clone()), words.as_slice()) {
+    Ok(paths) => paths,
+    Err(e) => fail!("{}", e)
+  };
+  match verify_certificate(&cert, &blank, words.as_slice(), paths.as_slice(), &opts) {
+    Ok(id) => println!("certificate is good: solution {}", id),
+    Err(e) => {
+      println!("certificate is bad: {}", e);
+      std::os::set_exit_status(1);
+    }
+  }
+}
+
+// What --session keeps between runs: the words placed so far, in the order
+// they went on, and the grids that survived each of them as canonical text.
+// A new word goes on the grids the last one left, and taking one back off
+// returns to the grids from before it, so an edited word list is only solved
+// from where it first differs.  The blank grid's hash and the command line
+// tell whether any of it still applies.
+#[deriving(Encodable, Decodable, Clone)]
+struct Session {
+  puzzle: ~str,
+  command: ~str,
+  words: Vec<~str>,
+  // levels[i] holds the results with the first i + 1 words placed
+  levels: Vec<Vec<~str>>
+}
+
+// One line of a --stats-db file: a run of a puzzle, what it was run with,
+// and how it went.
+#[deriving(Encodable, Decodable, Clone)]
+struct RunRecord {
+  date: ~str,
+  puzzle: ~str,
+  // a hash of the command line, leaving out --stats-db itself
+  config: ~str,
+  seconds: f64,
+  solutions: uint,
+  completeness: ~str,
+  words: Vec<WordRecord>
+}
+
+#[deriving(Encodable, Decodable, Clone)]
+struct WordRecord {
+  word: ~str,
+  nodes: uint,
+  grids_in: uint,
+  grids_out: uint
+}
+
+impl RunRecord {
+  fn nodes(&self) -> uint {
+    self.words.iter().fold(0, |n, w| n + w.nodes)
+  }
+}
+
+fn run_record<'a, 'g>(blank: &CrosswordGrid<'g>, words: &[~str], args: &[~str], seconds: f64, result: &SolveResult<'a>) -> RunRecord {
+  let mut config = Vec::new();
+  let mut skip = false;
+  for arg in args.iter() {
+    if skip {
+      skip = false;
+    } else if arg.as_slice() == "--stats-db" {
+      skip = true;
+    } else if !arg.starts_with("--stats-db=") {
+      config.push(arg.clone());
+    }
+  }
+  RunRecord {
+    date: time::now_utc().rfc3339(),
+    puzzle: puzzle_hash(blank, words),
+    config: format!("{:08x}", fnv1a(config.connect(" ").as_bytes())),
+    seconds: seconds,
+    solutions: result.solutions.len(),
+    completeness: result.completeness.to_str(),
+    words: result.word_stats.iter().map(|w| WordRecord { word: w.word.to_owned(), nodes: w.nodes, grids_in: w.grids_in, grids_out: w.grids_out }).collect()
+  }
+}
+
+// Adds a record to the end of a --stats-db file in a single write, so runs
+// going at once each get their own whole line.
+fn append_record(file: &str, record: &RunRecord) {
+  let line = format!("{}\n", json::Encoder::str_encode(record));
+  match File::open_mode(&Path::new(file), std::io::Append, std::io::Write) {
+    Ok(mut f) => f.write(line.as_bytes()).unwrap(),
+    Err(e) => fail!("{}: {}", file, e)
+  }
+}
+
+// "+1.25", "-3": how a value moved from the row before, to two places or
+// none
+fn delta(now: f64, before: Option<f64>, places: bool) -> ~str {
+  match before {
+    Some(b) if places => format!("{}{:.2f}", if now >= b { "+" } else { "" }, now - b),
+    Some(b) => format!("{}{:.0f}", if now >= b { "+" } else { "" }, now - b),
+    None => ~""
+  }
+}
+
+// stats --db FILE [--puzzle HASH] [--last N]: the last N runs recorded in
+// FILE, or just those of the puzzle whose hash starts with HASH, oldest
+// first, with how much the time and cells visited moved from one to the next.
+// Lines that don't read as records are skipped.
+fn stats_command(args: &[~str]) {
+  let flags = ~[
+    optopt("", "db", "the file --stats-db wrote to", "FILE"),
+    optopt("", "puzzle", "only the runs of the puzzle with this hash", "HASH"),
+    optopt("", "last", "how many runs to show (default 10)", "N")
+  ];
+  let matches = match getopts(args, flags) {
+    Ok(m) => m,
+    Err(f) => fail!(f.to_err_msg())
+  };
+  let file = matches.opt_str("db").expect("usage: stats --db FILE [--puzzle HASH] [--last N]");
+  let last = match count_flag(&matches, "last") {
+    Ok(n) => n.unwrap_or(10),
+    Err(e) => fail!("{}", e)
+  };
+  let puzzle = matches.opt_str("puzzle");
+  let records: Vec<RunRecord> = readlines(file).iter().filter_map(|line| {
+    json::from_str(line.as_slice()).ok().and_then(|parsed| Decodable::decode(&mut json::Decoder::new(parsed)).ok())
+  }).filter(|r: &RunRecord| puzzle.as_ref().map_or(true, |p| r.puzzle.starts_with(*p))).collect();
+  let shown = records.slice_from(records.len() - std::cmp::min(last, records.len()));
+  println!("{:<20} {:<8} {:<8} {:>9} {:>9} {:>10} {:>10} {:>9}  {}", "date", "puzzle", "config", "seconds", "change", "cells", "change", "results", "outcome");
+  let mut before: Option<&RunRecord> = None;
+  for r in shown.iter() {
+    // the previous row only counts for deltas when it ran the same puzzle
+    let previous = match before {
+      Some(b) if b.puzzle == r.puzzle => Some(b),
+      _ => None
+    };
+    println!("{:<20} {:<8} {:<8} {:>9.2f} {:>9} {:>10} {:>10} {:>9}  {}", r.date.slice_to(std::cmp::min(20, r.date.len())), r.puzzle, r.config, r.seconds,
+             delta(r.seconds, previous.map(|b| b.seconds), true), r.nodes(), delta(r.nodes() as f64, previous.map(|b| b.nodes() as f64), false), r.solutions, r.completeness);
+    before = Some(r);
+  }
+}
+
+// canonical_text read back in, with each cell's words found in `words`
+fn parse_canonical<'a>(text: &str, words: &'a [~str]) -> Result<CrosswordGrid<'a>, ~str> {
+  let find = |w: &str| -> Result<&'a str, ~str> {
+    if w.is_empty() {
+      return Ok(PREFILLED);
+    }
+    match words.iter().find(|n| n.as_slice() == w) {
+      Some(n) => Ok(n.as_slice()),
+      None => Err(format!("\"{}\" isn't in the word list", w))
+    }
+  };
+  let rows: Vec<&str> = text.lines().collect();
+  let mut tiles = Vec::new();
+  let mut width = None;
+  for row in rows.iter() {
+    let cells: Vec<&str> = row.words().collect();
+    if width.map_or(false, |w| w != cells.len()) {
+      return Err(~"rows of different widths");
+    }
+    width = Some(cells.len());
+    for &cell in cells.iter() {
+      let c = cell.char_at(0);
+      let tile = match cell.find(':') {
+        _ if cell == "." => NoWords,
+        _ if cell == "#" => Blocked,
+        None if cell.char_len() == 1 => Fixed(ALPHABET.normalize(c)),
+        Some(i) => {
+          let names = cell.slice_from(i + 1);
+          match names.find('+') {
+            Some(j) => TwoWords(c, try!(find(names.slice_to(j))), try!(find(names.slice_from(j + 1)))),
+            None if c.is_uppercase() => Through(ALPHABET.normalize(c), try!(find(names))),
+            None => OneWord(c, try!(find(names)))
+          }
+        }
+        None => return Err(format!("can't read cell \"{}\"", cell))
+      };
+      tiles.push(tile);
+    }
+  }
+  Ok(Grid { width: width.unwrap_or(0) as int, height: rows.len() as int, tiles: tiles })
+}
+
+impl Session {
+  fn new<'g>(blank: &CrosswordGrid<'g>, command: &str) -> Session {
+    Session { puzzle: puzzle_hash(blank, []), command: command.to_owned(), words: Vec::new(), levels: Vec::new() }
+  }
+
+  // the results with every word so far placed
+  fn solutions<'a>(&'a self) -> &'a [~str] {
+    match self.levels.last() {
+      Some(level) => level.as_slice(),
+      None => &[]
+    }
+  }
+
+  // Places `word` on every grid the last word left, and returns how many
+  // grids it's on.  The session doesn't change if the word doesn't fit.
+  fn push_word<'g>(&mut self, opts: &SolveOptions, diag: &mut Diagnostics, blank: &CrosswordGrid<'g>, endpoints: &WordEndpointMap, word: ~str) -> Result<uint, ~str> {
+    let ends = match endpoints.get(word) {
+      Some(ends) => ends,
+      None => return Err(format!("\"{}\" has no waypoints", word))
+    };
+    let mut words = self.words.clone();
+    words.push(word);
+    let level = {
+      let mut progress = SolveResult::start(blank);
+      if !self.levels.is_empty() {
+        progress.solutions = Vec::new();
+        for text in self.solutions().iter() {
+          progress.solutions.push(try!(parse_canonical(*text, words.as_slice())));
+        }
+      }
+      let name = words.last().unwrap().as_slice();
+      let result = add_word(&mut Search::new(opts, NoTrace), diag, progress, [(name, &ends)]);
+      if result.stuck_on.is_some() || result.completeness != Exhaustive {
+        return Err(format!("\"{}\" doesn't fit", name));
+      }
+      let ordered = order_solutions(opts.ordering, opts.tie_break.as_slice(), result.solutions);
+      ordered.iter().map(canonical_text).collect::<Vec<~str>>()
+    };
+    self.words = words;
+    self.levels.push(level);
+    Ok(self.solutions().len())
+  }
+
+  // Takes the last word back off, leaving the grids from before it.
+  fn undo(&mut self) -> Option<~str> {
+    self.levels.pop();
+    self.words.pop()
+  }
+
+  fn save(&self, file: &str) {
+    File::create(&Path::new(file)).write_str(json::Encoder::str_encode(self)).unwrap();
+  }
+
+  fn load(file: &str) -> Result<Session, ~str> {
+    match json::from_str(readlines(file).connect("\n")) {
+      Ok(parsed) => Decodable::decode(&mut json::Decoder::new(parsed)).map_err(|e| format!("{:?}", e)),
+      Err(e) => Err(format!("{:?}", e))
+    }
+  }
+}
+
+// --session: the results for `words`, from the session kept in `file`.
+// Words come back off the end of the session until what's left starts the
+// word list, and the rest go on one at a time, in list order; then the
+// session is saved again.  A session for a different grid or command line
+// starts over.  None if some word doesn't fit, so the puzzle is solved the
+// usual way, which explains why.
+fn session_solutions<'a, 'g>(opts: &SolveOptions, file: &str, command: &str, blank: &CrosswordGrid<'g>, endpoints: &WordEndpointMap, words: &'a [~str], diag: &mut Diagnostics) -> Option<Vec<CrosswordGrid<'a>>> {
+  let mut session = Session::new(blank, command);
+  if Path::new(file).exists() {
+    match Session::load(file) {
+      Ok(saved) => {
+        if saved.puzzle == session.puzzle && saved.command == session.command {
+          session = saved;
+        } else {
+          diag.report(Note, Nowhere, format!("{} was saved for a different grid or command line; starting over", file));
+        }
+      }
+      Err(e) => diag.report(Warning, Nowhere, format!("{}: {}; starting over", file, e))
+    }
+  }
+  while !words.starts_with(session.words.as_slice()) {
+    session.undo();
+  }
+  let kept = session.words.len();
+  if kept > 0 {
+    println!("picked up {} of {} words from {}", kept, words.len(), file);
+  }
+  for word in words.slice_from(kept).iter() {
+    match session.push_word(opts, diag, blank, endpoints, word.clone()) {
+      Ok(_) => { }
+      Err(e) => {
+        diag.report(Note, Nowhere, format!("{}: {}; solving the usual way", file, e));
+        session.save(file);
+        return None;
+      }
+    }
+  }
+  session.save(file);
+  let mut result = SolveResult::start(blank);
+  result.solutions = Vec::new();
+  for text in session.solutions().iter() {
+    match parse_canonical(*text, words) {
+      Ok(grid) => result.solutions.push(grid),
+      Err(e) => { diag.report(Warning, Nowhere, format!("{}: {}; solving the usual way", file, e)); return None; }
+    }
+  }
+  drop_unclaimed(&mut result, diag);
+  Some(result.solutions)
+}
+
+// Scrabble tile values, a to z: a rough measure of how rare a letter is.
+static LETTER_VALUES: [uint, ..26] = [1, 3, 3, 2, 1, 4, 2, 4, 1, 8, 5, 1, 3, 1, 1, 3, 10, 1, 1, 1, 1, 4, 4, 8, 4, 10];
+
+// Longer words and rarer letters are nicer to find in a puzzle.
+fn desirability(word: &str) -> uint {
+  word.chars().fold(0, |accum, c| accum + LETTER_VALUES[(c as uint) - ('a' as uint)])
+}
+
+// suggest --dict FILE --from A --to B [--min-len N] [--max-len N] [--verify] grid:
+// dictionary words that could run from waypoint A to waypoint B
+fn suggest(args: &[~str]) {
+  let mut flags = search_flags();
+  flags.push(optopt("", "dict", "dictionary to draw words from, one per line", "FILE"));
+  flags.push(optopt("", "from", "waypoint the words start on", "LETTER"));
+  flags.push(optopt("", "to", "waypoint the words end on", "LETTER"));
+  flags.push(optopt("", "min-len", "shortest word to suggest (default 2)", "N"));
+  flags.push(optopt("", "max-len", "longest word to suggest", "N"));
+  flags.push(optopt("", "pattern", "only words spelled like this, with '.' for any letter (as printed for a blind entry)", "PATTERN"));
+  flags.push(optflag("", "verify", "only suggest words that have a path on the grid as it stands"));
+  let matches = match getopts(args, flags) {
+    Ok(m) => m,
+    Err(f) => fail!(f.to_err_msg())
+  };
+  if matches.free.len() != 1 {
+    fail!("usage: suggest --dict FILE --from A --to B [--min-len N] [--max-len N] [--pattern P] [--verify] grid");
+  }
+  let dict = matches.opt_str("dict").expect("suggest needs --dict");
+  let letter = |name: &str| match matches.opt_str(name) {
+    Some(ref l) if l.char_len() == 1 => ALPHABET.normalize(l.char_at(0)),
+    _ => fail!("suggest needs --{} and a single letter", name)
+  };
+  let (from, to) = (letter("from"), letter("to"));
+  let min_len = matches.opt_str("min-len").map_or(2, |n| from_str::<uint>(n).expect("--min-len must be a number"));
+  let max_len = matches.opt_str("max-len").map(|n| from_str::<uint>(n).expect("--max-len must be a number"));
+  let pattern: Option<Vec<char>> = matches.opt_str("pattern").map(|p| ALPHABET.normalize_str(p).chars().collect());
+  let spelled = |word: &str| pattern.as_ref().map_or(true, |p| {
+    p.len() == word.char_len() && p.iter().zip(word.chars()).all(|(&a, b)| a == '.' || a == b)
+  });
+  let mut opts = solve_options(&matches);
+  let mut diag = Diagnostics::new();
+  let mut grid = *readgrid(matches.free[0], opts.prefilled, &mut diag);
+  match matches.opt_str("overlay") {
+    Some(file) => match readoverlay(file, &mut grid, &mut diag) {
+      Ok(overlay) => opts.capacity = Some(overlay.capacity),
+      Err(e) => fail!("{}", e)
+    },
+    None => { }
+  }
+  let gridmap = match waypoints(&grid) {
+    Ok(map) => map,
+    Err(e) => fail!("{}", e)
+  };
+  let (start, end) = match (gridmap.find(&from), gridmap.find(&to)) {
+    (Some(&s), Some(&e)) => (s, e),
+    _ => fail!("the grid has no waypoint {}", if gridmap.contains_key(&from) { to } else { from })
+  };
+  // a word can't use more cells than the grid has free
+  let free = grid.tiles.iter().filter(|t| **t == NoWords).count();
+  let fits = |len: uint| {
+    let steps = len as int - 1;
+    len >= min_len && max_len.map_or(true, |max| len <= max) && len - 2 <= free && match opts.bound {
+      Some(ref bound) => bound.min_steps(start, end) <= steps && bound.parity_ok(start, end, steps),
+      None => true
+    }
+  };
+  // dictionaries run to hundreds of thousands of words, so read line by line
+  // and keep only what fits
+  let file = match File::open(&Path::new(dict.as_slice())) {
+    Ok(f) => f,
+    Err(e) => fail!("{}: {}", dict, e)
+  };
+  let mut seen = HashSet::new();
+  let mut found: Vec<(uint, ~str)> = Vec::new();
+  for line in std::io::BufferedReader::new(file).lines().filter_map(|l| l.ok()) {
+    let word = line.trim();
+    if word.len() < 2 || !word.chars().all(|c| c.is_ascii() && c.is_alphabetic()) {
+      continue;
+    }
+    let word = ALPHABET.normalize_str(word);
+    if !ALPHABET.same(word.char_at(0), from) || !ALPHABET.same(word.char_at_reverse(word.len()), to) || !fits(word.len()) || !spelled(word) || !seen.insert(word.clone()) {
+      continue;
+    }
+    found.push((desirability(word), word));
+  }
+  found.sort_by(|&(a, ref x), &(b, ref y)| if a != b { b.cmp(&a) } else { x.cmp(y) });
+  for &(value, ref word) in found.iter() {
+    if matches.opt_present("verify") {
+      match count_paths(&opts, &grid, word.as_slice(), start, end, PATH_COUNT_CAP / 20) {
+        (0, true) => continue,
+        (n, true) => println!("{:<20} {:>4} {} paths", *word, value, n),
+        (n, false) => println!("{:<20} {:>4} {}+ paths", *word, value, n)
+      }
+    } else {
+      println!("{:<20} {:>4}", *word, value);
+    }
+  }
+  print!("{}", diag.summary());
+}
+
+// Every subcommand, and what it's for.  Anything else on the command line
+// goes to solve, so `./solve grid words` still works.
+static COMMANDS: [(&'static str, &'static str), ..11] = [
+  ("solve", "solve a puzzle (the default)"),
+  ("validate", "check a puzzle for problems without searching"),
+  ("batch", "solve every puzzle listed in a manifest"),
+  ("render", "print results saved by solve --save"),
+  ("explain", "trace one word's search cell by cell"),
+  ("learn", "build a word-order model from solved puzzles"),
+  ("suggest", "find dictionary words to fit between two waypoints"),
+  ("check", "check a solution certificate"),
+  ("crosscheck", "solve a tiny puzzle again by brute force and compare"),
+  ("stats", "show the runs recorded by solve --stats-db"),
+  ("help", "list these commands")
+];
+
+// The subcommand a command line is for, and the arguments it gets: everything
+// after the subcommand's name, or after the program's if it didn't name one.
+fn route<'a>(args: &'a [~str]) -> (&'a str, &'a [~str]) {
+  match args.get(1) {
+    Some(first) if COMMANDS.iter().any(|&(name, _)| name == first.as_slice()) => (first.as_slice(), args.slice_from(2)),
+    _ => ("solve", args.tail())
+  }
+}
+
+fn main() {
+  let args = std::os::args();
+  let (command, rest) = route(args);
+  match command {
+    "validate" => validate(rest),
+    "batch" => batch_command(rest),
+    "render" => render(rest),
+    "explain" => explain(rest),
+    "learn" => learn(rest),
+    "suggest" => suggest(rest),
+    "check" => check(rest),
+    "crosscheck" => crosscheck(rest),
+    "stats" => stats_command(rest),
+    "help" => {
+      for &(name, what) in COMMANDS.iter() {
+        println!("  {:<10} {}", name, what);
+      }
+    }
+    _ => solve_command(rest)
+  }
+}
+
+// batch FILE: the same as solve --manifest FILE
+fn batch_command(args: &[~str]) {
+  if args.len() != 1 {
+    fail!("usage: batch manifest");
+  }
+  batch(args[0]);
+}
+
+// validate [options] grid words, or validate [options] puzzle: everything
+// solve checks before it starts searching, and nothing else.  Sets the exit
+// status when there's something wrong, for scripts.
+fn validate(args: &[~str]) {
+  let flags = search_flags();
+  let mut matches = match getopts(args, flags) {
+    Ok(m) => m,
+    Err(f) => fail!(f.to_err_msg())
+  };
+  if matches.free.len() == 1 {
+    let args = with_puzzle_config(args, flags, &matches, matches.free[0]);
+    matches = match getopts(args, flags) {
+      Ok(m) => m,
+      Err(f) => fail!(f.to_err_msg())
+    };
+  }
+  if matches.free.len() != 1 && matches.free.len() != 2 {
+    fail!("usage: validate [options] grid words\n       validate [options] puzzle");
+  }
+  let opts = solve_options(&matches);
+  let mut diag = Diagnostics::new();
+  let overlay_file = matches.opt_str("overlay");
+  let loaded = if matches.free.len() == 1 {
+    load_puzzle(matches.free[0], &opts, &mut diag)
+  } else {
+    load(matches.free[0], overlay_file.as_ref().map(|f| f.as_slice()), matches.free[1], &opts, &mut diag)
+  };
+  let problem = match loaded {
+    Ok((grid, _, words)) => {
+      let (words, _) = split_variants(split_chains(words).val0());
+      match word_endpoints(&hashgrid(grid), words.as_slice()) {
+        Ok(_) => None,
+        Err(e) => Some(e.to_str())
+      }
+    }
+    Err(e) => Some(e.to_str())
+  };
+  print!("{}", diag.summary());
+  match problem {
+    Some(e) => {
+      println!("{}", e);
+      std::os::set_exit_status(1);
+    }
+    None => println!("no problems found")
+  }
+}
+
+// solve [options] grid words, or solve [options] puzzle
+fn solve_command(args: &[~str]) {
+  let mut flags = search_flags();
+  flags.push_all(output_flags());
+  flags.push(optopt("i", "iterations", "solve this many times with different word orders (default 1)", "N"));
+  flags.push(optopt("", "per-iteration-cap", "keep at most K results from each iteration", "K"));
+  flags.push(optflag("", "dry-run", "check the puzzle and report on it without solving"));
+  flags.push(optopt("", "commit-forced", "with --dry-run, write the grid here with every forced letter drawn in, for --prefilled", "FILE"));
+  flags.push(optflag("", "estimate", "print a rough size of the search before solving"));
+  flags.push(optopt("", "summary", "also write a JSON summary of the run here", "FILE"));
+  flags.push(optflag("", "stats", "describe the first result's shape: its crossings, and how many filled cells have each number of filled neighbors"));
+  flags.push(optflag("", "distribution", "show how many results have each number of crossings"));
+  flags.push(optflag("", "word-stats", "after a complete solve, show which words narrowed the search most"));
+  flags.push(optopt("", "format", "how to print results: text (default), or edgelist for the first result's cell graph as TSV", "FORMAT"));
+  flags.push(optopt("", "certificate", "after a complete solve with exactly one result, write a certificate for it here", "FILE"));
+  flags.push(optopt("", "save", "write every distinct result here, for render", "FILE"));
+  flags.push(optopt("", "stats-db", "add a line about this run to FILE, for the stats command", "FILE"));
+  flags.push(optopt("", "zip", "write every distinct result as its own file in a zip archive, with a manifest", "FILE"));
+  flags.push(optmulti("", "region", "a named region of the grid, like NAME=x1,y1:x2,y2", "REGION"));
+  flags.push(optmulti("", "require-region-fill", "drop complete results with less of a region filled than this, like NAME=70", "NAME=PERCENT"));
+  flags.push(optflag("", "require-full-fill", "drop complete results that leave a cell blank or a waypoint no word uses"));
+  flags.push(optflag("", "watch", "solve again whenever the words file changes"));
+  flags.push(optopt("", "session", "keep each word's results here, and pick up the words that haven't changed instead of solving them again", "FILE"));
+  flags.push(optopt("", "manifest", "solve every puzzle listed in this file", "FILE"));
+  flags.push(optopt("", "word-model", "order words using a model built by learn", "FILE"));
+  let mut matches = match getopts(args, flags) {
+    Ok(m) => m,
+    Err(f) => fail!(f.to_err_msg())
+  };
+  if matches.free.len() == 1 {
+    let args = with_puzzle_config(args, flags, &matches, matches.free[0]);
+    matches = match getopts(args, flags) {
+      Ok(m) => m,
+      Err(f) => fail!(f.to_err_msg())
+    };
+  }
+  match matches.opt_str("manifest") {
+    Some(manifest) => {
+      batch(manifest);
+      return;
+    }
+    None => { }
+  }
+  if matches.free.len() != 1 && matches.free.len() != 2 {
+    fail!("usage: solve [--overlay FILE] [--adjacency MOVES] [--no-bound] [--iterations N] [--dry-run] [--summary FILE] [--order ORDER] [--best K] grid words\n       solve [options] puzzle\n       solve --manifest FILE");
+  }
+  let iterations = match count_flag(&matches, "iterations") {
+    Ok(Some(0)) => fail!("--iterations must be at least 1"),
+    Ok(n) => n.unwrap_or(1),
+    Err(e) => fail!("{}", e)
+  };
+  let mut opts = solve_options(&matches);
+  let output = output_plan(&matches);
+  if opts.topology == Hex && !output.orientation.is_identity() {
+    fail!("--rotate and --transpose only work on a square grid");
+  }
+  if output.best.is_some() && !matches.opt_present("order") {
+    opts.ordering = BestScore;
+  }
+  let mut diag = Diagnostics::new();
+  let overlay_file = matches.opt_str("overlay");
+  let loaded = if matches.free.len() == 1 {
+    load_puzzle(matches.free[0], &opts, &mut diag)
+  } else {
+    load(matches.free[0], overlay_file.as_ref().map(|f| f.as_slice()), matches.free[1], &opts, &mut diag)
+  };
+  let (blankgrid, overlay, words) = match loaded {
+    Ok(puzzle) => puzzle,
+    Err(e) => {
+      println!("{}", e);
+      fail!("problems with the grid");
+    }
+  };
+  let (words, chains) = split_chains(words);
+  let (words, variants) = split_variants(words);
+  opts.chains = chains;
+  opts.variants = variants;
+  if matches.opt_present("dry-run") {
+    match overlay {
+      Some(ref overlay) => print!("{}", overlay_report(&blankgrid, overlay)),
+      None => { }
+    }
+    print!("{}", letter_report(&blankgrid, words.as_slice()));
+    let names: Vec<&str> = words.iter().map(|w| w.as_slice()).collect();
+    let crossings = possible_crossings(names.as_slice());
+    let mut apart: Vec<~str> = crossings.iter().filter(|&(_, letters)| letters.is_empty()).map(|(&(ref a, ref b), _)| format!("{}/{}", *a, *b)).collect();
+    apart.sort();
+    println!("{} of {} word pairs have no letter in common to cross on{}{}", apart.len(), crossings.len(), if apart.is_empty() { "" } else { ": " }, apart.connect(", "));
+    print!("{}", chokepoint_report(&blankgrid, &opts, &mut diag));
+    let mut dryopts = opts.clone();
+    dryopts.capacity = overlay.as_ref().map(|o| o.capacity.clone());
+    match word_endpoints(&hashgrid(blankgrid.clone()), words.as_slice()) {
+      Ok(paths) => {
+        let (report, committed) = forced_report(&dryopts, &blankgrid, words.as_slice(), paths.as_slice(), &mut diag);
+        print!("{}", report);
+        match matches.opt_str("commit-forced") {
+          Some(file) => File::create(&Path::new(file.as_slice())).write_str(format!("{}\n", committed.to_strgrid())).unwrap(),
+          None => { }
+        }
+      }
+      Err(e) => println!("{}", e)
+    }
+    print!("{}", diag.summary());
+    return;
+  }
+  let mut regions = overlay.as_ref().map_or(Vec::new(), overlay_regions);
+  let drawn = regions.len();
+  for spec in matches.opt_strs("region").iter() {
+    match parse_region(*spec) {
+      Ok(region) => regions.push(region),
+      Err(e) => fail!("--region: {}", e)
+    }
+  }
+  // the overlay's own regions are on the grid already
+  match check_coordinates(&blankgrid, region_coordinates(regions.slice_from(drawn)).as_slice()) {
+    Ok(()) => { }
+    Err(e) => fail!("{}", e)
+  }
+  let requirements: Vec<(~str, f64)> = matches.opt_strs("require-region-fill").iter().map(|spec| match parse_fill_requirement(*spec) {
+    Ok((name, percent)) => {
+      if !regions.iter().any(|r| r.name == name) {
+        fail!("--require-region-fill: no region named {}", name);
+      }
+      (name, percent)
+    }
+    Err(e) => fail!("--require-region-fill: {}", e)
+  }).collect();
+  opts.capacity = overlay.map(|o| o.capacity);
+  let gridmap: HashMap<char, Point> = hashgrid(blankgrid.clone());
+  let names: Vec<&str> = words.iter().map(|w| w.as_slice()).collect();
+  let endpoints = match build_endpoint_map(names.as_slice(), &gridmap) {
+    Ok(endpoints) => endpoints,
+    Err(e) => fail!("{}", e)
+  };
+  let paths: Vec<(Point, Point)> = names.iter().map(|w| endpoints.get(*w).unwrap()).collect();
+  let fixed = std::mem::replace(&mut opts.fixed_paths, Vec::new());
+  for (word, path) in fixed.move_iter() {
+    let i = match words.iter().position(|w| *w == word) {
+      Some(i) => i,
+      None => fail!("--fix-path: \"{}\" isn't in the word list", word)
+    };
+    let (start, end) = *paths.get(i);
+    match check_fixed_path(&opts, word, start, end, path.as_slice()) {
+      Ok(cells) => opts.fixed_paths.push((word, cells)),
+      Err(e) => fail!("--fix-path: {}", e)
+    }
+  }
+  println!("loaded {} words!", words.len());
+  let mut solver = CrosswordSolver::new(opts.clone());
+  match matches.opt_str("word-model") {
+    Some(file) => match PlacementModel::from_json_str(readlines(file).connect("\n")) {
+      Ok(model) => solver = solver.with_learned_ordering(model),
+      Err(e) => fail!("{}: {}", file, e)
+    },
+    None => { }
+  }
+  let base = solver.word_order(&blankgrid, &endpoints, words.as_slice());
+  if matches.opt_present("estimate") {
+    let wordpts: Vec<(&str, &(Point, Point))> = words.iter().map(|w| w.as_slice()).zip(paths.iter()).collect();
+    let space = estimate_space(&opts, &blankgrid, wordpts.as_slice());
+    if space == 0.0 {
+      println!("estimated search space: nothing, some word has no path at all");
+    } else {
+      println!("estimated search space: about 10^{:.0f} grids", space.log10());
+    }
+  }
+  let mut plan = IterationPlan::new(words.len());
+  plan.cap = match count_flag(&matches, "per-iteration-cap") {
+    Ok(Some(0)) => fail!("--per-iteration-cap must be at least 1"),
+    Ok(k) => k,
+    Err(e) => fail!("{}", e)
+  };
+  let iterations = if iterations > plan.distinct_orders() {
+    diag.report(Note, Nowhere, format!("only {} different word orders to try; stopping after that many iterations instead of {}", plan.distinct_orders(), iterations));
+    plan.distinct_orders()
+  } else {
+    iterations
+  };
+  let mut runs: ~[json::Json] = ~[];
+  let mut saved = StrBuf::new();
+  let mut saved_ids = HashSet::new();
+  // for --zip: each distinct result's file, and a manifest line for it
+  let mut archived: Vec<(~str, ~str)> = Vec::new();
+  let mut manifest = StrBuf::new();
+  let mut last = Vec::new();
+  let session = matches.opt_str("session");
+  let command = args.connect(" ");
+  let mut restored = session.as_ref().and_then(|file| session_solutions(&opts, *file, command.as_slice(), &blankgrid, &endpoints, words.as_slice(), &mut diag));
+  for iteration in range(0, iterations) {
+    let order: Vec<uint> = plan.next_order().iter().map(|&i| *base.get(i)).collect();
+    let wordpts: Vec<(&str, &(Point, Point))> = order.iter().map(|&i| (words.get(i).as_slice(), paths.get(i))).collect();
+    let started = time::precise_time_ns();
+    let mut result = match restored.take() {
+      Some(solutions) => {
+        println!("picked up {} results from {}", solutions.len(), *session.get_ref());
+        let mut result = SolveResult::start(&blankgrid);
+        result.solutions = solutions;
+        result
+      }
+      None => solver.solve(&mut diag, &blankgrid, wordpts.as_slice())
+    };
+    let seconds = (time::precise_time_ns() - started) as f64 / 1e9;
+    match result.error(&opts) {
+      Some(e) => {
+        print!("{}", diag.summary());
+        println!("{}", e);
+        fail!("search aborted");
+      }
+      None => { }
+    }
+    // what --watch builds on, if the words file changes: everything the
+    // search found, before the filters and the cap below thin it out
+    last = carried_results(&result);
+    match matches.opt_str("stats-db") {
+      Some(ref file) if iteration == 0 => append_record(*file, &run_record(&blankgrid, words.as_slice(), args, seconds, &result)),
+      _ => { }
+    }
+    // the fill requirements that left no results at all
+    let mut unmet: Vec<&str> = Vec::new();
+    if !requirements.is_empty() && result.stuck_on.is_none() {
+      let before = result.solutions.len();
+      result.solutions.retain(|grid| {
+        let stats = grid_stats(grid, regions.as_slice(), wordpts.as_slice());
+        requirements.iter().all(|&(ref name, percent)| stats.region_percent(*name).unwrap() >= percent)
+      });
+      if result.solutions.len() < before {
+        diag.report(Note, Nowhere, format!("dropped {} of {} results for their region fill", before - result.solutions.len(), before));
+      }
+      if result.solutions.is_empty() && before > 0 {
+        unmet.push("--require-region-fill");
+      }
+    }
+    // a full fill leaves no cell blank and no waypoint without a word
+    if matches.opt_present("require-full-fill") && result.stuck_on.is_none() {
+      let before = result.solutions.len();
+      result.solutions.retain(|grid| {
+        let stats = grid_stats(grid, [], wordpts.as_slice());
+        stats.filled == stats.cells && stats.unused_waypoints.is_empty()
+      });
+      if result.solutions.len() < before {
+        diag.report(Note, Nowhere, format!("dropped {} of {} results that left a cell blank or a waypoint unused", before - result.solutions.len(), before));
+      }
+      if result.solutions.is_empty() && before > 0 {
+        unmet.push("--require-full-fill");
+      }
+      // every cell is filled, so every one should be on some word's path; one
+      // that isn't means the grid doesn't say how its words got there
+      for (i, grid) in result.solutions.iter().enumerate() {
+        let paths = placed_paths(grid, wordpts.as_slice(), &opts);
+        if covers_all_cells(grid, paths.as_slice()) {
+          continue;
+        }
+        for &p in uncovered_cells(grid, paths.as_slice()).iter() {
+          diag.report(Warning, Cell(p), format!("result {} fills this cell, but no word's path goes through it", i + 1));
+        }
+      }
+    }
+    // what the search found, before any cap keeps this iteration to its share
+    let found = result.count_str();
+    let (capped, fresh) = plan.take(&mut result.solutions);
+    let repeats = fresh.iter().filter(|&&f| !f).count();
+    if iterations > 1 {
+      println!("iteration {}: {} results, {} not seen before ({} distinct so far)", iteration + 1, result.solutions.len(), result.solutions.len() - repeats, plan.seen.len());
+    }
+    if !output.legacy {
+      println!("{}", found);
+    }
+    if capped {
+      println!("keeping the first {} of them for this iteration", result.solutions.len());
+    }
+    if !opts.optional.is_empty() && !result.solutions.is_empty() {
+      print!("{}", optional_report(&result));
+    }
+    if !opts.variants.is_empty() {
+      print!("{}", spelling_report(&result));
+    }
+    if matches.opt_present("stats") && !result.solutions.is_empty() {
+      print!("{}", degree_report(&histogram_neighbor_degrees(result.solutions.get(0))));
+      println!("crossings: {}", count_crossings(result.solutions.get(0)));
+      let distances = word_pair_distance_matrix(result.solutions.get(0));
+      if !distances.is_empty() {
+        let total = distances.values().fold(0.0, |sum, d| sum + *d);
+        println!("average distance between word centers: {:.2f}", total / distances.len() as f64);
+      }
+    }
+    if matches.opt_present("distribution") {
+      print!("{}", distribution_report(&crossing_distribution(result.solutions.as_slice())));
+    }
+    if matches.opt_present("word-stats") && result.stuck_on.is_none() {
+      print!("{}", constraint_table(&result, &opts));
+    }
+    match matches.opt_str("format") {
+      _ if !output.per_iteration && iteration + 1 < iterations => { }
+      // there's nothing to draw
+      _ if result.solutions.is_empty() && !unmet.is_empty() => println!("no results satisfy {}", unmet.connect(" and ")),
+      Some(ref format) if format.as_slice() == "edgelist" => {
+        if !result.solutions.is_empty() {
+          print!("{}", edge_list_tsv(result.solutions.get(0)));
+        }
+      }
+      Some(ref format) if format.as_slice() != "text" => fail!("--format must be text or edgelist"),
+      // after the first iteration, only what it found that's new
+      _ if output.per_iteration && !output.legacy && iteration > 0 && repeats > 0 => {
+        let new: Vec<CrosswordGrid> = result.solutions.iter().zip(fresh.iter()).filter(|&(_, &f)| f).map(|(grid, _)| grid.clone()).collect();
+        println!("{} results found by earlier iterations aren't shown again", repeats);
+        if !new.is_empty() {
+          print!("{}", render_results(&blankgrid, &new, words.as_slice(), &opts, &output, regions.as_slice(), &mut diag));
+        }
+      }
+      _ => print!("{}", render_results(&blankgrid, &result.solutions, words.as_slice(), &opts, &output, regions.as_slice(), &mut diag))
+    }
+    match matches.opt_str("certificate") {
+      Some(ref file) if iteration == 0 => {
+        if result.solutions.len() == 1 && result.completeness == Exhaustive && result.stuck_on.is_none() {
+          let cert = certify(&blankgrid, result.solutions.get(0), words.as_slice(), paths.as_slice(), &opts);
+          File::create(&Path::new(file.as_slice())).write_str(json::Encoder::str_encode(&cert)).unwrap();
+        } else {
+          diag.report(Warning, Nowhere, format!("not writing {}: only a complete solve with one result gets a certificate", *file));
+        }
+      }
+      _ => { }
+    }
+    let mut run = ~TreeMap::new();
+    run.insert(~"solutions", json::Number(result.solutions.len() as f64));
+    run.insert(~"exhaustive", json::Boolean(result.completeness == Exhaustive));
+    run.insert(~"completeness", json::String(result.completeness.to_str()));
+    match result.stuck_on {
+      Some(word) => { run.insert(~"stuck_on", json::String(word.to_owned())); }
+      None => { }
+    }
+    let ids: Vec<~str> = result.solutions.iter().map(solution_id).collect();
+    for (id, grid) in ids.iter().zip(result.solutions.iter()) {
+      if saved_ids.insert(id.clone()) {
+        saved.push_str(format!("solution {}\n{}\n\n", *id, canonical_text(grid)));
+        let name = format!("solution-{}.txt", *id);
+        manifest.push_str(format!("{}\t{}\t{}\n", name, *id, score(grid)));
+        archived.push((name, format!("{}\n", drawn_for(&opts, &output, grid.to_strgrid()))));
+      }
+    }
+    run.insert(~"ids", json::List(ids.move_iter().map(|id| json::String(id)).collect()));
+    if !opts.variants.is_empty() {
+      let spellings = result.spellings.iter().map(|chosen| json::List(chosen.iter().map(|w| json::String(w.to_owned())).collect())).collect();
+      run.insert(~"spellings", json::List(spellings));
+    }
+    runs.push(json::Object(run));
+  }
+  if iterations > 1 {
+    let yields: Vec<~str> = plan.yields.iter().map(|n| n.to_str()).collect();
+    println!("new results per iteration: {}", yields.connect(", "));
+    println!("{} distinct results in all", plan.seen.len());
+  }
+  match matches.opt_str("save") {
+    Some(file) => File::create(&Path::new(file.as_slice())).write_str(saved.as_slice()).unwrap(),
+    None => { }
+  }
+  match matches.opt_str("zip") {
+    Some(file) => {
+      archived.insert(0, (~"manifest.txt", format!("file\tid\tscore\n{}", manifest.as_slice())));
+      File::create(&Path::new(file.as_slice())).write(zip_archive(archived.as_slice()).as_slice()).unwrap();
+    }
+    None => { }
+  }
+  // kept off stdout for scripts diffing --legacy-output, but not lost
+  if output.legacy {
+    std::io::stderr().write_str(diag.summary()).unwrap();
+  } else {
+    print!("{}", diag.summary());
+  }
+  match matches.opt_str("summary") {
+    Some(file) => {
+      let mut obj = ~TreeMap::new();
+      obj.insert(~"runs", json::List(runs));
+      obj.insert(~"orientation", json::String(output.orientation.describe()));
+      obj.insert(~"diagnostics", diag.to_json());
+      let path = Path::new(file.as_slice());
+      File::create(&path).write_str(json::Object(obj).to_pretty_str()).unwrap();
+    }
+    None => { }
+  }
+  if matches.opt_present("watch") {
+    if matches.free.len() != 2 {
+      fail!("--watch needs a separate words file");
+    }
+    let file = matches.free[1].as_slice();
+    let keep = session.as_ref().map(|f| (f.as_slice(), command.as_slice()));
+    watch(&opts, &output, &mut diag, &blankgrid, &gridmap, file, file_stamp(file), keep, words.clone(), last);
+  }
+}
+
+// When a file was last changed and a hash of what's in it.
+fn file_stamp(file: &str) -> (u64, u32) {
+  let path = Path::new(file);
+  let modified = path.stat().map(|stat| stat.modified).unwrap_or(0);
+  let hash = File::open(&path).read_to_end().map(|bytes| fnv1a(bytes.as_slice())).unwrap_or(0);
+  (modified, hash)
+}
+
+// One reload's solve.  `words` and `previous` are the last solve's word list
+// and its results, as canonical text.  When words were only added, the new
+// ones are placed on top of those results rather than starting over;
+// removing or changing a word can open up placements the last solve threw
+// out, so that starts from scratch, as does having no results to build on.
+fn resolve_words<'a, 'g>(opts: &SolveOptions, diag: &mut Diagnostics, grid: &CrosswordGrid<'g>, endpoints: &WordEndpointMap, words: &[~str], previous: &[~str], fresh: &'a [~str]) -> SolveResult<'a> {
+  let added: Vec<&str> = fresh.iter().filter(|w| !words.contains(*w)).map(|w| w.as_slice()).collect();
+  let removed = words.iter().filter(|w| !fresh.contains(*w)).count();
+  println!("{} words added, {} removed", added.len(), removed);
+  let carried: Vec<CrosswordGrid<'a>> = if removed == 0 { previous.iter().filter_map(|text| parse_canonical(*text, fresh).ok()).collect() } else { Vec::new() };
+  if carried.is_empty() || carried.len() < previous.len() {
+    let paths: Vec<(Point, Point)> = fresh.iter().map(|w| endpoints.get(*w).unwrap()).collect();
+    let wordpts: Vec<(&str, &(Point, Point))> = fresh.iter().map(|w| w.as_slice()).zip(paths.iter()).collect();
+    return solve(opts, diag, grid, wordpts.as_slice());
+  }
+  let mut progress = SolveResult::start(grid);
+  progress.solutions = carried;
+  let paths: Vec<(Point, Point)> = added.iter().map(|w| endpoints.get(*w).unwrap()).collect();
+  let wordpts: Vec<(&str, &(Point, Point))> = added.iter().map(|w| *w).zip(paths.iter()).collect();
+  let mut result = add_word(&mut Search::new(opts, NoTrace), diag, progress, wordpts.as_slice());
+  drop_unclaimed(&mut result, diag);
+  result.solutions = order_solutions(opts.ordering, opts.tie_break.as_slice(), result.solutions);
+  result
+}
+
+// The results a reload can build on, as canonical text.  Only a complete set
+// will do: adding words to part of one would quietly lose the rest.
+fn carried_results<'a>(result: &SolveResult<'a>) -> Vec<~str> {
+  if result.completeness == Exhaustive && result.timed_out.is_empty() {
+    result.solutions.iter().map(canonical_text).collect()
+  } else {
+    Vec::new()
+  }
+}
+
+// --watch: waits for the words file to change, then solves again with
+// resolve_words.  Chains and alternate spellings are rules of the puzzle, so
+// a change to either starts over too.  The results are kept between reloads
+// as canonical text, since each grid borrows the word list it was solved
+// with.  `session` is the --session file and the command line to save it with.
+fn watch<'g>(opts: &SolveOptions, output: &OutputPlan, diag: &mut Diagnostics, grid: &CrosswordGrid<'g>, gridmap: &HashMap<char, Point>, file: &str, stamp: (u64, u32), session: Option<(&str, &str)>, words: Vec<~str>, previous: Vec<~str>) {
+  println!("watching {} for changes", file);
+  let mut opts = opts.clone();
+  let (mut stamp, mut words, mut previous) = (stamp, words, previous);
+  loop {
+    std::io::timer::sleep(500);
+    let (modified, hash) = file_stamp(file);
+    if modified == stamp.val0() {
+      continue;
+    }
+    let changed = hash != stamp.val1();
+    stamp = (modified, hash);
+    if !changed {
+      continue;
+    }
+    // anchored words already have their waypoints on the grid
+    let fresh = readwords(file, opts.max_word_len, diag).move_iter().map(|line| match parse_anchored(line) {
+      Some(Ok((word, _, _))) => word,
+      _ => line
+    }).collect();
+    let (fresh, chains) = split_chains(fresh);
+    let (mut fresh, variants) = split_variants(fresh);
+    if chains != opts.chains || variants != opts.variants {
+      diag.report(Note, Nowhere, format!("{}: the chains or spellings changed, so solving from scratch", file));
+      opts.chains = chains;
+      opts.variants = variants;
+      previous.clear();
+      // the session's grids were solved under the old rules
+      match session {
+        Some((keep, _)) if Path::new(keep).exists() => std::io::fs::unlink(&Path::new(keep)).unwrap(),
+        _ => { }
+      }
+    }
+    fresh.sort_by(|a,b| a.len().cmp(&b.len()));
+    previous = {
+      let names: Vec<&str> = fresh.iter().map(|w| w.as_slice()).collect();
+      let endpoints = match build_endpoint_map(names.as_slice(), gridmap) {
+        Err(e) => {
+          println!("{}", e);
+          continue;
+        }
+        Ok(endpoints) => endpoints
+      };
+      print!("{}: ", file);
+      let kept = session.and_then(|(keep, command)| session_solutions(&opts, keep, command, grid, &endpoints, fresh.as_slice(), diag));
+      let result = match kept {
+        Some(solutions) => {
+          let mut result = SolveResult::start(grid);
+          result.solutions = solutions;
+          result
+        }
+        None => resolve_words(&opts, diag, grid, &endpoints, words.as_slice(), previous.as_slice(), fresh.as_slice())
+      };
+      println!("{}", result.count_str());
+      if !result.solutions.is_empty() {
+        let plan = OutputPlan { best: None, symbols: false, ..output.clone() };
+        print!("{}", render_results(grid, &result.solutions, fresh.as_slice(), &opts, &plan, [], diag));
+      }
+      carried_results(&result)
+    };
+    words = fresh;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::io::{File, TempDir};
+  use std::sync::atomics::SeqCst;
+  use time::precise_time_ns;
+  use getopts::getopts;
+  use serialize::json;
+  use collections::HashMap;
+  use super::{CrosswordGrid, Diagnostics, Point, SolveOptions, SolveResult, OneWord, KNIGHT_MOVES, parsegrid, hashgrid, word_endpoints, solve, IsolatedAnchor, preflight, TwoWords, AsFound, BestScore, Canonical, DEFAULT_TIE_BREAK, order_solutions, canonical_text, score, batch, CrosswordSolver, TruncatedBy, Cancelled, Timeout, WordTimeout, word_symbols, render_symbols, total_turns, solution_id, count_paths, estimate_space, Puzzle, parse_puzzle, write_puzzle, resolve_words, build_endpoint_map, NoWords, SolveError, BadFile, BadGrid, DuplicateWaypoint, MissingWaypoints, DepthExceeded, OutsideGrid, MissingEndpoints, NoWaypoints, DisconnectedGrid, parsewords, DEFAULT_MAX_WORD_LEN, ALPHABET, check_placement, word_path, Grid, Session, session_solutions, OutputPlan, Orientation, render_results, FlattenCrossword, crossing_distribution, parse_output_plan, output_flags, parse_solve_options, search_flags, fill_ratio, route, checked, ByShape, ByWordPaths, CanonicalText, word_box_area, added_cells, Fixed, IterationPlan, brute_force, EIGHT_WAYS, parity_cells, Hex, check_fixed_path, merge_grids, solve_command, independent_groups, AnchorOutsideBox, longest_word, is_planar, zip_archive, crc32, check_coordinates, option_coordinates, region_coordinates, parse_region, possible_crossings, Stuck, Exhaustive, RegionOfInterest, symmetry_of, carried_results, Through, most_constrained_word, constraint_table, certify, verify_certificate, Certificate};
+
+  // a grid from its rows, read the way a grid file is
+  fn grid<'a>(rows: &[&str]) -> CrosswordGrid<'a> {
+    let lines: Vec<~str> = rows.iter().map(|row| row.to_owned()).collect();
+    *parsegrid("test", 1, lines.as_slice(), false, &mut Diagnostics::new())
+  }
+
+  // the same, with lowercase letters as cells already drawn in
+  fn prefilled_grid<'a>(rows: &[&str]) -> CrosswordGrid<'a> {
+    let lines: Vec<~str> = rows.iter().map(|row| row.to_owned()).collect();
+    *parsegrid("test", 1, lines.as_slice(), true, &mut Diagnostics::new())
+  }
+
+  fn word_list(words: &[&str]) -> Vec<~str> {
+    words.iter().map(|w| w.to_owned()).collect()
+  }
+
+  // a words file's lines, read the way the file is
+  fn parsed_words(lines: &[&str], diag: &mut Diagnostics) -> Vec<~str> {
+    parsewords("test", 1, word_list(lines).as_slice(), DEFAULT_MAX_WORD_LEN, diag)
+  }
+
+  fn texts<'a>(grids: &[CrosswordGrid<'a>]) -> Vec<~str> {
+    grids.iter().map(canonical_text).collect()
+  }
+
+  fn write_file(dir: &Path, name: &str, text: &str) {
+    File::create(&dir.join(name)).write_str(text).unwrap();
+  }
+
+  // A word from A to B that can wander around the grid almost forever
+  // without getting anywhere, since B is walled in; and one from C to D that
+  // has exactly one way to go.
+  static ENDLESS: &'static str = "axxxxxxxxxxxxxxxxxxxxxxxxxxxb";
+  fn endless<'a>() -> CrosswordGrid<'a> {
+    grid(["A       ", "        ", "        ", "        ", "        ", "        ", "       #", "C D   #B"])
+  }
+
+  // with every placement checked, even in a build with --cfg ndebug
+  fn solved<'a>(opts: &SolveOptions, blank: &CrosswordGrid<'a>, words: &'a [~str]) -> SolveResult<'a> {
+    let mut opts = opts.clone();
+    opts.paranoid = true;
+    let opts = &opts;
+    let paths = word_endpoints(&hashgrid(blank.clone()), words).unwrap();
+    let wordpts: Vec<(&str, &(Point, Point))> = words.iter().map(|w| w.as_slice()).zip(paths.iter()).collect();
+    solve(opts, &mut Diagnostics::new(), blank, wordpts.as_slice())
+  }
+
+  #[test]
+  fn knight_moves_find_a_path_four_ways_cannot() {
+    let blank = grid(["A   B", "     "]);
+    let words = word_list(["axb"]);
+    let knight = SolveOptions::with_offsets(Vec::from_slice(KNIGHT_MOVES.as_slice()));
+    let result = solved(&knight, &blank, words.as_slice());
+    assert_eq!(result.stuck_on, None);
+    assert_eq!(result.solutions.len(), 1);
+    assert!(*result.solutions.get(0).get_ref(Point { x: 2, y: 1 }).unwrap() == OneWord('x', "axb"));
+    let square = solved(&SolveOptions::new(), &blank, words.as_slice());
+    assert_eq!(square.stuck_on, Some("axb"));
+  }
+
+  #[test]
+  fn anchor_walled_in_is_flagged() {
+    let blank = grid(["A#  ", "##  ", "   B"]);
+    let errors = preflight(&blank, &SolveOptions::new(), &mut Diagnostics::new()).unwrap_err();
+    assert!(errors.iter().any(|e| match *e { IsolatedAnchor('a', Point { x: 0, y: 0 }) => true, _ => false }));
+    assert!(!errors.iter().any(|e| match *e { IsolatedAnchor('b', _) => true, _ => false }));
+  }
+
+  #[test]
+  fn a_waypoint_next_to_another_is_not_walled_in() {
+    let blank = grid(["AB#", "###"]);
+    assert!(preflight(&blank, &SolveOptions::new(), &mut Diagnostics::new()).is_ok());
+    let words = word_list(["ab"]);
+    assert_eq!(solved(&SolveOptions::new(), &blank, words.as_slice()).solutions.len(), 1);
+  }
+
+  #[test]
+  fn each_ordering_arranges_a_fixed_set_as_documented() {
+    let blank: CrosswordGrid = grid(["A B"]);
+    let middle = Point { x: 1, y: 0 };
+    let one = blank.replace(middle, OneWord('x', "axb"));
+    let two = blank.replace(middle, TwoWords('x', "axb", "cxd"));
+    let results = vec!(one.clone(), blank.clone(), two.clone(), one.clone());
+    let found = order_solutions(AsFound, DEFAULT_TIE_BREAK.as_slice(), results.clone());
+    assert_eq!(texts(found.as_slice()), texts(results.as_slice()));
+    let best = order_solutions(BestScore, DEFAULT_TIE_BREAK.as_slice(), results.clone());
+    let scores: Vec<int> = best.iter().map(score).collect();
+    assert_eq!(scores, vec!(2, 1, 1, 0));
+    let canonical = order_solutions(Canonical, DEFAULT_TIE_BREAK.as_slice(), results.clone());
+    assert_eq!(texts(canonical.as_slice()), texts([blank.clone(), one.clone(), two.clone()]));
+  }
+
+  #[test]
+  fn batch_solves_each_manifest_entry_into_its_own_file() {
+    let dir = TempDir::new("batch").unwrap();
+    write_file(dir.path(), "one.grid", "A B\n");
+    write_file(dir.path(), "one.words", "axb\n");
+    write_file(dir.path(), "two.grid", "C  D\n");
+    write_file(dir.path(), "two.words", "cxyd\n");
+    write_file(dir.path(), "manifest", "one.grid one.words\ntwo.grid two.words -o two.txt\n");
+    let summary = batch(dir.path().join("manifest").as_str().unwrap());
+    assert_eq!(summary.solved, 2);
+    assert_eq!(summary.lines, vec!(~"one.grid: solved, 1 solutions, exhaustive", ~"two.grid: solved, 1 solutions, exhaustive", ~"solved 2 of 2 puzzles"));
+    for name in ["one.out", "two.txt"].iter() {
+      let report = File::open(&dir.path().join(*name)).read_to_str().unwrap();
+      assert!(report.starts_with("1 solutions, exhaustive\n"), "{}: {}", *name, report);
+    }
+  }
+
+  #[test]
+  fn cancelling_from_another_task_ends_a_long_solve() {
+    let blank = endless();
+    let words = word_list([ENDLESS]);
+    let mut solver = CrosswordSolver::new(SolveOptions::new());
+    let flag = solver.cancel_handle();
+    spawn(proc() {
+      std::io::timer::sleep(100);
+      flag.store(true, SeqCst);
+    });
+    let started = precise_time_ns();
+    let result = solved(&solver.opts, &blank, words.as_slice());
+    assert!(precise_time_ns() - started < 5_000_000_000);
+    assert!(result.completeness == TruncatedBy(Cancelled, ENDLESS));
+    assert_eq!(result.stuck_on, Some(ENDLESS));
+  }
+
+  #[test]
+  fn word_timeout_skips_a_word_with_nothing_found_and_moves_on() {
+    let blank = endless();
+    let words = word_list([ENDLESS, "cxd"]);
+    let mut opts = SolveOptions::new();
+    opts.word_timeout = Some(0.2);
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.timed_out, vec!(ENDLESS));
+    assert_eq!(result.stuck_on, None);
+    assert!(result.completeness == TruncatedBy(WordTimeout, ENDLESS));
+    assert_eq!(result.solutions.len(), 1);
+    assert!(*result.solutions.get(0).get_ref(Point { x: 1, y: 7 }).unwrap() == OneWord('x', "cxd"));
+  }
+
+  #[test]
+  fn the_global_timeout_outranks_a_longer_word_timeout() {
+    let blank = endless();
+    let words = word_list([ENDLESS, "cxd"]);
+    let mut opts = SolveOptions::new();
+    opts.timeout = Some(0.2);
+    opts.word_timeout = Some(60.0);
+    let started = precise_time_ns();
+    let result = solved(&opts, &blank, words.as_slice());
+    assert!(precise_time_ns() - started < 5_000_000_000);
+    assert!(result.timed_out.is_empty());
+    assert_eq!(result.stuck_on, Some(ENDLESS));
+    assert!(result.completeness == TruncatedBy(Timeout, ENDLESS));
+  }
+
+  #[test]
+  fn the_symbol_legend_lists_each_placed_word_once() {
+    let blank = grid(["A B", "   ", "C D"]);
+    let words = word_list(["axb", "cyd"]);
+    let result = solved(&SolveOptions::new(), &blank, words.as_slice());
+    let symbols = word_symbols(words.as_slice(), &mut Diagnostics::new());
+    let drawn = render_symbols(result.solutions.get(0), symbols.as_slice());
+    let legend: Vec<(char, &str)> = drawn.lines().skip(blank.height as uint).map(|line| (line.char_at(2), line.slice_from(5))).collect();
+    assert_eq!(legend.len(), words.len());
+    for w in words.iter() {
+      assert_eq!(legend.iter().filter(|&&(_, listed)| listed == w.as_slice()).count(), 1);
+    }
+    let (first, _) = *legend.get(0);
+    let (second, _) = *legend.get(1);
+    assert!(first != second);
+  }
+
+  #[test]
+  fn prefer_straight_puts_the_straighter_layouts_first() {
+    let blank = grid(["A  ", "   ", "  B"]);
+    let words = word_list(["axyzb"]);
+    let mut opts = SolveOptions::new();
+    opts.prefer_straight = true;
+    let result = solved(&opts, &blank, words.as_slice());
+    let ends = (Point { x: 0, y: 0 }, Point { x: 2, y: 2 });
+    let wordpts = [("axyzb", &ends)];
+    let turns: Vec<uint> = result.solutions.iter().map(|g| total_turns(g, wordpts.as_slice(), &opts)).collect();
+    assert_eq!(turns, vec!(1, 1, 2, 2, 3, 3));
+  }
+
+  // the README's worked example; if this changes, every saved id does
+  #[test]
+  fn solution_ids_stay_the_same() {
+    let blank = grid(["C T", "D G"]);
+    let words = word_list(["cat", "dog"]);
+    let result = solved(&SolveOptions::new(), &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 1);
+    let solution = result.solutions.get(0);
+    assert_eq!(canonical_text(solution), ~"C a:cat T\nD o:dog G");
+    assert_eq!(solution_id(solution), ~"50375c39");
+  }
+
+  #[test]
+  fn batch_solves_a_repeated_puzzle_only_once() {
+    let dir = TempDir::new("batch").unwrap();
+    write_file(dir.path(), "one.grid", "A  \n   \n  B\n");
+    write_file(dir.path(), "one.words", "axyzb\n");
+    write_file(dir.path(), "once", "one.grid one.words\n");
+    write_file(dir.path(), "twice", "one.grid one.words\none.grid one.words -o again.out\n");
+    let once = batch(dir.path().join("once").as_str().unwrap());
+    let twice = batch(dir.path().join("twice").as_str().unwrap());
+    assert!(once.nodes > 0);
+    assert_eq!(twice.nodes, once.nodes);
+    assert_eq!(twice.solved, 2);
+    assert_eq!(twice.lines.get(0), twice.lines.get(1));
+    let first = File::open(&dir.path().join("one.out")).read_to_str().unwrap();
+    let again = File::open(&dir.path().join("again.out")).read_to_str().unwrap();
+    assert_eq!(first, again);
+  }
+
+  #[test]
+  fn the_space_estimate_is_the_product_of_path_counts() {
+    let blank = grid(["A C", "   ", "D B"]);
+    let ab = (Point { x: 0, y: 0 }, Point { x: 2, y: 2 });
+    let cd = (Point { x: 2, y: 0 }, Point { x: 0, y: 2 });
+    let opts = SolveOptions::new();
+    let (first, _) = count_paths(&opts, &blank, "axyzb", ab.val0(), ab.val1(), 1000);
+    let (second, _) = count_paths(&opts, &blank, "cpqrd", cd.val0(), cd.val1(), 1000);
+    assert_eq!((first, second), (4, 4));
+    assert_eq!(estimate_space(&opts, &blank, [("axyzb", &ab), ("cpqrd", &cd)]), 16.0);
+  }
+
+  static FULL_PUZZLE: &'static str = "; every section, with walls, regions, pre-filled cells and a chain
+[metadata]
+title = Walls and regions
+author = test
+
+[grid]
+A #  B
+  cat 
+      
+C  #  E
+[overlay]
+AA  BB
+ 1  # 
+      
+AA  BB
+[words]
+achab
+chain: ember -> river -> rest
+?6 a b
+
+[constraints]
+; a note that isn't a constraint
+no crossings at 2,1
+
+[config]
+prefilled
+max-word-len = 12
+";
+
+  static BARE_PUZZLE: &'static str = "[words]
+ab
+[grid]
+A
+B
+";
+
+  fn reparsed(text: &str) -> Puzzle {
+    let lines: Vec<~str> = text.lines().map(|l| l.to_owned()).collect();
+    let first = parse_puzzle(lines.as_slice()).unwrap();
+    let written = write_puzzle(&first);
+    let lines: Vec<~str> = written.lines().map(|l| l.to_owned()).collect();
+    let second = parse_puzzle(lines.as_slice()).unwrap();
+    assert!(first == second, "{}", written);
+    assert_eq!(write_puzzle(&second), written);
+    second
+  }
+
+  #[test]
+  fn a_puzzle_with_every_section_survives_writing_out() {
+    let puzzle = reparsed(FULL_PUZZLE);
+    assert_eq!(puzzle.grid, vec!(~"A #  B", ~"  cat ", ~"      ", ~"C  #  E"));
+    assert_eq!(puzzle.overlay, Some(vec!(~"AA  BB", ~" 1  # ", ~"      ", ~"AA  BB")));
+    assert_eq!(puzzle.words, vec!(~"achab", ~"chain: ember -> river -> rest", ~"?6 a b"));
+    assert_eq!(puzzle.constraints, vec!(~"no crossings at 2,1"));
+    assert_eq!(puzzle.metadata, vec!((~"title", ~"Walls and regions"), (~"author", ~"test")));
+    assert_eq!(puzzle.config, vec!((~"prefilled", ~""), (~"max-word-len", ~"12")));
+  }
+
+  #[test]
+  fn a_puzzle_with_only_the_required_sections_survives_writing_out() {
+    let puzzle = reparsed(BARE_PUZZLE);
+    assert_eq!(puzzle.grid, vec!(~"A", ~"B"));
+    assert_eq!(puzzle.overlay, None);
+    assert!(puzzle.metadata.is_empty() && puzzle.constraints.is_empty() && puzzle.config.is_empty());
+  }
+
+  #[test]
+  fn adding_a_word_builds_on_the_last_results() {
+    let blank = grid(["A  ", "   ", "  B", "C D"]);
+    let words = word_list(["axyzb"]);
+    let first = solved(&SolveOptions::new(), &blank, words.as_slice());
+    let previous = texts(first.solutions.as_slice());
+    let fresh = word_list(["axyzb", "cqd"]);
+    let names: Vec<&str> = fresh.iter().map(|w| w.as_slice()).collect();
+    let endpoints = build_endpoint_map(names.as_slice(), &hashgrid(blank.clone())).ok().unwrap();
+    let result = resolve_words(&SolveOptions::new(), &mut Diagnostics::new(), &blank, &endpoints, words.as_slice(), previous.as_slice(), fresh.as_slice());
+    // only the new word was searched, once on each of the old results
+    assert_eq!(result.word_stats.len(), 1);
+    assert_eq!(result.word_stats.get(0).word, "cqd");
+    assert_eq!(result.word_stats.get(0).grids_in, previous.len());
+    let mut incremental = texts(result.solutions.as_slice());
+    let mut from_scratch = texts(solved(&SolveOptions::new(), &blank, fresh.as_slice()).solutions.as_slice());
+    incremental.sort();
+    from_scratch.sort();
+    assert_eq!(incremental, from_scratch);
+  }
+
+  #[test]
+  fn removing_a_word_solves_from_scratch() {
+    let blank = grid(["A  ", "   ", "  B", "C D"]);
+    let words = word_list(["axyzb", "cqd"]);
+    let previous = texts(solved(&SolveOptions::new(), &blank, words.as_slice()).solutions.as_slice());
+    let fresh = word_list(["axyzb"]);
+    let names: Vec<&str> = fresh.iter().map(|w| w.as_slice()).collect();
+    let endpoints = build_endpoint_map(names.as_slice(), &hashgrid(blank.clone())).ok().unwrap();
+    let result = resolve_words(&SolveOptions::new(), &mut Diagnostics::new(), &blank, &endpoints, words.as_slice(), previous.as_slice(), fresh.as_slice());
+    assert_eq!(result.word_stats.len(), 1);
+    assert_eq!(result.word_stats.get(0).grids_in, 1);
+    assert!(texts(result.solutions.as_slice()).iter().all(|t| !t.as_slice().contains("cqd")));
+  }
+
+  #[test]
+  fn isolate_anchors_drops_paths_that_hug_another_waypoint() {
+    let blank = grid(["A  ", "C  ", "  B"]);
+    let words = word_list(["axyzb"]);
+    let mut opts = SolveOptions::new();
+    assert_eq!(solved(&opts, &blank, words.as_slice()).solutions.len(), 3);
+    opts.isolate_anchors = true;
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 1);
+    assert!(*result.solutions.get(0).get_ref(Point { x: 1, y: 1 }).unwrap() == NoWords);
+  }
+
+  #[test]
+  fn the_word_with_fewest_paths_is_searched_first() {
+    let blank = grid(["A  ", "   ", "  B", "C D"]);
+    let words = word_list(["axyzb", "cqd", "cpd"]);
+    let names: Vec<&str> = words.iter().map(|w| w.as_slice()).collect();
+    let endpoints = build_endpoint_map(names.as_slice(), &hashgrid(blank.clone())).ok().unwrap();
+    let order = CrosswordSolver::new(SolveOptions::new()).word_order(&blank, &endpoints, words.as_slice());
+    assert_eq!(order, vec!(1, 2, 0));
+  }
+
+  #[test]
+  fn every_solve_error_names_what_is_at_fault() {
+    let p = Point { x: 3, y: 4 };
+    let q = Point { x: 5, y: 6 };
+    let cases: Vec<(SolveError, Vec<&str>)> = vec!(
+      (BadFile(~"words.txt", ~"no such file"), vec!("words.txt", "no such file")),
+      (BadGrid(~"grid.txt", vec!(IsolatedAnchor('q', p), DisconnectedGrid(2))), vec!("grid.txt", "Q at 3,4", "2 separate regions")),
+      (DuplicateWaypoint('e', p, q), vec!("E", "3,4", "5,6")),
+      (MissingWaypoints(MissingEndpoints(vec!((~"zebra", vec!('z'))))), vec!("\"zebra\"", "Z")),
+      (MissingWaypoints(NoWaypoints(vec!(~"denim"))), vec!("denim")),
+      (DepthExceeded(~"giraffe", 12), vec!("\"giraffe\"", "12")),
+      (OutsideGrid(~"--within corner", p, 3, 3), vec!("--within corner", "3,4", "3x3"))
+    );
+    for &(ref error, ref details) in cases.iter() {
+      let message = error.to_str();
+      for detail in details.iter() {
+        assert!(message.contains(*detail), "\"{}\" doesn't mention {}", message, *detail);
+      }
+    }
+  }
+
+  #[test]
+  fn mixed_case_words_solve_on_an_uppercase_grid() {
+    let blank = grid(["C T", "D G"]);
+    let words = parsed_words(["CaT", "dOG"], &mut Diagnostics::new());
+    assert_eq!(words, vec!(~"cat", ~"dog"));
+    let result = solved(&SolveOptions::new(), &blank, words.as_slice());
+    assert_eq!(result.stuck_on, None);
+    assert_eq!(result.solutions.len(), 1);
+    assert!(ALPHABET.same('A', 'a') && ALPHABET.same('g', 'G') && !ALPHABET.same('a', 'b'));
+  }
+
+  #[test]
+  fn a_prefilled_cell_has_a_word_routed_through_it() {
+    let blank = prefilled_grid(["A  ", " x ", "  B"]);
+    let words = word_list(["apxqb"]);
+    let mut opts = SolveOptions::new();
+    opts.prefilled = true;
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 4);
+    for solution in result.solutions.iter() {
+      assert!(*solution.get_ref(Point { x: 1, y: 1 }).unwrap() == OneWord('x', "apxqb"));
+    }
+  }
+
+  #[test]
+  #[should_fail]
+  fn the_paranoid_check_catches_a_misspelled_placement() {
+    let blank: CrosswordGrid = grid(["A B"]);
+    let bad = blank.replace(Point { x: 1, y: 0 }, OneWord('x', "ayb"));
+    check_placement(&bad, &blank, "ayb", &SolveOptions::new());
+  }
+
+  #[test]
+  #[should_fail]
+  fn the_paranoid_check_catches_a_wall_written_over() {
+    let blank: CrosswordGrid = grid(["A#B", "   "]);
+    let bad = blank.replace(Point { x: 1, y: 0 }, OneWord('x', "axb"));
+    check_placement(&bad, &blank, "axb", &SolveOptions::new());
+  }
+
+  #[test]
+  fn the_paranoid_check_passes_a_good_placement() {
+    let blank: CrosswordGrid = grid(["A B"]);
+    let good = blank.replace(Point { x: 1, y: 0 }, OneWord('x', "axb"));
+    check_placement(&good, &blank, "axb", &SolveOptions::new());
+  }
+
+  #[test]
+  fn a_fixed_path_keeps_only_the_solutions_that_follow_it() {
+    let blank = grid(["A C", "   ", "D B"]);
+    let words = word_list(["axmzb", "cpmrd"]);
+    let mut opts = SolveOptions::new();
+    assert_eq!(solved(&opts, &blank, words.as_slice()).solutions.len(), 2);
+    let path = vec!(Point { x: 1, y: 0 }, Point { x: 1, y: 1 }, Point { x: 1, y: 2 });
+    opts.fixed_paths.push((~"axmzb", path.clone()));
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 1);
+    let solution = result.solutions.get(0);
+    assert_eq!(word_path(solution, "axmzb", &opts), path);
+    assert_eq!(word_path(solution, "cpmrd", &opts), vec!(Point { x: 2, y: 1 }, Point { x: 1, y: 1 }, Point { x: 0, y: 1 }));
+  }
+
+  #[test]
+  fn flips_mirror_each_axis() {
+    // a b c
+    // d e f
+    let g = Grid { width: 3, height: 2, tiles: vec!('a', 'b', 'c', 'd', 'e', 'f') };
+    assert_eq!(g.flip_h().tiles, vec!('c', 'b', 'a', 'f', 'e', 'd'));
+    assert_eq!(g.flip_v().tiles, vec!('d', 'e', 'f', 'a', 'b', 'c'));
+    assert_eq!(g.flip_h().flip_h().tiles, g.tiles);
+    assert_eq!(g.flip_v().flip_v().tiles, g.tiles);
+    let (h, v) = (g.flip_h(), g.flip_v());
+    assert_eq!((h.width, h.height, v.width, v.height), (3, 2, 3, 2));
+  }
+
+  #[test]
+  fn each_solution_lists_the_optional_words_it_placed() {
+    let blank = grid(["A B", "C D"]);
+    let words = word_list(["axb", "cyd"]);
+    let mut opts = SolveOptions::new();
+    opts.optional.push(~"axb");
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 2);
+    assert_eq!(result.optional_words.len(), 2);
+    for (solution, &(ref placed, ref omitted)) in result.solutions.iter().zip(result.optional_words.iter()) {
+      if *solution.get_ref(Point { x: 1, y: 0 }).unwrap() == OneWord('x', "axb") {
+        assert_eq!((placed.clone(), omitted.len()), (vec!("axb"), 0));
+      } else {
+        assert_eq!((placed.len(), omitted.clone()), (0, vec!("axb")));
+      }
+    }
+    assert!(result.optional_words.iter().any(|&(ref p, _)| p.is_empty()));
+    assert!(result.optional_words.iter().any(|&(_, ref o)| o.is_empty()));
+  }
+
+  #[test]
+  fn a_low_depth_cap_is_an_error() {
+    let blank = grid(["A  ", "   ", "  B"]);
+    let words = word_list(["axyzb"]);
+    let mut opts = SolveOptions::new();
+    opts.max_depth = Some(4);
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 6);
+    assert!(result.error(&opts).is_none());
+    opts.max_depth = Some(3);
+    let result = solved(&opts, &blank, words.as_slice());
+    match result.error(&opts) {
+      Some(DepthExceeded(word, cap)) => assert_eq!((word, cap), (~"axyzb", 3)),
+      other => fail!("expected DepthExceeded, got {}", other.map(|e| e.to_str()))
+    }
+  }
+
+  #[test]
+  fn a_saved_session_carries_on_like_one_that_never_stopped() {
+    let blank = grid(["A C", "   ", "D B"]);
+    let words = word_list(["axmzb", "cpmrd"]);
+    let names: Vec<&str> = words.iter().map(|w| w.as_slice()).collect();
+    let endpoints = build_endpoint_map(names.as_slice(), &hashgrid(blank.clone())).ok().unwrap();
+    let (opts, mut diag) = (SolveOptions::new(), Diagnostics::new());
+    let mut session = Session::new(&blank, "solve");
+    assert_eq!(session.push_word(&opts, &mut diag, &blank, &endpoints, ~"axmzb"), Ok(4));
+    let dir = TempDir::new("session").unwrap();
+    let path = dir.path().join("session");
+    let file = path.as_str().unwrap();
+    session.save(file);
+    let mut restored = Session::load(file).unwrap();
+    assert_eq!(session.push_word(&opts, &mut diag, &blank, &endpoints, ~"cpmrd"), Ok(2));
+    assert_eq!(restored.push_word(&opts, &mut diag, &blank, &endpoints, ~"cpmrd"), Ok(2));
+    assert_eq!(restored.words, session.words);
+    assert_eq!(restored.levels, session.levels);
+    assert_eq!((restored.undo(), session.undo()), (Some(~"cpmrd"), Some(~"cpmrd")));
+    assert_eq!(restored.solutions(), session.solutions());
+    assert_eq!(restored.solutions().len(), 4);
+  }
+
+  #[test]
+  fn a_word_that_doesnt_fit_leaves_the_session_alone() {
+    let blank = grid(["A C", "   ", "D B"]);
+    let words = word_list(["axmzb", "cqd"]);
+    let names: Vec<&str> = words.iter().map(|w| w.as_slice()).collect();
+    let endpoints = build_endpoint_map(names.as_slice(), &hashgrid(blank.clone())).ok().unwrap();
+    let (opts, mut diag) = (SolveOptions::new(), Diagnostics::new());
+    let mut session = Session::new(&blank, "solve");
+    assert_eq!(session.push_word(&opts, &mut diag, &blank, &endpoints, ~"axmzb"), Ok(4));
+    assert!(session.push_word(&opts, &mut diag, &blank, &endpoints, ~"cqd").is_err());
+    assert_eq!(session.words, vec!(~"axmzb"));
+    assert_eq!(session.levels.len(), 1);
+  }
+
+  #[test]
+  fn a_session_takes_back_only_the_words_that_changed() {
+    let blank = grid(["A C", "   ", "D B"]);
+    let words = word_list(["axmzb", "cpmrd"]);
+    let names: Vec<&str> = words.iter().map(|w| w.as_slice()).collect();
+    let endpoints = build_endpoint_map(names.as_slice(), &hashgrid(blank.clone())).ok().unwrap();
+    let (opts, mut diag) = (SolveOptions::new(), Diagnostics::new());
+    let dir = TempDir::new("session").unwrap();
+    let path = dir.path().join("session");
+    let file = path.as_str().unwrap();
+    let both = session_solutions(&opts, file, "solve", &blank, &endpoints, words.as_slice(), &mut diag).unwrap();
+    assert_eq!(both.len(), 2);
+    let first = session_solutions(&opts, file, "solve", &blank, &endpoints, words.slice_to(1), &mut diag).unwrap();
+    assert_eq!(first.len(), 4);
+    let saved = Session::load(file).unwrap();
+    assert_eq!(saved.words, vec!(~"axmzb"));
+    let again = session_solutions(&opts, file, "solve", &blank, &endpoints, words.as_slice(), &mut diag).unwrap();
+    assert_eq!(texts(again.as_slice()), texts(both.as_slice()));
+    let other = session_solutions(&opts, file, "solve --hex", &blank, &endpoints, words.slice_to(1), &mut diag).unwrap();
+    assert_eq!(other.len(), 4);
+    assert_eq!(Session::load(file).unwrap().command, ~"solve --hex");
+  }
+
+  #[test]
+  fn legacy_output_is_only_the_merged_grid_and_each_word() {
+    let blank = grid(["A  ", "   ", "  B", "C D"]);
+    let words = word_list(["axyzb", "cqd"]);
+    let opts = SolveOptions::new();
+    let result = solved(&opts, &blank, words.as_slice());
+    let plan = OutputPlan { best: Some(1), symbols: true, per_word: true, per_iteration: true, added: false, parity: false, orientation: Orientation { transpose: false, turns: 0 }, legacy: true };
+    let mut diag = Diagnostics::new();
+    let out = render_results(&blank, &result.solutions, words.as_slice(), &opts, &plan, [], &mut diag);
+    let mut expected = StrBuf::new();
+    expected.push_str(format!("{}\n", result.solutions.flatten()));
+    for w in words.iter() {
+      expected.push_str(format!("Showing only \"{}\":\n{}\n", *w, result.solutions.flattenWord(*w)));
+    }
+    assert_eq!(out, expected.into_owned());
+  }
+
+  #[test]
+  fn crossing_distribution_counts_each_solution_once() {
+    let blank = grid(["A  ", "   ", "  B"]);
+    let one = blank.replace(Point { x: 1, y: 1 }, TwoWords('m', "axmzb", "cpmrd"));
+    let other = blank.replace(Point { x: 0, y: 1 }, TwoWords('x', "axmzb", "dxd"));
+    let two = one.replace(Point { x: 2, y: 1 }, TwoWords('z', "axmzb", "rzr"));
+    let solutions = vec!(blank.clone(), one, other, two, blank.replace(Point { x: 1, y: 0 }, OneWord('x', "axmzb")));
+    let counts = crossing_distribution(solutions.as_slice());
+    assert_eq!(counts.values().fold(0, |sum, n| sum + *n), solutions.len());
+    assert_eq!(counts.len(), 3);
+    assert_eq!((counts.find(&0), counts.find(&1), counts.find(&2)), (Some(&2), Some(&2), Some(&1)));
+  }
+
+  #[test]
+  fn letters_outside_ascii_take_one_cell_each() {
+    let blank = grid(["A B"]);
+    let words = word_list(["a\u00e9b"]);
+    let result = solved(&SolveOptions::new(), &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 1);
+    assert_eq!(*result.solutions.get(0).get_ref(Point { x: 1, y: 0 }).unwrap(), OneWord('\u00e9', "a\u00e9b"));
+    // two words crossing on the same accented letter
+    let blank = grid(["A C", "   ", "D B"]);
+    let words = word_list(["ax\u00e9zb", "cp\u00e9rd"]);
+    let result = solved(&SolveOptions::new(), &blank, words.as_slice());
+    assert!(result.stuck_on.is_none());
+    assert_eq!(result.solutions.len(), 2);
+    for solution in result.solutions.iter() {
+      match *solution.get_ref(Point { x: 1, y: 1 }).unwrap() {
+        TwoWords(c, _, _) => assert_eq!(c, '\u00e9'),
+        other => fail!("expected the words to cross on \u00e9, got {:?}", other)
+      }
+    }
+  }
+
+  fn plan_for(args: &[&str]) -> Result<OutputPlan, ~str> {
+    let args: Vec<~str> = args.iter().map(|a| a.to_owned()).collect();
+    parse_output_plan(&getopts(args.as_slice(), output_flags()).unwrap())
+  }
+
+  #[test]
+  fn rotate_takes_only_a_quarter_turn_or_more() {
+    for &(degrees, turns) in [("90", 1u), ("180", 2), ("270", 3)].iter() {
+      assert_eq!(plan_for(["--rotate", degrees]).unwrap().orientation.turns, turns);
+    }
+    assert_eq!(plan_for([]).unwrap().orientation.turns, 0);
+    for bad in ["0", "360", "45", "-90", "90x", "", " 90", "+90", "090", "1e2", "\u0669\u0660"].iter() {
+      match plan_for([format!("--rotate={}", *bad).as_slice()]) {
+        Err(e) => assert!(e.contains("--rotate"), "{}", e),
+        Ok(_) => fail!("--rotate {} was accepted", *bad)
+      }
+    }
+    // every other whole number of degrees up to a full turn
+    for n in range(0u, 361).filter(|n| *n != 90 && *n != 180 && *n != 270) {
+      assert!(plan_for(["--rotate", n.to_str().as_slice()]).is_err());
+    }
+  }
+
+  #[test]
+  fn best_takes_a_count_of_at_least_one() {
+    assert_eq!(plan_for(["--best", "3"]).unwrap().best, Some(3));
+    assert_eq!(plan_for([]).unwrap().best, None);
+    assert_eq!(plan_for(["--best", "0"]).err(), Some(~"--best must be at least 1"));
+    for bad in ["-1", "x", "", "1.5", "99999999999999999999999"].iter() {
+      match plan_for([format!("--best={}", *bad).as_slice()]) {
+        Err(e) => assert!(e.contains("--best"), "{}", e),
+        Ok(_) => fail!("--best {} was accepted", *bad)
+      }
+    }
+  }
+
+  #[test]
+  fn numeric_search_flags_err_cleanly_or_solve() {
+    let blank = grid(["A  ", "   ", "  B"]);
+    let words = word_list(["axyzb"]);
+    let flags = ["beam", "max-nodes", "max-depth", "roi-border", "max-word-len", "timeout", "word-timeout"];
+    let values = ["0", "1", "2", "5", "-1", "x", "", "0.5", "nan", "inf", "-inf", "1e300", "4294967296", "18446744073709551616"];
+    for flag in flags.iter() {
+      for value in values.iter() {
+        let args = vec!(format!("--{}={}", *flag, *value));
+        let matches = getopts(args.as_slice(), search_flags()).unwrap();
+        match parse_solve_options(&matches) {
+          Err(e) => assert!(e.contains(*flag), "--{}={}: {}", *flag, *value, e),
+          Ok(opts) => {
+            let result = solved(&opts, &blank, words.as_slice());
+            assert!(result.solutions.len() <= 6, "--{}={}", *flag, *value);
+          }
+        }
+      }
+    }
+  }
+
+  #[test]
+  fn fill_ratio_counts_only_free_cells() {
+    let blank = grid(["A   B", " ####"]);
+    let words = word_list(["axyzb"]);
+    let result = solved(&SolveOptions::new(), &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 1);
+    assert_eq!(fill_ratio(result.solutions.get(0)), 0.75);
+    assert_eq!(fill_ratio(&blank), 0.0);
+  }
+
+  #[test]
+  fn subcommands_get_the_arguments_after_their_name() {
+    let args = word_list(["solve", "solve", "-a", "8", "grid", "words"]);
+    assert_eq!(route(args.as_slice()), ("solve", args.slice_from(2)));
+    let args = word_list(["solve", "validate", "--max-word-len", "9", "puzzle"]);
+    assert_eq!(route(args.as_slice()), ("validate", args.slice_from(2)));
+    // a command line that doesn't name one is all solve's
+    let args = word_list(["solve", "grid", "words"]);
+    assert_eq!(route(args.as_slice()), ("solve", args.slice_from(1)));
+    let args = word_list(["solve", "--best", "3", "validate", "words"]);
+    assert_eq!(route(args.as_slice()), ("solve", args.slice_from(1)));
+    let args = word_list(["solve"]);
+    assert_eq!(route(args.as_slice()), ("solve", args.slice_from(1)));
+  }
+
+  #[test]
+  fn an_all_blank_grid_solves_words_with_coordinates() {
+    let mut diag = Diagnostics::new();
+    let opts = SolveOptions::new();
+    let (blank, _, words) = checked("blank", grid(["   ", "   ", "   "]), None, word_list(["axyzb 0,0 2,2"]), &opts, &mut diag).ok().unwrap();
+    assert_eq!(words, vec!(~"axyzb"));
+    let endpoints = word_endpoints(&hashgrid(blank.clone()), words.as_slice()).ok().unwrap();
+    assert_eq!(endpoints, vec!((Point { x: 0, y: 0 }, Point { x: 2, y: 2 })));
+    let wordpts: Vec<(&str, &(Point, Point))> = words.iter().map(|w| w.as_slice()).zip(endpoints.iter()).collect();
+    assert_eq!(solve(&opts, &mut diag, &blank, wordpts.as_slice()).solutions.len(), 6);
+  }
+
+  #[test]
+  fn an_all_blank_grid_names_the_words_that_need_coordinates() {
+    let mut diag = Diagnostics::new();
+    let (blank, _, words) = checked("blank", grid(["   ", "   ", "   "]), None, word_list(["axyzb", "cqd"]), &SolveOptions::new(), &mut diag).ok().unwrap();
+    match word_endpoints(&hashgrid(blank.clone()), words.as_slice()) {
+      Err(MissingWaypoints(NoWaypoints(missing))) => assert_eq!(missing, vec!(~"cqd", ~"axyzb")),
+      Err(e) => fail!("expected NoWaypoints, got {}", e),
+      Ok(_) => fail!("found waypoints on a blank grid")
+    }
+  }
+
+  #[test]
+  fn dedup_by_word_paths_keeps_solutions_that_only_look_the_same() {
+    // the two words spell the same letters around the middle, so swapping
+    // which word takes which side draws the same grid
+    let blank = grid(["A C", "   ", "D B"]);
+    let words = word_list(["axmxb", "cxmxd"]);
+    let mut opts = SolveOptions::new();
+    let all = solved(&opts, &blank, words.as_slice()).solutions.len();
+    opts.dedup = ByShape;
+    let by_shape = solved(&opts, &blank, words.as_slice()).solutions.len();
+    opts.dedup = ByWordPaths;
+    let by_paths = solved(&opts, &blank, words.as_slice()).solutions.len();
+    assert!(by_shape < by_paths, "{} by shape, {} by paths", by_shape, by_paths);
+    assert_eq!(by_paths, all);
+  }
+
+  #[test]
+  fn equal_scores_come_out_in_the_same_order_whatever_order_they_went_in() {
+    let blank = grid(["A  ", "   ", "  B"]);
+    let words = word_list(["axyzb"]);
+    let found = solved(&SolveOptions::new(), &blank, words.as_slice()).solutions;
+    assert_eq!(found.len(), 6);
+    assert!(found.iter().all(|g| score(g) == score(found.get(0))));
+    let first = texts(order_solutions(BestScore, DEFAULT_TIE_BREAK.as_slice(), found.clone()).as_slice());
+    for k in range(0, found.len()) {
+      let mut shuffled: Vec<CrosswordGrid> = found.slice_from(k).iter().chain(found.slice_to(k).iter()).map(|g| g.clone()).collect();
+      assert_eq!(texts(order_solutions(BestScore, DEFAULT_TIE_BREAK.as_slice(), shuffled.clone()).as_slice()), first);
+      shuffled.reverse();
+      assert_eq!(texts(order_solutions(BestScore, DEFAULT_TIE_BREAK.as_slice(), shuffled).as_slice()), first);
+    }
+    // the routes straight through the middle row or column have the smallest
+    // box, and the rest go by their text
+    let ordered = order_solutions(BestScore, DEFAULT_TIE_BREAK.as_slice(), found.clone());
+    let areas: Vec<uint> = ordered.iter().map(|g| word_box_area(g)).collect();
+    assert_eq!(areas, vec!(3, 3, 4, 4, 4, 4));
+    assert!(first.slice_from(2).windows(2).all(|w| w[0] < w[1]));
+    let mut sorted = first.clone();
+    sorted.sort();
+    assert_eq!(texts(order_solutions(BestScore, [CanonicalText], found).as_slice()), sorted);
+  }
+
+  #[test]
+  fn added_cells_shows_only_what_the_search_filled_in() {
+    let blank = grid(["A  B", "#   "]);
+    let words = word_list(["axyb"]);
+    let result = solved(&SolveOptions::new(), &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 1);
+    let added = added_cells(&blank, result.solutions.get(0));
+    assert_eq!((added.width, added.height), (4, 2));
+    assert_eq!(added.tiles, vec!(' ', 'x', 'y', ' ', ' ', ' ', ' ', ' '));
+  }
+
+  #[test]
+  fn a_word_carries_on_from_a_fragment_with_no_waypoint() {
+    let mut opts = SolveOptions::new();
+    opts.prefilled = true;
+    let mut diag = Diagnostics::new();
+    let (blank, _, words) = checked("fragment", prefilled_grid(["A  de", "    #"]), None, word_list(["axyde"]), &opts, &mut diag).ok().unwrap();
+    // the fragment's open end becomes the word's last waypoint
+    assert_eq!(*blank.get_ref(Point { x: 4, y: 0 }).unwrap(), Fixed('e'));
+    let endpoints = word_endpoints(&hashgrid(blank.clone()), words.as_slice()).ok().unwrap();
+    let wordpts: Vec<(&str, &(Point, Point))> = words.iter().map(|w| w.as_slice()).zip(endpoints.iter()).collect();
+    let result = solve(&opts, &mut diag, &blank, wordpts.as_slice());
+    assert_eq!(result.solutions.len(), 1);
+    let solution = result.solutions.get(0);
+    assert_eq!(*solution.get_ref(Point { x: 3, y: 0 }).unwrap(), OneWord('d', "axyde"));
+  }
+
+  #[test]
+  fn each_iteration_keeps_at_most_its_cap_and_repeats_arent_new() {
+    let blank = grid(["A  ", "   ", "  B"]);
+    let words = word_list(["axyzb"]);
+    let found = solved(&SolveOptions::new(), &blank, words.as_slice()).solutions;
+    assert_eq!(found.len(), 6);
+    let mut plan = IterationPlan::new(1);
+    plan.cap = Some(2);
+    let mut first = found.clone();
+    assert_eq!(plan.take(&mut first), (true, vec!(true, true)));
+    assert_eq!(texts(first.as_slice()), texts(found.slice_to(2)));
+    // a later iteration finding them the other way round keeps its own first two
+    let mut second = found.clone();
+    second.reverse();
+    assert_eq!(plan.take(&mut second), (true, vec!(true, true)));
+    assert_eq!(second.len(), 2);
+    let mut third = found.clone();
+    assert_eq!(plan.take(&mut third), (true, vec!(false, false)));
+    // under the cap, nothing is cut
+    let mut fourth = Vec::from_slice(found.slice(2, 4));
+    assert_eq!(plan.take(&mut fourth), (false, vec!(true, true)));
+    assert_eq!(plan.yields, vec!(2, 2, 0, 2));
+    assert_eq!(plan.seen.len(), 6);
+  }
+
+  // Tiny puzzles and how many results each has, worked out by hand: the grid,
+  // the words, whether words move eight ways instead of four, and the count.
+  static FIXTURES: [(&'static [&'static str], &'static [&'static str], bool, uint), ..10] = [
+    // the six shortest routes across a 3x3 box
+    (&["A  ", "   ", "  B"], &["axyzb"], false, 6),
+    // a king's route: two cells, the first next to A and the second to B
+    (&["A  ", "   ", "  B"], &["axyb"], true, 6),
+    // straight through the middle
+    (&["A  ", "   ", "  B"], &["axb"], true, 1),
+    // each route of one word takes a side the other needs, except two
+    (&["A C", "   ", "D B"], &["axmzb", "cpmrd"], false, 2),
+    // the same, but every letter agrees, so any pair of routes can share
+    (&["A C", "   ", "D B"], &["axmxb", "cxmxd"], false, 16),
+    (&["A  B", "#   "], &["axyb"], false, 1),
+    (&["A   B", " ####"], &["axyzb"], false, 1),
+    (&["A B", "C D"], &["axb", "cyd"], false, 1),
+    (&["A B", "   ", "C D"], &["axb", "cyd"], false, 1),
+    (&["A  ", "   ", "  B", "C D"], &["axyzb", "cqd"], false, 6)
+  ];
+
+  #[test]
+  fn the_search_and_the_brute_force_agree_on_every_fixture() {
+    for &(rows, listed, eight, count) in FIXTURES.iter() {
+      let blank = grid(rows);
+      let words = word_list(listed);
+      let opts = if eight { SolveOptions::with_offsets(Vec::from_slice(EIGHT_WAYS.as_slice())) } else { SolveOptions::new() };
+      let result = solved(&opts, &blank, words.as_slice());
+      assert!(result.stuck_on.is_none(), "{:?}", listed);
+      assert_eq!((listed, result.solutions.len()), (listed, count));
+      let mut searched = texts(result.solutions.as_slice());
+      searched.sort();
+      let paths = word_endpoints(&hashgrid(blank.clone()), words.as_slice()).ok().unwrap();
+      let wordpts: Vec<(&str, &(Point, Point))> = words.iter().map(|w| w.as_slice()).zip(paths.iter()).collect();
+      assert_eq!((listed, brute_force(&blank, wordpts.as_slice(), &opts)), (listed, searched));
+    }
+  }
+
+  #[test]
+  fn parity_marks_alternate_along_each_word() {
+    let blank = grid(["A   ", "    ", "    ", "   B"]);
+    let words = word_list(["axyzwvb"]);
+    let opts = SolveOptions::new();
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 20);
+    for solution in result.solutions.iter() {
+      let marks = parity_cells(solution, words.as_slice(), &opts);
+      let path = word_path(solution, "axyzwvb", &opts);
+      assert_eq!(path.len(), 5);
+      let along: Vec<char> = path.iter().map(|&p| *marks.get_ref(p).unwrap()).collect();
+      assert_eq!(along, vec!('x', 'o', 'x', 'o', 'x'));
+      assert_eq!(marks.tiles.iter().filter(|&&c| c == 'x' || c == 'o').count(), 5);
+    }
+  }
+
+  fn hex_options() -> SolveOptions {
+    let mut opts = SolveOptions::with_offsets(Vec::from_slice(EIGHT_WAYS.as_slice()));
+    opts.topology = Hex;
+    opts
+  }
+
+  #[test]
+  fn a_word_path_on_a_hex_grid_follows_hex_neighbors() {
+    // (1,1) is a king's move from the a at (0,2) but not a hex neighbor of
+    // it, so on hex the walk has to start from (1,2)
+    let blank = grid([" B", "  ", "A "]);
+    let solution = blank.replace(Point { x: 1, y: 1 }, OneWord('x', "axxb")).replace(Point { x: 1, y: 2 }, OneWord('x', "axxb"));
+    let square = SolveOptions::with_offsets(Vec::from_slice(EIGHT_WAYS.as_slice()));
+    assert_eq!(word_path(&solution, "axxb", &square), vec!(Point { x: 1, y: 1 }, Point { x: 1, y: 2 }));
+    assert_eq!(word_path(&solution, "axxb", &hex_options()), vec!(Point { x: 1, y: 2 }, Point { x: 1, y: 1 }));
+  }
+
+  #[test]
+  fn a_fixed_path_on_a_hex_grid_takes_only_hex_steps() {
+    let opts = hex_options();
+    let start = Point { x: 0, y: 2 };
+    let end = Point { x: 1, y: 0 };
+    assert!(check_fixed_path(&opts, "axxb", start, end, [Point { x: 1, y: 2 }, Point { x: 1, y: 1 }]).is_ok());
+    // a diagonal the king graph has but the hex grid doesn't
+    assert!(check_fixed_path(&opts, "axxb", start, end, [Point { x: 1, y: 1 }, Point { x: 1, y: 2 }]).is_err());
+  }
+
+  #[test]
+  fn merging_grids_lays_words_on_different_cells_side_by_side() {
+    let blank = grid(["A B", "   ", "C D"]);
+    let top = blank.replace(Point { x: 1, y: 0 }, OneWord('x', "axb"));
+    let bottom = blank.replace(Point { x: 1, y: 2 }, OneWord('y', "cyd"));
+    let merged = merge_grids(&top, &bottom).unwrap();
+    assert!(merged == top.replace(Point { x: 1, y: 2 }, OneWord('y', "cyd")));
+    assert!(merge_grids(&bottom, &top).unwrap() == merged);
+  }
+
+  #[test]
+  fn merging_grids_crosses_words_on_a_shared_letter() {
+    let blank = grid(["A C", "   ", "D B"]);
+    let across = blank.replace(Point { x: 1, y: 1 }, OneWord('m', "axmzb"));
+    let down = blank.replace(Point { x: 1, y: 1 }, OneWord('m', "cpmrd"));
+    let merged = merge_grids(&across, &down).unwrap();
+    assert_eq!(*merged.get_ref(Point { x: 1, y: 1 }).unwrap(), TwoWords('m', "axmzb", "cpmrd"));
+  }
+
+  #[test]
+  fn merging_grids_refuses_cells_that_disagree() {
+    let blank = grid(["A C", "   ", "D B"]);
+    let across = blank.replace(Point { x: 1, y: 1 }, OneWord('m', "axmzb"));
+    let down = blank.replace(Point { x: 1, y: 1 }, OneWord('q', "cpqrd"));
+    assert!(merge_grids(&across, &down).is_none());
+    // a third word can't join a cell two words already share
+    let crossed = merge_grids(&across, &blank.replace(Point { x: 1, y: 1 }, OneWord('m', "cpmrd"))).unwrap();
+    assert!(merge_grids(&crossed, &blank.replace(Point { x: 1, y: 1 }, OneWord('m', "emf"))).is_none());
+  }
+
+  #[test]
+  fn merging_grids_refuses_a_word_in_two_places() {
+    let blank = grid(["A B", "   ", "   "]);
+    let high = blank.replace(Point { x: 1, y: 0 }, OneWord('x', "axb"));
+    let low = blank.replace(Point { x: 1, y: 1 }, OneWord('x', "axb"));
+    assert!(merge_grids(&high, &low).is_none());
+    assert!(merge_grids(&high, &high).unwrap() == high);
+  }
+
+  #[test]
+  fn merging_grids_of_different_sizes_fails() {
+    assert!(merge_grids(&grid(["A B"]), &grid(["A B", "   "])).is_none());
+  }
+
+  // A at 0,0 and B at 2,0 on a grid three wide and two high, so every turn
+  // of it looks different
+  fn oriented_results(orientation: Orientation) -> ~str {
+    let blank = grid(["A B", "###"]);
+    let results = vec!(blank.replace(Point { x: 1, y: 0 }, OneWord('x', "axb")));
+    let plan = OutputPlan { best: None, symbols: false, per_word: false, per_iteration: false, added: false, parity: false, orientation: orientation, legacy: false };
+    render_results(&blank, &results, word_list(["axb"]).as_slice(), &SolveOptions::new(), &plan, [], &mut Diagnostics::new())
+  }
+
+  #[test]
+  fn an_oriented_grid_is_drawn_and_listed_the_way_it_is_turned() {
+    let cases = [
+      (false, 0u, (3u, 2u), " 0,0 ", " 2,0 "),
+      (false, 1, (2, 3), " 1,0 ", " 1,2 "),
+      (false, 2, (3, 2), " 2,1 ", " 0,1 "),
+      (false, 3, (2, 3), " 0,2 ", " 0,0 "),
+      (true, 0, (2, 3), " 0,0 ", " 0,2 "),
+      (true, 1, (3, 2), " 2,0 ", " 0,0 ")
+    ];
+    for &(transpose, turns, (width, height), start, end) in cases.iter() {
+      let out = oriented_results(Orientation { transpose: transpose, turns: turns });
+      let drawn: Vec<&str> = out.lines().take_while(|line| !line.starts_with("+")).collect();
+      assert_eq!(drawn.len(), height);
+      assert!(drawn.iter().all(|line| line.char_len() == width), "{}", out);
+      let row: Vec<&str> = out.lines().filter(|line| line.starts_with("| axb")).collect();
+      assert_eq!(row.len(), 1);
+      assert!(row.get(0).contains(start) && row.get(0).contains(end), "{} for transpose {} turns {}", *row.get(0), transpose, turns);
+    }
+  }
+
+  #[test]
+  fn the_summary_says_how_the_grid_was_turned_but_the_certificate_keeps_its_coordinates() {
+    let dir = TempDir::new("orientation").unwrap();
+    write_file(dir.path(), "puzzle.grid", "A B\n###\n");
+    write_file(dir.path(), "puzzle.words", "axb\n");
+    let at = |name: &str| dir.path().join(name).as_str().unwrap().to_owned();
+    let mut certificates = Vec::new();
+    for &(flags, described) in [("", "as given"), ("--rotate=90", "rotated 90"), ("--transpose --rotate=270", "transposed, then rotated 270")].iter() {
+      let mut args: Vec<~str> = flags.words().map(|f| f.to_owned()).collect();
+      args.push_all([~"--summary", at("summary.json"), ~"--certificate", at("cert.json"), at("puzzle.grid"), at("puzzle.words")]);
+      solve_command(args.as_slice());
+      let summary = File::open(&dir.path().join("summary.json")).read_to_str().unwrap();
+      match json::from_str(summary) {
+        Ok(json::Object(obj)) => assert_eq!(obj.find(&~"orientation"), Some(&json::String(described.to_owned()))),
+        other => fail!("expected a JSON object, got {:?}", other)
+      }
+      certificates.push(File::open(&dir.path().join("cert.json")).read_to_str().unwrap());
+    }
+    assert!(certificates.iter().all(|cert| *cert == *certificates.get(0)));
+  }
+
+  #[test]
+  fn independent_groups_combine_into_every_pairing() {
+    // a wall down the middle keeps each word to its own side, and each has
+    // the six ways across a three by three square
+    let blank = grid(["A  #C  ", "   #   ", "  B#  D"]);
+    let words = word_list(["axyzb", "cpqrd"]);
+    let opts = SolveOptions::new();
+    let paths = word_endpoints(&hashgrid(blank.clone()), words.as_slice()).ok().unwrap();
+    let wordpts: Vec<(&str, &(Point, Point))> = words.iter().map(|w| w.as_slice()).zip(paths.iter()).collect();
+    assert_eq!(independent_groups(&blank, &opts, wordpts.as_slice()), vec!(vec!(0u), vec!(1u)));
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 36);
+    let mut searched = texts(result.solutions.as_slice());
+    searched.sort();
+    assert_eq!(brute_force(&blank, wordpts.as_slice(), &opts), searched);
+    let mut diag = Diagnostics::new();
+    solve(&opts, &mut diag, &blank, wordpts.as_slice());
+    assert!(diag.summary().contains("fall into 2 groups"), "{}", diag.summary());
+  }
+
+  fn within_options(spec: &str) -> Result<SolveOptions, ~str> {
+    let args = vec!(format!("--within={}", spec));
+    parse_solve_options(&getopts(args.as_slice(), search_flags()).unwrap())
+  }
+
+  // whether every cell a word takes is in the box from 0,0 to corner,corner
+  fn inside_box<'a>(grid: &CrosswordGrid<'a>, corner: int) -> bool {
+    grid.tiles.iter().enumerate().all(|(i, tile)| match *tile {
+      OneWord(..) | TwoWords(..) => i as int % grid.width <= corner && i as int / grid.width <= corner,
+      _ => true
+    })
+  }
+
+  #[test]
+  fn within_keeps_exactly_the_results_inside_its_box() {
+    let blank = grid(["A   ", "    ", "  B ", "    "]);
+    let words = word_list(["axyzwvb"]);
+    let opts = within_options("2,2;0,0").unwrap();
+    assert_eq!(opts.within, Some((Point { x: 0, y: 0 }, Point { x: 2, y: 2 })));
+    let everywhere = solved(&SolveOptions::new(), &blank, words.as_slice());
+    let mut expected: Vec<~str> = everywhere.solutions.iter().filter(|g| inside_box(*g, 2)).map(canonical_text).collect();
+    assert!(!expected.is_empty() && expected.len() < everywhere.solutions.len());
+    let mut boxed = texts(solved(&opts, &blank, words.as_slice()).solutions.as_slice());
+    expected.sort();
+    boxed.sort();
+    assert_eq!(boxed, expected);
+  }
+
+  #[test]
+  fn within_rejects_a_box_leaving_out_a_waypoint() {
+    let blank = grid(["A  ", "   ", "  B"]);
+    let opts = within_options("0,0;1,2").unwrap();
+    let errors = preflight(&blank, &opts, &mut Diagnostics::new()).unwrap_err();
+    assert!(errors.iter().any(|e| match *e { AnchorOutsideBox('b', Point { x: 2, y: 2 }) => true, _ => false }));
+    assert!(!errors.iter().any(|e| match *e { AnchorOutsideBox('a', _) => true, _ => false }));
+  }
+
+  #[test]
+  fn within_needs_two_corners() {
+    for bad in ["", "1,2", "1,2;3", "1,2;3,4;5,6", "a,b;c,d"].iter() {
+      match within_options(*bad) {
+        Err(e) => assert!(e.contains("--within"), "{}", e),
+        Ok(_) => fail!("--within {} was accepted", *bad)
+      }
+    }
+  }
+
+  #[test]
+  fn max_word_len_measures_the_word_as_parsed() {
+    assert_eq!(longest_word("the quick brown fox jumps over the lazy dog"), 43);
+    assert_eq!(longest_word("x-ray-vision"), 12);
+    assert_eq!(longest_word("denim 0,0 4,4"), 5);
+    assert_eq!(longest_word("colour / color"), 6);
+    assert_eq!(longest_word("ember->river"), 5);
+    let lines = ["x-ray-vision", "denim 0,0 4,4", "colour / color", "chain: ember -> river"];
+    let mut diag = Diagnostics::new();
+    let words = parsewords("test", 1, word_list(lines.as_slice()).as_slice(), 7, &mut diag);
+    assert_eq!(words, vec!(~"denim 0,0 4,4", ~"colour / color", ~"ember->river"));
+    assert!(diag.summary().contains("at 12 letters"), "{}", diag.summary());
+  }
+
+  #[test]
+  fn a_line_with_spaces_that_isnt_an_anchored_word_is_an_error() {
+    for bad in ["denim 0,0", "denim 0,0 4,4 9,9", "denim a,b 4,4", "ice cream"].iter() {
+      let mut diag = Diagnostics::new();
+      assert_eq!(parsed_words([*bad], &mut diag).len(), 0);
+      assert!(diag.summary().contains("WORD x,y x,y"), "{}: {}", *bad, diag.summary());
+    }
+  }
+
+  #[test]
+  fn is_planar_finds_the_grids_that_can_be_drawn_flat() {
+    let eight = SolveOptions::with_offsets(Vec::from_slice(EIGHT_WAYS.as_slice()));
+    let knight = SolveOptions::with_offsets(Vec::from_slice(KNIGHT_MOVES.as_slice()));
+    let square = grid(["A    ", "     ", "     ", "     ", "    B"]);
+    assert!(is_planar(&square, &SolveOptions::new()));
+    assert!(is_planar(&square, &hex_options()));
+    // 72 edges on 25 cells, past Euler's bound of 69
+    assert!(!is_planar(&square, &eight));
+    // with some of the diagonal moves drawn around the outside
+    assert!(is_planar(&grid(["A  ", "   ", "  B"]), &eight));
+    // 42 edges on 16 cells is right at the bound, and still can't be drawn
+    let four = grid(["A   ", "    ", "    ", "   B"]);
+    assert!(!is_planar(&four, &eight));
+    // and 24 knight moves is well under it
+    assert!(!is_planar(&four, &knight));
+    assert!(is_planar(&grid(["A   ", "    ", "   B"]), &knight));
+    // blocking two cells on the diagonal opens enough room
+    assert!(is_planar(&grid(["A   ", " #  ", "  # ", "   B"]), &eight));
+    assert!(!is_planar(&grid(["A   ", " #  ", "    ", "   B"]), &eight));
+  }
+
+  fn read_u16(bytes: &[u8], at: uint) -> uint {
+    bytes[at] as uint | bytes[at + 1] as uint << 8
+  }
+
+  fn read_u32(bytes: &[u8], at: uint) -> uint {
+    read_u16(bytes, at) | read_u16(bytes, at + 2) << 16
+  }
+
+  #[test]
+  fn crc32_matches_the_standard_check_value() {
+    assert_eq!(crc32("123456789".as_bytes()), 0xcbf43926);
+    assert_eq!(crc32([]), 0);
+  }
+
+  #[test]
+  fn a_zip_archive_lists_each_file_where_it_is_stored() {
+    let files = [(~"manifest.txt", ~"file\tid\tscore\n"), (~"solution-1.txt", ~"AXB\n"), (~"empty.txt", ~"")];
+    let zip = zip_archive(files.as_slice());
+    let bytes = zip.as_slice();
+    // the end record: how many files, and where the directory is
+    let end = bytes.len() - 22;
+    assert_eq!(read_u32(bytes, end), 0x06054b50);
+    assert_eq!((read_u16(bytes, end + 8), read_u16(bytes, end + 10)), (3, 3));
+    let mut entry = read_u32(bytes, end + 16);
+    assert_eq!(entry + read_u32(bytes, end + 12), end);
+    for &(ref name, ref text) in files.iter() {
+      assert_eq!(read_u32(bytes, entry), 0x02014b50);
+      let crc = read_u32(bytes, entry + 16);
+      assert_eq!(crc, crc32(text.as_bytes()) as uint);
+      let name_len = read_u16(bytes, entry + 28);
+      assert_eq!(bytes.slice(entry + 46, entry + 46 + name_len), name.as_bytes());
+      // the local header it points to says the same, and the data follows
+      let local = read_u32(bytes, entry + 42);
+      assert_eq!(read_u32(bytes, local), 0x04034b50);
+      assert_eq!((read_u32(bytes, local + 14), read_u32(bytes, local + 18)), (crc, text.len()));
+      assert_eq!(read_u16(bytes, local + 26), name_len);
+      let data = local + 30 + name_len;
+      assert_eq!(bytes.slice(data, data + text.len()), text.as_bytes());
+      entry += 46 + name_len;
+    }
+    assert_eq!(entry, end);
+    assert_eq!(zip_archive(files.as_slice()), zip);
+    assert_eq!(zip_archive([]).len(), 22);
+  }
+
+  // the fewest cells a search has to visit before it finds a path for `word`
+  fn nodes_to_first_path<'a>(opts: &SolveOptions, blank: &CrosswordGrid<'a>, word: &'a str, start: Point, end: Point) -> uint {
+    range(1u, 100000).find(|&cap| count_paths(opts, blank, word, start, end, cap).val0() > 0).unwrap()
+  }
+
+  #[test]
+  fn toward_dest_finds_a_path_in_fewer_nodes() {
+    // left, right, up, down wanders off to the left before it ever heads
+    // down to B; with the bound off, nothing cuts that short
+    let blank = grid(["  A  ", "     ", "     ", "     ", "     ", "  B  "]);
+    let (start, end) = (Point { x: 2, y: 0 }, Point { x: 2, y: 5 });
+    let mut opts = SolveOptions::new();
+    opts.bound = None;
+    let mut toward = opts.clone();
+    toward.toward_dest = true;
+    let plain_nodes = nodes_to_first_path(&opts, &blank, "axxxxxxb", start, end);
+    let toward_nodes = nodes_to_first_path(&toward, &blank, "axxxxxxb", start, end);
+    assert!(toward_nodes < plain_nodes, "{} nodes toward B, {} in order", toward_nodes, plain_nodes);
+    // an exhaustive search finds the same paths either way
+    assert_eq!(count_paths(&toward, &blank, "axxxxxxb", start, end, 1000000), count_paths(&opts, &blank, "axxxxxxb", start, end, 1000000));
+  }
+
+  #[test]
+  fn coordinates_off_the_grid_are_reported_with_what_they_were_for() {
+    let blank = grid(["A  ", "   ", "  B"]);
+    let mut opts = within_options("0,0;3,2").unwrap();
+    match check_coordinates(&blank, option_coordinates(&opts).as_slice()) {
+      Err(OutsideGrid(what, p, 3, 3)) => assert_eq!((what, p), (~"--within corner", Point { x: 3, y: 2 })),
+      other => fail!("expected the --within corner to be off the grid, got {:?}", other)
+    }
+    opts.within = None;
+    opts.fixed_paths.push((~"axyzb", vec!(Point { x: 0, y: 1 }, Point { x: 0, y: 3 })));
+    match check_coordinates(&blank, option_coordinates(&opts).as_slice()) {
+      Err(OutsideGrid(what, p, _, _)) => assert_eq!((what, p), (~"--fix-path cell of \"axyzb\"", Point { x: 0, y: 3 })),
+      other => fail!("expected the --fix-path cell to be off the grid, got {:?}", other)
+    }
+    let regions = vec!(parse_region("north=0,0:2,0").unwrap(), parse_region("south=0,2:2,4").unwrap());
+    match check_coordinates(&blank, region_coordinates(regions.as_slice()).as_slice()) {
+      Err(OutsideGrid(what, p, _, _)) => assert_eq!((what, p), (~"--region south corner", Point { x: 2, y: 4 })),
+      other => fail!("expected the south region to be off the grid, got {:?}", other)
+    }
+    assert!(check_coordinates(&blank, region_coordinates(regions.slice_to(1)).as_slice()).is_ok());
+  }
+
+  #[test]
+  fn words_with_no_inner_letter_in_common_have_no_crossings() {
+    let crossings = possible_crossings(["axmzb", "cpqrd", "cpmrd", "abc", "bcd", "eg"]);
+    assert_eq!(crossings.len(), 15);
+    assert_eq!(*crossings.get(&(~"axmzb", ~"cpqrd")), Vec::new());
+    assert_eq!(*crossings.get(&(~"axmzb", ~"cpmrd")), vec!('m'));
+    // b and c are in both, but only as waypoints, which no word crosses on
+    assert_eq!(*crossings.get(&(~"abc", ~"bcd")), Vec::new());
+    // a two-letter word has no cells at all
+    assert_eq!(*crossings.get(&(~"cpmrd", ~"eg")), Vec::new());
+    // and a blind entry's unknown letters could be anything
+    assert_eq!(*possible_crossings(["cpqrd", "a?b"]).get(&(~"a?b", ~"cpqrd")), vec!('?'));
+    // axmzb and cpqrd can't cross, so with five open cells for their six
+    // they can't both go on
+    let blank = grid(["A C", "   ", "D B"]);
+    let words = word_list(["axmzb", "cpqrd"]);
+    assert!(solved(&SolveOptions::new(), &blank, words.as_slice()).stuck_on.is_some());
+  }
+
+  #[test]
+  fn a_stuck_solve_is_not_reported_as_exhaustive() {
+    let blank = grid(["A C", "   ", "D B"]);
+    let words = word_list(["axmzb", "cpqrd"]);
+    let result = solved(&SolveOptions::new(), &blank, words.as_slice());
+    let stuck = result.stuck_on.unwrap();
+    assert!(result.completeness == Stuck(stuck));
+    assert_eq!(result.count_str(), format!("no solutions; stuck on '{}' after {} partial grids", stuck, result.solutions.len()));
+  }
+
+  #[test]
+  fn limits_that_never_fire_leave_the_search_exhaustive() {
+    let blank = grid(["A  ", "   ", "  B"]);
+    let words = word_list(["axyzb"]);
+    let mut opts = SolveOptions::new();
+    // the accumulator never gets past the six ways across
+    opts.beam = Some(7);
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 6);
+    assert!(result.completeness == Exhaustive, "{}", result.completeness);
+    let mut opts = SolveOptions::new();
+    opts.max_nodes = Some(100000);
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 6);
+    assert!(result.completeness == Exhaustive, "{}", result.completeness);
+    assert_eq!(result.count_str(), ~"6 solutions, exhaustive");
+  }
+
+  #[test]
+  fn a_region_of_interest_that_cuts_nothing_off_leaves_the_search_exhaustive() {
+    // with no room to spare, every cell outside the box is too far from B
+    // to be worth entering
+    let blank = grid(["     ", " A   ", "     ", "   B ", "     "]);
+    let mut opts = SolveOptions::new();
+    opts.roi_border = Some(0);
+    let words = word_list(["axyzb"]);
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 6);
+    assert!(result.completeness == Exhaustive, "{}", result.completeness);
+    // two letters to spare could go outside it, so there it does cut
+    let words = word_list(["axyzwvb"]);
+    let result = solved(&opts, &blank, words.as_slice());
+    assert!(result.completeness == TruncatedBy(RegionOfInterest, "axyzwvb"), "{}", result.completeness);
+  }
+
+  #[test]
+  fn a_prefilled_cell_no_word_claims_leaves_no_results_to_draw() {
+    // axb can only go across the top, never through the q
+    let blank = prefilled_grid(["A B", "q  "]);
+    let words = word_list(["axb"]);
+    let mut opts = SolveOptions::new();
+    opts.prefilled = true;
+    let result = solved(&opts, &blank, words.as_slice());
+    assert!(result.stuck_on.is_none());
+    assert_eq!(result.solutions.len(), 0);
+    let plan = plan_for([]).unwrap();
+    assert_eq!(render_results(&blank, &result.solutions, words.as_slice(), &opts, &plan, [], &mut Diagnostics::new()), ~"no results\n");
+  }
+
+  #[test]
+  fn symmetry_only_reflects_a_within_box_that_mirrors_onto_itself() {
+    let blank = grid([" A ", "   ", " B "]);
+    let (start, end) = (Point { x: 1, y: 0 }, Point { x: 1, y: 2 });
+    let mut opts = within_options("0,0;2,2").unwrap();
+    opts.symmetry = true;
+    assert!(symmetry_of(&opts, &blank, start, end).is_some());
+    let mut opts = within_options("1,0;2,2").unwrap();
+    opts.symmetry = true;
+    assert!(symmetry_of(&opts, &blank, start, end).is_none());
+    // the three paths that stay right of the axis, with or without --symmetry
+    let words = word_list(["axyzb"]);
+    let mut mirrored = texts(solved(&opts, &blank, words.as_slice()).solutions.as_slice());
+    opts.symmetry = false;
+    let mut plain = texts(solved(&opts, &blank, words.as_slice()).solutions.as_slice());
+    plain.sort();
+    mirrored.sort();
+    assert_eq!(plain.len(), 3);
+    assert_eq!(mirrored, plain);
+  }
+
+  #[test]
+  fn only_a_complete_set_is_carried_over_to_a_reload() {
+    let blank = grid(["A  ", "   ", "  B"]);
+    let words = word_list(["axyzb"]);
+    let result = solved(&SolveOptions::new(), &blank, words.as_slice());
+    let mut carried = carried_results(&result);
+    let mut expected = texts(result.solutions.as_slice());
+    carried.sort();
+    expected.sort();
+    assert_eq!(carried.len(), 6);
+    assert_eq!(carried, expected);
+    // a beam narrower than the six ways across keeps only some of them
+    let mut opts = SolveOptions::new();
+    opts.beam = Some(3);
+    let result = solved(&opts, &blank, words.as_slice());
+    assert!(result.completeness != Exhaustive && !result.solutions.is_empty());
+    assert!(carried_results(&result).is_empty());
+    let (blank, words) = (grid(["A C", "   ", "D B"]), word_list(["axmzb", "cpqrd"]));
+    let stuck = solved(&SolveOptions::new(), &blank, words.as_slice());
+    assert!(carried_results(&stuck).is_empty());
+  }
+
+  #[test]
+  fn a_word_can_only_cross_a_waypoint_with_through_waypoints() {
+    // C is the only cell between A and B
+    let blank = grid(["ACB"]);
+    let words = word_list(["acb"]);
+    assert!(solved(&SolveOptions::new(), &blank, words.as_slice()).stuck_on == Some("acb"));
+    let mut opts = SolveOptions::new();
+    opts.through_waypoints = true;
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 1);
+    assert!(*result.solutions.get(0).get_ref(Point { x: 1, y: 0 }).unwrap() == Through('c', "acb"));
+  }
+
+  #[test]
+  fn a_waypoint_passed_through_counts_against_its_capacity() {
+    // acb and dce both have to cross C
+    let blank = grid([" D ", "ACB", " E "]);
+    let (one, both) = (word_list(["acb"]), word_list(["acb", "dce"]));
+    let mut opts = SolveOptions::new();
+    opts.through_waypoints = true;
+    let mut capacity = blank.map(|_| 2u);
+    capacity.set(Point { x: 1, y: 1 }, 1);
+    opts.capacity = Some(capacity);
+    assert!(solved(&opts, &blank, one.as_slice()).stuck_on == Some("acb"));
+    // at 2, the waypoint and one word passing through fill it
+    opts.capacity = Some(blank.map(|_| 2u));
+    assert_eq!(solved(&opts, &blank, one.as_slice()).solutions.len(), 1);
+    let result = solved(&opts, &blank, both.as_slice());
+    assert!(result.stuck_on == Some("dce"));
+    assert_eq!(result.solutions.len(), 1);
+  }
+
+  #[test]
+  fn short_words_need_their_waypoints_a_word_length_apart() {
+    fn paths(rows: &[&str], word: &str) -> uint {
+      let blank = grid(rows);
+      let gridmap = hashgrid(blank.clone());
+      let (a, b) = (*gridmap.find(&'a').unwrap(), *gridmap.find(&'b').unwrap());
+      let (found, finished) = count_paths(&SolveOptions::new(), &blank, word, a, b, 1000);
+      assert!(finished);
+      found
+    }
+    let (adjacent, apart) = (["AB", "  "], ["A B", "   "]);
+    // two letters fill nothing, so A and B have to touch
+    assert_eq!(paths(adjacent.as_slice(), "ab"), 1);
+    assert_eq!(paths(apart.as_slice(), "ab"), 0);
+    // three letters put the middle one on the only cell between them
+    assert_eq!(paths(apart.as_slice(), "axb"), 1);
+    assert_eq!(paths(adjacent.as_slice(), "axb"), 0);
+    let (blank, words) = (grid(apart.as_slice()), word_list(["axb"]));
+    let result = solved(&SolveOptions::new(), &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 1);
+    assert!(*result.solutions.get(0).get_ref(Point { x: 1, y: 0 }).unwrap() == OneWord('x', "axb"));
+    let (blank, words) = (grid(adjacent.as_slice()), word_list(["ab"]));
+    let result = solved(&SolveOptions::new(), &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 1);
+    assert!(*result.solutions.get(0) == blank);
+  }
+
+  #[test]
+  fn the_most_constrained_word_is_the_one_with_fewest_paths() {
+    let blank = grid(["A  ", "   ", "  B", "C D"]);
+    let words = word_list(["axyzb", "cqd", "cpd"]);
+    let names: Vec<&str> = words.iter().map(|w| w.as_slice()).collect();
+    let endpoints = build_endpoint_map(names.as_slice(), &hashgrid(blank.clone())).ok().unwrap();
+    let mut counts = HashMap::new();
+    // cqd and cpd both have one path, so the earlier one wins
+    assert_eq!(most_constrained_word(&SolveOptions::new(), &blank, names.as_slice(), &endpoints, &mut counts), Some("cqd"));
+    assert_eq!(counts.len(), 3);
+    assert_eq!(*counts.get(&"axyzb"), 6);
+    assert_eq!(most_constrained_word(&SolveOptions::new(), &blank, [], &endpoints, &mut counts), None);
+  }
+
+  #[test]
+  fn every_order_an_iteration_plan_hands_out_finds_the_same_results() {
+    // axmzb and cpmrd have two ways to cross in the middle, and eghif has the
+    // six ways across its own square
+    let blank = grid(["A C#E  ", "   #   ", "D B#  F"]);
+    let words = word_list(["axmzb", "cpmrd", "eghif"]);
+    let opts = SolveOptions::new();
+    let paths = word_endpoints(&hashgrid(blank.clone()), words.as_slice()).ok().unwrap();
+    let wordpts: Vec<(&str, &(Point, Point))> = words.iter().map(|w| w.as_slice()).zip(paths.iter()).collect();
+    let known = brute_force(&blank, wordpts.as_slice(), &opts);
+    assert_eq!(known.len(), 12);
+    let mut plan = IterationPlan::new(3);
+    assert_eq!(plan.distinct_orders(), 6);
+    let mut orders: Vec<Vec<uint>> = Vec::new();
+    let mut union: Vec<~str> = Vec::new();
+    for _ in range(0, plan.distinct_orders()) {
+      let order = plan.next_order();
+      let mut sorted = order.clone();
+      sorted.sort();
+      assert_eq!(sorted, vec!(0u, 1, 2));
+      assert!(!orders.contains(&order), "{:?} came up twice", order);
+      let ordered: Vec<(&str, &(Point, Point))> = order.iter().map(|&i| *wordpts.get(i)).collect();
+      let mut result = solve(&opts, &mut Diagnostics::new(), &blank, ordered.as_slice());
+      let (capped, fresh) = plan.take(&mut result.solutions);
+      assert!(!capped);
+      let mut found = texts(result.solutions.as_slice());
+      for (text, &new) in found.iter().zip(fresh.iter()) {
+        assert_eq!(new, !union.contains(text));
+        if new {
+          union.push(text.clone());
+        }
+      }
+      found.sort();
+      assert_eq!(found, known);
+      orders.push(order);
+    }
+    // the first order finds them all, and none after it finds anything new
+    assert_eq!(plan.yields, vec!(12u, 0, 0, 0, 0, 0));
+    assert_eq!(plan.seen.len(), 12);
+    union.sort();
+    assert_eq!(union, known);
+  }
+
+  #[test]
+  fn the_constraint_table_puts_the_word_that_cut_the_most_first() {
+    // axmzb has four ways around C and D, all through the middle; cpmrd fits
+    // two of those four grids, one way each
+    let blank = grid(["A C", "   ", "D B"]);
+    let words = word_list(["axmzb", "cpmrd"]);
+    let opts = SolveOptions::new();
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 2);
+    let table = constraint_table(&result, &opts);
+    let rows: Vec<&str> = table.lines().collect();
+    assert_eq!(rows, vec!(
+      "word   out per in   fit on  per fitting  routes",
+      "cpmrd       0.500    50.0%         1.00       2",
+      "axmzb       4.000   100.0%         4.00       2"));
+  }
+
+  #[test]
+  fn a_certificate_verifies_until_any_part_of_it_is_edited() {
+    let blank = grid(["A C", "   ", "D B"]);
+    let words = word_list(["axmzb", "cpmrd"]);
+    let opts = SolveOptions::new();
+    let paths = word_endpoints(&hashgrid(blank.clone()), words.as_slice()).ok().unwrap();
+    let result = solved(&opts, &blank, words.as_slice());
+    assert_eq!(result.solutions.len(), 2);
+    let cert = certify(&blank, result.solutions.get(0), words.as_slice(), paths.as_slice(), &opts);
+    assert_eq!(verify_certificate(&cert, &blank, words.as_slice(), paths.as_slice(), &opts), Ok(solution_id(result.solutions.get(0))));
+    // the other solution's route for axmzb
+    let other = certify(&blank, result.solutions.get(1), words.as_slice(), paths.as_slice(), &opts);
+    let rejected = |edited: &Certificate, why: &str| match verify_certificate(edited, &blank, words.as_slice(), paths.as_slice(), &opts) {
+      Err(e) => assert!(e.contains(why), "{}", e),
+      Ok(id) => fail!("an edited certificate verified as {}", id)
+    };
+    let mut moved = cert.clone();
+    moved.words.get_mut(0).moves = other.words.get(0).moves.clone();
+    rejected(&moved, "has been changed");
+    let mut renamed = cert.clone();
+    renamed.words.get_mut(1).word = ~"cpqrd";
+    rejected(&renamed, "where the puzzle has");
+    let mut rehashed = cert.clone();
+    rehashed.hash = ~"00000000";
+    rejected(&rehashed, "final hash");
+    // even with every hash in its chain right, the moves have to make the
+    // solution it names
+    let mut forged = other.clone();
+    forged.solution = cert.solution.clone();
+    rejected(&forged, "the words make solution");
   }
 }